@@ -9,6 +9,29 @@
 //! 3. KiCad API must be enabled
 //!
 //! Run with: `cargo run --example real_kicad_ecs`
+//!
+//! With `KICAD_ECS_SNIPPETS=<dir>`, the pcbnew selection is saved as the snippet `selection` in
+//! that snippet library before violations are selected.
+//!
+//! `KICAD_ECS_ACTIONS` takes a comma-separated list of pcbnew action names, such as
+//! `pcbnew.ZoneFiller.zoneFillAll`, to run before the board is loaded.
+//!
+//! Set `KICAD_ECS_DOCUMENT=1` to load the whole board with one `SaveDocumentToString` request,
+//! parsed like a `.kicad_pcb` file, instead of fetching each item type.
+//!
+//! Items involved in design rule violations are selected in pcbnew. Set `KICAD_ECS_MARKERS=1`
+//! to also draw a marker for each violation on User.9, replacing the markers of the previous run.
+//! `KICAD_ECS_VIEW=<preset>` then switches pcbnew to a view preset, `front` or `back` or one
+//! defined in the TOML file given by `KICAD_ECS_VIEWS`.
+//! With `KICAD_ECS_SAVE_COPY=<file.kicad_pcb>`, the marked-up board is then saved to that file,
+//! leaving the open board's own file untouched.
+//!
+//! Set `KICAD_ECS_BOM=<dir>` to write the bill of materials there as `bom.csv`, `bom.json` and
+//! `bom.xlsx` (an Excel workbook), and `KICAD_ECS_CPL=<dir>` to write JLCPCB placement
+//! files `cpl-top.csv` and `cpl-bottom.csv` measured from the lower left of the board outline.
+//! With `KICAD_ECS_VARIANTS=<file.toml>`, the part count of each assembly variant is listed too.
+//! `KICAD_ECS_SNAPSHOT=<file>` saves the analyzed board there (as JSON for a `.json` file) for
+//! later offline use with `PcbWorld::load`.
 
 use kicad_ecs::prelude::*;
 use prettytable::{Table, row, format, Cell};
@@ -67,6 +90,10 @@ impl KiCadSupervisor {
     async fn load_open_board(&mut self) -> Result<(), String> {
         info!("Loading open board from KiCad");
         
+        if std::env::var("KICAD_ECS_DOCUMENT").is_ok_and(|v| v == "1") {
+            return self.load_board_document().await;
+        }
+        
         if let Some(client) = &mut self.client {
             // Get the open board
             match client.get_board().await {
@@ -78,6 +105,12 @@ impl KiCadSupervisor {
                         println!("📁 Project: {}", project);
                     }
 
+                    // Pick up any layers renamed in the stackup
+                    match client.get_layer_names().await {
+                        Ok(names) => self.pcb_world.set_layer_names(names),
+                        Err(e) => warn!("Failed to get layer names: {}", e),
+                    }
+
                     // Get footprints from the board
                     match client.get_footprints().await {
                         Ok(footprints) => {
@@ -89,8 +122,60 @@ impl KiCadSupervisor {
                                 return Err("No components found on the open board".to_string());
                             }
 
+                            let pad_ids: Vec<String> = footprints.iter()
+                                .flat_map(|fp| fp.pads.iter().map(|pad| pad.id.clone()))
+                                .collect();
+
+                            // Outer copper pad outlines give footprints real shapes
+                            let mut pad_shapes = std::collections::HashMap::new();
+                            for layer in [PcbLayer::FCu, PcbLayer::BCu] {
+                                match client.get_pad_shapes(&pad_ids, layer).await {
+                                    Ok(shapes) => pad_shapes.extend(shapes.into_iter().map(|(id, shape)| ((id, layer), shape))),
+                                    Err(e) => warn!("Failed to get pad shapes on {}: {}", layer, e),
+                                }
+                            }
+
+                            let board_shapes = client.get_board_shapes().await.unwrap_or_else(|e| {
+                                warn!("Failed to get board shapes: {}", e);
+                                Vec::new()
+                            });
+                            let tracks = client.get_tracks().await.unwrap_or_else(|e| {
+                                warn!("Failed to get tracks: {}", e);
+                                Vec::new()
+                            });
+                            let vias = client.get_vias().await.unwrap_or_else(|e| {
+                                warn!("Failed to get vias: {}", e);
+                                Vec::new()
+                            });
+                            let zones = client.get_zones().await.unwrap_or_else(|e| {
+                                warn!("Failed to get zones: {}", e);
+                                Vec::new()
+                            });
+
+                            // Netclass clearances and widths drive the design rule checks
+                            let net_names: Vec<String> = tracks.iter().filter_map(|t| t.net.clone())
+                                .chain(vias.iter().filter_map(|v| v.net.clone()))
+                                .chain(footprints.iter().flat_map(|fp| fp.pads.iter().filter_map(|pad| pad.net.clone())))
+                                .collect::<std::collections::BTreeSet<_>>()
+                                .into_iter()
+                                .collect();
+                            match (client.get_net_classes().await, client.get_net_class_for_nets(&net_names).await) {
+                                (Ok(classes), Ok(by_net)) => self.pcb_world.set_net_classes(classes, by_net),
+                                (Err(e), _) | (_, Err(e)) => warn!("Failed to get netclasses, using defaults: {}", e),
+                            }
+
                             // Load into ECS
                             self.load_footprints_into_ecs(footprints)?;
+                            self.pcb_world.apply_pad_shapes(&pad_shapes);
+                            self.pcb_world.spawn_tracks(tracks);
+                            self.pcb_world.spawn_vias(vias);
+                            self.pcb_world.spawn_zones(zones);
+                            self.pcb_world.spawn_silkscreen(&board_shapes);
+
+                            let outline = self.pcb_world.build_board_outline(&board_shapes);
+                            for issue in &outline.issues {
+                                warn!("Board outline: {}", issue);
+                            }
                             Ok(())
                         }
                         Err(e) => {
@@ -109,74 +194,54 @@ impl KiCadSupervisor {
         }
     }
 
+    /// Load the open board from a single document request
+    async fn load_board_document(&mut self) -> Result<(), String> {
+        let Some(client) = &mut self.client else {
+            return Err("KiCad client not initialized".to_string());
+        };
+        let mut pcb_world = PcbWorld::from_kicad_document(client).await
+            .map_err(|e| format!("Failed to load board document: {:?}", e))?;
+        println!("🔍 Found {} components", pcb_world.component_count());
+        
+        // Netclasses live in the project, not the board document
+        let net_names: Vec<String> = pcb_world.world.query::<&Net>()
+            .iter(&pcb_world.world)
+            .map(|net| net.name.clone())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        match (client.get_net_classes().await, client.get_net_class_for_nets(&net_names).await) {
+            (Ok(classes), Ok(by_net)) => pcb_world.set_net_classes(classes, by_net),
+            (Err(e), _) | (_, Err(e)) => warn!("Failed to get netclasses, using defaults: {}", e),
+        }
+        
+        self.pcb_world = pcb_world;
+        self.classify_components()
+    }
+
     /// Load footprint data into ECS world
     fn load_footprints_into_ecs(&mut self, footprints: Vec<kicad_ecs::client::FootprintData>) -> Result<(), String> {
 
-        let mut _mounting_holes = 0;
-
+        // Mounting holes are detected from pad drill data while spawning
         for fp in footprints {
-            // Check if it's a mounting hole
-            if self.is_mounting_hole(&fp) {
-                _mounting_holes += 1;
-                self.pcb_world.spawn_mounting_hole(
-                    fp.id,
-                    fp.reference,
-                    (fp.position.0, fp.position.1, fp.rotation),
-                    fp.layer,
-                    3.2, // Default diameter - could parse from footprint name
-                    "M3".to_string(), // Default screw size
-                );
-            } else {
-                // Smart spawning based on component reference prefix
-                match fp.reference.chars().next() {
-                    Some('R') => {
-                        self.pcb_world.spawn_resistor(
-                            fp.id, fp.reference, fp.value, fp.footprint_name,
-                            (fp.position.0, fp.position.1, fp.rotation), fp.layer
-                        );
-                    },
-                    Some('C') => {
-                        self.pcb_world.spawn_capacitor(
-                            fp.id, fp.reference, fp.value, fp.footprint_name,
-                            (fp.position.0, fp.position.1, fp.rotation), fp.layer
-                        );
-                    },
-                    Some('U') => {
-                        self.pcb_world.spawn_ic(
-                            fp.id, fp.reference, fp.value, fp.footprint_name,
-                            (fp.position.0, fp.position.1, fp.rotation), fp.layer
-                        );
-                    },
-                    Some('J') => {
-                        self.pcb_world.spawn_connector(
-                            fp.id, fp.reference, fp.value, fp.footprint_name,
-                            (fp.position.0, fp.position.1, fp.rotation), fp.layer
-                        );
-                    },
-                    _ => {
-                        // Generic footprint for unknown component types
-                        self.pcb_world.spawn_footprint(fp);
-                    },
-                }
-            }
+            self.pcb_world.spawn_footprint(fp);
         }
 
-        
-        Ok(())
+        self.classify_components()
     }
 
-    /// Check if a footprint is a mounting hole
-    fn is_mounting_hole(&self, fp: &kicad_ecs::client::FootprintData) -> bool {
-        let ref_name = &fp.reference;
-        let value = &fp.value;
-        let footprint = &fp.footprint_name;
-        
-        ref_name.starts_with("H") || 
-        ref_name.starts_with("MH") ||
-        ref_name.contains("MountingHole") ||
-        value.contains("MountingHole") ||
-        footprint.contains("MountingHole") ||
-        footprint.contains("Hole_")
+    /// Classify components and parse their values
+    fn classify_components(&mut self) -> Result<(), String> {
+        // Classify components with project rules when present, else by reference prefix
+        let registry = if std::path::Path::new("classification.toml").exists() {
+            ClassificationRegistry::from_file("classification.toml").map_err(|e| e.to_string())?
+        } else {
+            ClassificationRegistry::with_default_rules()
+        };
+        self.pcb_world.classify(&registry);
+        self.pcb_world.parse_values();
+
+        Ok(())
     }
 
     /// Perform ECS-based analysis
@@ -193,6 +258,9 @@ impl KiCadSupervisor {
         summary_table.add_row(row!["Back Layer (B.Cu)", stats.back_components]);
         summary_table.add_row(row!["Other Layers", stats.other_layer_components]);
         summary_table.add_row(row!["Mounting Holes", stats.mounting_holes]);
+        if let Some((width, height)) = self.pcb_world.board_outline().and_then(|outline| outline.size()) {
+            summary_table.add_row(row!["Board Size", format!("{:.2} x {:.2}", width, height)]);
+        }
         
         if stats.dnp_components > 0 {
             summary_table.add_row(row!["Do Not Populate (DNP)", stats.dnp_components]);
@@ -210,29 +278,252 @@ impl KiCadSupervisor {
         // Component breakdown by type
         self.analyze_component_types();
         
+        // Capacitance per supply rail
+        self.analyze_capacitance();
+        
+        // Design rule checks
+        self.run_design_rule_checks();
+        self.analyze_decoupling();
+        
+        // Bill of materials
+        self.export_bom();
+        self.export_placement();
+        self.save_snapshot();
+        
         // Detailed component table
         self.print_components();
     }
 
+    /// Run the pcbnew actions listed in `KICAD_ECS_ACTIONS`
+    async fn run_actions(&mut self) {
+        let Ok(names) = std::env::var("KICAD_ECS_ACTIONS") else {
+            return;
+        };
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        
+        for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let action = PcbAction::from_name(name);
+            match client.run_action(&action).await {
+                Ok(ActionStatus::Ok) => println!("▶️  Ran {}", action),
+                Ok(status) => println!("⚠️  {} not run: {}", action, status),
+                Err(e) => warn!("Failed to run {}: {}", action, e),
+            }
+        }
+    }
+
+    /// Save the pcbnew selection as the snippet `selection` in the `KICAD_ECS_SNIPPETS` library
+    async fn capture_snippet(&mut self) {
+        let Ok(dir) = std::env::var("KICAD_ECS_SNIPPETS") else {
+            return;
+        };
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        
+        let result = async {
+            let contents = client.save_selection_to_string().await?;
+            let mut library = if std::path::Path::new(&dir).exists() {
+                SnippetLibrary::from_dir(&dir)?
+            } else {
+                SnippetLibrary::default()
+            };
+            let snippet = Snippet::new("selection", &contents)?;
+            println!("✂️  Saved selection snippet using nets {}", snippet.nets.join(", "));
+            library.insert(snippet);
+            library.save_to_dir(&dir)
+        }.await;
+        if let Err(e) = result {
+            warn!("Failed to save the selection as a snippet: {:?}", e);
+        }
+    }
+
+    /// Select the items involved in violations in KiCad and refresh the violation markers
+    async fn report_violations(&mut self) {
+        let Some(client) = &mut self.client else {
+            return;
+        };
+        
+        let mut query = self.pcb_world.world.query::<&Violation>();
+        let violations: Vec<Violation> = query.iter(&self.pcb_world.world).cloned().collect();
+        let items: Vec<String> = violations.iter().flat_map(|v| v.items.iter().cloned()).collect();
+        match client.select_items(&items).await {
+            Ok(count) if count > 0 => println!("🎯 Selected {} items with violations in KiCad", count),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to select violations: {}", e),
+        }
+        
+        if std::env::var("KICAD_ECS_MARKERS").is_ok_and(|v| v == "1") {
+            match client.replace_violation_markers(&violations, &kicad_ecs::drc::MarkerStyle::default()).await {
+                Ok((removed, created)) => println!("📍 Replaced {} marker items with {}", removed, created),
+                Err(e) => warn!("Failed to update violation markers: {}", e),
+            }
+        }
+        
+        if let Ok(name) = std::env::var("KICAD_ECS_VIEW") {
+            let result = async {
+                let presets = match std::env::var("KICAD_ECS_VIEWS") {
+                    Ok(path) => ViewPresets::from_file(path)?,
+                    Err(_) => ViewPresets::new(),
+                };
+                client.apply_view_preset(presets.get(&name)?).await
+            }.await;
+            match result {
+                Ok(_) => println!("👁️  Switched pcbnew to the {} view", name),
+                Err(e) => warn!("Failed to apply view preset {}: {:?}", name, e),
+            }
+        }
+        
+        if let Ok(path) = std::env::var("KICAD_ECS_SAVE_COPY") {
+            let options = SaveCopyOptions { overwrite: true, include_project: false };
+            match client.save_copy_of_document(&path, &options).await {
+                Ok(()) => println!("💾 Saved a copy of the board to {}", path),
+                Err(e) => warn!("Failed to save a copy of the board: {:?}", e),
+            }
+        }
+    }
+
+    /// Run the DRC systems and list what they found
+    fn run_design_rule_checks(&mut self) {
+        let violations = self.pcb_world.run_drc();
+        if violations.is_empty() {
+            println!("\n✅ No design rule violations");
+            return;
+        }
+        
+        let mut drc_table = Table::new();
+        drc_table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        drc_table.add_row(row![b->"Severity", b->"Rule", b->"Location", b->"Details"]);
+        for violation in violations.iter().take(25) {
+            drc_table.add_row(row![violation.severity, violation.rule, violation.location, violation.message]);
+        }
+        
+        println!("\n🚦 Design Rule Violations ({}):", violations.len());
+        drc_table.printstd();
+        if violations.len() > 25 {
+            println!("   ... and {} more", violations.len() - 25);
+        }
+    }
+
+    /// Show the nearest decoupling capacitor of each IC power pin, found by the DRC run
+    fn analyze_decoupling(&mut self) {
+        let mut query = self.pcb_world.world.query::<(&ComponentInfo, &Decoupling)>();
+        let mut rows: Vec<_> = query.iter(&self.pcb_world.world)
+            .flat_map(|(info, decoupling)| decoupling.pins.iter().map(move |pin| (info.reference.clone(), pin)))
+            .collect();
+        if rows.is_empty() {
+            return;
+        }
+        rows.sort_by(|a, b| (&a.0, &a.1.pad).cmp(&(&b.0, &b.1.pad)));
+        
+        let mut decoupling_table = Table::new();
+        decoupling_table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        decoupling_table.add_row(row![b->"IC", b->"Pin", b->"Rail", b->"Capacitor", b->"Distance", b->"Vias"]);
+        for (reference, pin) in rows {
+            match pin.nearest() {
+                Some(capacitor) => decoupling_table.add_row(row![
+                    reference, pin.pad, pin.net, capacitor.reference, capacitor.distance,
+                    capacitor.vias.map(|vias| vias.to_string()).unwrap_or_else(|| "unrouted".to_string())
+                ]),
+                None => decoupling_table.add_row(row![reference, pin.pad, pin.net, "-", "-", "-"]),
+            };
+        }
+        
+        println!("\n🔋 Decoupling per Power Pin:");
+        decoupling_table.printstd();
+    }
+
+    /// Summarize the BOM and write it to `KICAD_ECS_BOM` in every export format
+    fn export_bom(&mut self) {
+        let bom = self.pcb_world.bom(&BomOptions::default());
+        println!("\n📋 Bill of Materials: {} lines, {} parts", bom.lines.len(), bom.part_count());
+        
+        if let Ok(path) = std::env::var("KICAD_ECS_VARIANTS") {
+            self.summarize_variants(&path);
+        }
+        
+        let Ok(dir) = std::env::var("KICAD_ECS_BOM") else {
+            return;
+        };
+        for format in [BomFormat::Csv, BomFormat::Json, BomFormat::Xlsx] {
+            let path = std::path::Path::new(&dir).join(format!("bom.{}", format.extension()));
+            match bom.write(&path, format) {
+                Ok(()) => println!("   Wrote {}", path.display()),
+                Err(e) => warn!("Failed to write BOM: {:?}", e),
+            }
+        }
+    }
+
+    /// Load assembly variants and show the BOM size of each, then go back to the board as drawn
+    fn summarize_variants(&mut self, path: &str) {
+        let loaded = VariantOverrides::from_file(path)
+            .and_then(|variants| self.pcb_world.set_variants(variants));
+        if let Err(e) = loaded {
+            warn!("Failed to load variants: {:?}", e);
+            return;
+        }
+        for name in self.pcb_world.variant_names() {
+            if self.pcb_world.select_variant(Some(&name)).is_ok() {
+                let bom = self.pcb_world.bom(&BomOptions::default());
+                println!("   Variant {}: {} lines, {} parts", name, bom.lines.len(), bom.part_count());
+            }
+        }
+        let _ = self.pcb_world.select_variant(None);
+    }
+
+    /// Write the pick-and-place files to `KICAD_ECS_CPL`
+    fn export_placement(&mut self) {
+        let Ok(dir) = std::env::var("KICAD_ECS_CPL") else {
+            return;
+        };
+        let options = CplOptions { origin: CplOrigin::OutlineLowerLeft, ..Default::default() };
+        let written = self.pcb_world.cpl(&options)
+            .and_then(|cpl| cpl.write_sides(&dir, "cpl", CplFormat::Jlcpcb));
+        match written {
+            Ok(paths) => paths.iter().for_each(|path| println!("   Wrote {}", path.display())),
+            Err(e) => warn!("Failed to write placement files: {:?}", e),
+        }
+    }
+
+    /// Save the world, analysis results included, to `KICAD_ECS_SNAPSHOT`
+    fn save_snapshot(&mut self) {
+        let Ok(path) = std::env::var("KICAD_ECS_SNAPSHOT") else {
+            return;
+        };
+        match self.pcb_world.save(&path) {
+            Ok(()) => println!("   Saved snapshot to {}", path),
+            Err(e) => warn!("Failed to save snapshot: {:?}", e),
+        }
+    }
+
+    /// Sum parsed capacitance per non-ground net
+    fn analyze_capacitance(&mut self) {
+        let totals = kicad_ecs::values::capacitance_per_net(&mut self.pcb_world.world);
+        if totals.is_empty() {
+            return;
+        }
+        
+        let mut cap_table = Table::new();
+        cap_table.set_format(*format::consts::FORMAT_BOX_CHARS);
+        cap_table.add_row(row![b->"Net", b->"Total Capacitance"]);
+        for (net, farads) in totals {
+            cap_table.add_row(row![net, kicad_ecs::values::format_si(farads, "F")]);
+        }
+        
+        println!("\n⚡ Capacitance per Rail:");
+        cap_table.printstd();
+    }
+
     /// Analyze component types using ECS queries
     fn analyze_component_types(&mut self) {
         let mut type_counts = std::collections::HashMap::new();
         
-        let mut query = self.pcb_world.world.query::<&ComponentInfo>();
-        for info in query.iter(&self.pcb_world.world) {
-            let comp_type = match info.reference.chars().next() {
-                Some('U') => "ICs",
-                Some('R') => "Resistors",
-                Some('C') => "Capacitors", 
-                Some('L') => "Inductors",
-                Some('D') => "Diodes/LEDs",
-                Some('Q') => "Transistors",
-                Some('Y') => "Crystals/Oscillators",
-                Some('J') => "Connectors",
-                Some('S') if info.reference.starts_with("SW") => "Switches",
-                Some('T') if info.reference.starts_with("TP") => "Test Points",
-                _ => "Other",
-            };
+        let mut query = self.pcb_world.world.query_filtered::<Option<&Classification>, With<ComponentInfo>>();
+        for classification in query.iter(&self.pcb_world.world) {
+            let comp_type = classification
+                .map(|c| c.class.clone())
+                .unwrap_or_else(|| "Other".to_string());
             *type_counts.entry(comp_type).or_insert(0) += 1;
         }
 
@@ -262,7 +553,7 @@ impl KiCadSupervisor {
         let mut query = self.pcb_world.world.query::<(&ComponentInfo, &Position, &Layer, &ComponentDescription)>();
         for (info, pos, layer, desc) in query.iter(&self.pcb_world.world) {
             
-            let layer_name = &layer.layer_name;
+            let layer_name = self.pcb_world.layer_name(layer.id);
             
             // Create a formatted description that wraps at word boundaries
             let desc_wrapped = if desc.description.len() > 12 {
@@ -318,9 +609,9 @@ impl KiCadSupervisor {
                 value_short,
                 footprint_short,
                 desc_cell,
-                format!("{:.1}", pos.x),
-                format!("{:.1}", pos.y),
-                format!("{:.0}", pos.rotation),
+                format!("{:.1}", pos.x.mm()),
+                format!("{:.1}", pos.y.mm()),
+                format!("{:.0}", pos.rotation.degrees()),
                 layer_name
             ]);
         }
@@ -354,6 +645,7 @@ impl KiCadSupervisor {
                 self.state = ConnectionState::LoadingBoard;
             }
             ConnectionState::LoadingBoard => {
+                self.run_actions().await;
                 println!("📥 Loading board data...");
                 match self.load_open_board().await {
                     Ok(_) => {
@@ -368,6 +660,8 @@ impl KiCadSupervisor {
             ConnectionState::AnalyzingBoard => {
                 println!("🔍 Analyzing board with ECS...");
                 self.perform_analysis();
+                self.capture_snippet().await;
+                self.report_violations().await;
                 println!("\n✅ Analysis complete!");
                 self.state = ConnectionState::Terminated;
             }
@@ -116,15 +116,16 @@ fn create_demo_components(world: &mut PcbWorld) {
                 footprint_name: footprint.to_string(),
             },
             Position { 
-                x: (i as f64) * 10.0, 
-                y: (i as f64) * 5.0, 
-                rotation: 0.0 
+                x: kicad_ecs::units::Length::from_mm((i as f64) * 10.0), 
+                y: kicad_ecs::units::Length::from_mm((i as f64) * 5.0), 
+                rotation: kicad_ecs::units::Angle::ZERO 
             },
-            Layer { layer_name: "F.Cu".to_string() },
+            Layer { id: kicad_ecs::layers::PcbLayer::FCu },
             ComponentFlags {
                 exclude_from_bom: false,
                 do_not_populate: false,
                 locked: false,
+                ..Default::default()
             },
         ));
     }
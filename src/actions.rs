@@ -0,0 +1,117 @@
+//! Editor actions
+//!
+//! pcbnew's menu and hotkey commands are tool actions with dotted names such as
+//! `pcbnew.ZoneFiller.zoneFillAll`. `KiCadClient::run_action` submits one by name. KiCad treats
+//! these names as internal, so they may change between versions; `PcbAction` names the ones
+//! automation scripts commonly need, and `PcbAction::Custom` passes any other name through.
+//!
+//! The names are those of KiCad 9.0 (`common/tool/actions.cpp` for `common.*`,
+//! `pcbnew/tools/pcb_actions.cpp` for `pcbnew.*`), the first release with the IPC API. Actions
+//! shared by all editors live under `common.`, e.g. update from schematic and clearing the
+//! selection, which were `pcbnew.` actions before KiCad 8.
+
+use std::fmt;
+
+/// A pcbnew tool action
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PcbAction {
+    /// Open the Design Rules Checker dialog
+    RunDrc,
+    /// Zoom to fit the board in the view
+    ZoomFitScreen,
+    /// Update the board from the schematic, as Tools > Update PCB from Schematic does
+    UpdateFromSchematic,
+    /// Fill every zone on the board
+    FillAllZones,
+    /// Remove the fill of every zone on the board
+    UnfillAllZones,
+    /// Clear the editor selection
+    ClearSelection,
+    /// Any other action, by full name
+    Custom(String),
+}
+
+impl PcbAction {
+    /// The actions with a fixed name
+    pub const KNOWN: [PcbAction; 6] = [
+        PcbAction::RunDrc,
+        PcbAction::ZoomFitScreen,
+        PcbAction::UpdateFromSchematic,
+        PcbAction::FillAllZones,
+        PcbAction::UnfillAllZones,
+        PcbAction::ClearSelection,
+    ];
+
+    /// The action name KiCad knows it by
+    pub fn name(&self) -> &str {
+        match self {
+            PcbAction::RunDrc => "pcbnew.DRCTool.runDRC",
+            PcbAction::ZoomFitScreen => "common.Control.zoomFitScreen",
+            PcbAction::UpdateFromSchematic => "common.Control.updatePcbFromSchematic",
+            PcbAction::FillAllZones => "pcbnew.ZoneFiller.zoneFillAll",
+            PcbAction::UnfillAllZones => "pcbnew.ZoneFiller.zoneUnfillAll",
+            PcbAction::ClearSelection => "common.InteractiveSelection.ClearSelection",
+            PcbAction::Custom(name) => name,
+        }
+    }
+
+    /// The action for a name, falling back to `Custom` for names without a variant
+    pub fn from_name(name: &str) -> Self {
+        Self::KNOWN.into_iter()
+            .find(|action| action.name() == name)
+            .unwrap_or_else(|| PcbAction::Custom(name.to_string()))
+    }
+}
+
+impl fmt::Display for PcbAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Outcome of submitting an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActionStatus {
+    /// The action was submitted; it runs asynchronously in the editor
+    Ok,
+    /// The editor does not know the action
+    Invalid,
+    /// The editor the action targets is not open
+    FrameNotOpen,
+    /// KiCad reported no status
+    Unknown,
+}
+
+impl ActionStatus {
+    /// Whether the action was submitted
+    pub fn is_ok(self) -> bool {
+        self == ActionStatus::Ok
+    }
+}
+
+impl fmt::Display for ActionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ActionStatus::Ok => "ok",
+            ActionStatus::Invalid => "unknown action",
+            ActionStatus::FrameNotOpen => "editor not open",
+            ActionStatus::Unknown => "no status",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn action_names_round_trip() {
+        for action in PcbAction::KNOWN {
+            assert_eq!(PcbAction::from_name(action.name()), action);
+        }
+        assert_eq!(PcbAction::UpdateFromSchematic.name(), "common.Control.updatePcbFromSchematic");
+        let custom = PcbAction::from_name("pcbnew.Control.toggleRatsnest");
+        assert_eq!(custom, PcbAction::Custom("pcbnew.Control.toggleRatsnest".to_string()));
+        assert_eq!(custom.to_string(), "pcbnew.Control.toggleRatsnest");
+    }
+}
@@ -0,0 +1,727 @@
+//! Reading boards from KiCad's `.kicad_pcb` format
+//!
+//! `BoardFile` parses the S-expression text of a board, whether read from disk or handed back
+//! by KiCad through `KiCadClient::save_document_to_string`, into the same `FootprintData`,
+//! `TrackData`, `ViaData`, `ZoneData` and `BoardShapeData` the live client produces, so both
+//! loaders populate a world through the same `PcbWorld` spawn functions.
+//!
+//! The file has no pad polygons, so pad copper is built from each pad's shape, size and, for
+//! trapezoids, `rect_delta`; custom pads join their anchor with their primitives. Like KiCad's
+//! pad polygons, the copper is given on every copper layer in the pad's layer list, with `*.Cu`
+//! standing for all of the board's copper layers. Netclasses are project settings and are not
+//! part of the board.
+
+use eyre::{eyre, Result, WrapErr};
+use geo::Area;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::{debug, info, instrument};
+use crate::client::{BoardShapeData, FootprintData, NetData, TrackData, ViaData, ZoneData};
+use crate::components::{DesignRuleOverrides, Model3D, MountingStyle, Pad, PadKind, Position, ViaType, ZoneConnection, ZoneKind};
+use crate::geometry::{Graphic, GraphicShape, PolyLine, PolyLineNode, PolySet, PolygonWithHoles, Tessellation};
+use crate::layers::{LayerNames, PcbLayer};
+use crate::sexpr::SExpr;
+use crate::units::{Angle, Length, Point};
+
+/// The contents of a `.kicad_pcb` board
+#[derive(Debug, Clone, Default)]
+pub struct BoardFile {
+    pub layer_names: LayerNames,
+    pub nets: Vec<NetData>,
+    pub footprints: Vec<FootprintData>,
+    /// Copper pad outlines in board coordinates, keyed by pad UUID and layer
+    pub pad_shapes: HashMap<(String, PcbLayer), PolygonWithHoles>,
+    pub shapes: Vec<BoardShapeData>,
+    pub tracks: Vec<TrackData>,
+    pub vias: Vec<ViaData>,
+    pub zones: Vec<ZoneData>,
+    /// The drill/place (auxiliary axis) origin; KiCad leaves it out of the file when at (0, 0)
+    pub aux_origin: Point,
+}
+
+impl BoardFile {
+    /// Parse the text of a `.kicad_pcb` file
+    #[instrument(skip(text), fields(bytes = text.len()))]
+    pub fn parse(text: &str) -> Result<Self> {
+        let root = SExpr::parse(text)?;
+        if !root.is("kicad_pcb") {
+            return Err(eyre!("Not a KiCad board: expected kicad_pcb, found {:?}", root.name()));
+        }
+
+        let mut board = BoardFile::default();
+        let mut copper_layers = Vec::new();
+        for entry in root.child("layers").map(SExpr::args).unwrap_or_default() {
+            // (0 "F.Cu" signal ["Top"])
+            let Some(layer) = entry.name().and(entry.atom(0)).and_then(PcbLayer::from_name) else {
+                continue;
+            };
+            if layer.is_copper() {
+                copper_layers.push(layer);
+            }
+            if let Some(user_name) = entry.atom(2).filter(|name| *name != layer.canonical_name()) {
+                board.layer_names.names.insert(layer, user_name.to_string());
+            }
+        }
+
+        if let Some(origin) = root.child("setup").and_then(|setup| point(setup, "aux_axis_origin")) {
+            board.aux_origin = origin;
+        }
+
+        let mut nets = Nets::default();
+        for net in root.children("net") {
+            let (Some(code), Some(name)) = (net.atom(0), net.atom(1)) else {
+                continue;
+            };
+            if let Ok(number) = code.parse() {
+                nets.by_code.insert(code.to_string(), name.to_string());
+                board.nets.push(NetData { code: number, name: name.to_string() });
+            }
+        }
+
+        for item in root.args() {
+            match item.name() {
+                Some("footprint" | "module") => {
+                    board.footprints.push(footprint(item, &nets, &copper_layers, &mut board.pad_shapes));
+                }
+                Some(name) if name.starts_with("gr_") => {
+                    if let Some(graphic) = graphic(item) {
+                        board.shapes.push(BoardShapeData {
+                            id: uuid(item),
+                            layer: layer(item),
+                            graphic,
+                            net: nets.name(item),
+                        });
+                    }
+                }
+                Some(name @ ("segment" | "arc")) => board.tracks.push(TrackData {
+                    id: uuid(item),
+                    layer: layer(item),
+                    start: point(item, "start").unwrap_or_default(),
+                    mid: (name == "arc").then(|| point(item, "mid")).flatten(),
+                    end: point(item, "end").unwrap_or_default(),
+                    width: length(item, "width").unwrap_or(Length::ZERO),
+                    net: nets.name(item),
+                }),
+                Some("via") => board.vias.push(via(item, &nets)),
+                Some("zone") => {
+                    if let Some(zone) = zone(item, &nets, &copper_layers) {
+                        board.zones.push(zone);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        info!(
+            "Parsed board with {} footprints, {} tracks, {} vias, {} zones and {} shapes",
+            board.footprints.len(), board.tracks.len(), board.vias.len(), board.zones.len(), board.shapes.len()
+        );
+        Ok(board)
+    }
+
+    /// Read and parse a `.kicad_pcb` file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read board {}", path.display()))?;
+        Self::parse(&text).wrap_err_with(|| format!("Failed to parse board {}", path.display()))
+    }
+}
+
+/// Net names by code. Items refer to nets as `(net 3)`, pads as `(net 3 "GND")`, and newer
+/// files by name alone as `(net "GND")`.
+#[derive(Default)]
+struct Nets {
+    by_code: HashMap<String, String>,
+}
+
+impl Nets {
+    fn name(&self, item: &SExpr) -> Option<String> {
+        let net = item.child("net")?;
+        let name = match net.atom(1) {
+            Some(name) => name,
+            None => {
+                let code = net.atom(0)?;
+                self.by_code.get(code).map(String::as_str).unwrap_or(code)
+            }
+        };
+        (!name.is_empty()).then(|| name.to_string())
+    }
+}
+
+// ===== Primitives =====
+
+fn uuid(item: &SExpr) -> String {
+    item.value("uuid").or_else(|| item.value("tstamp")).unwrap_or_default().to_string()
+}
+
+fn layer(item: &SExpr) -> PcbLayer {
+    item.value("layer").and_then(PcbLayer::from_name).unwrap_or(PcbLayer::Undefined)
+}
+
+fn mm(value: f64) -> Length {
+    Length::from_mm(value)
+}
+
+fn length(item: &SExpr, name: &str) -> Option<Length> {
+    item.child(name).and_then(|child| child.number(0)).map(mm)
+}
+
+fn xy(item: &SExpr) -> Option<Point> {
+    Some(Point::from_mm(item.number(0)?, item.number(1)?))
+}
+
+fn point(item: &SExpr, name: &str) -> Option<Point> {
+    item.child(name).and_then(xy)
+}
+
+/// The `(at x y [angle])` of an item
+fn at(item: &SExpr) -> (Point, Angle) {
+    let at = item.child("at");
+    let position = at.and_then(xy).unwrap_or_default();
+    let angle = at.and_then(|at| at.number(2)).unwrap_or(0.0);
+    (position, Angle::from_degrees(angle))
+}
+
+/// Layers of a `(layers "F.Cu" "B.Cu")` list, with `*.Cu` and `F&B.Cu` expanded
+fn layer_list(item: &SExpr, copper_layers: &[PcbLayer]) -> Vec<PcbLayer> {
+    let mut layers = Vec::new();
+    let names = item.child("layers").map(SExpr::args).unwrap_or_default();
+    for name in names.iter().filter_map(SExpr::as_atom).chain(item.value("layer")) {
+        match name {
+            "*.Cu" => layers.extend_from_slice(copper_layers),
+            "F&B.Cu" => layers.extend([PcbLayer::FCu, PcbLayer::BCu]),
+            name => layers.extend(PcbLayer::from_name(name)),
+        }
+    }
+    layers
+}
+
+/// A `(pts (xy ..) (arc (start ..) (mid ..) (end ..)) ..)` list
+fn poly_line(pts: &SExpr, closed: bool) -> PolyLine {
+    let nodes = pts
+        .args()
+        .iter()
+        .filter_map(|node| match node.name() {
+            Some("xy") => xy(node).map(PolyLineNode::Point),
+            Some("arc") => Some(PolyLineNode::Arc {
+                start: point(node, "start")?,
+                mid: point(node, "mid")?,
+                end: point(node, "end")?,
+            }),
+            _ => None,
+        })
+        .collect();
+    PolyLine { nodes, closed }
+}
+
+/// A board (`gr_*`) or footprint (`fp_*`) graphic. Arcs in the pre-KiCad 6 center/angle form are
+/// skipped.
+fn graphic(item: &SExpr) -> Option<Graphic> {
+    let kind = item.name()?.split_once('_')?.1;
+    let shape = match kind {
+        "line" => GraphicShape::Segment { start: point(item, "start")?, end: point(item, "end")? },
+        "rect" => GraphicShape::Rectangle { top_left: point(item, "start")?, bottom_right: point(item, "end")? },
+        "arc" => GraphicShape::Arc { start: point(item, "start")?, mid: point(item, "mid")?, end: point(item, "end")? },
+        "circle" => GraphicShape::Circle { center: point(item, "center")?, radius_point: point(item, "end")? },
+        "poly" => {
+            let outline = poly_line(item.child("pts")?, true);
+            GraphicShape::Polygon(PolySet { polygons: vec![PolygonWithHoles { outline, holes: Vec::new() }] })
+        }
+        "curve" => {
+            let points: Vec<Point> = item.child("pts")?.children("xy").filter_map(xy).collect();
+            let [start, control1, control2, end] = points[..] else {
+                return None;
+            };
+            GraphicShape::Bezier { start, control1, control2, end }
+        }
+        _ => return None,
+    };
+    let stroke_width = item.child("stroke").and_then(|stroke| length(stroke, "width")).or_else(|| length(item, "width"));
+    Some(Graphic {
+        shape,
+        stroke_width: stroke_width.unwrap_or(Length::ZERO),
+        filled: matches!(item.value("fill"), Some("solid" | "yes")),
+    })
+}
+
+// ===== Footprints =====
+
+fn footprint(
+    item: &SExpr,
+    nets: &Nets,
+    copper_layers: &[PcbLayer],
+    pad_shapes: &mut HashMap<(String, PcbLayer), PolygonWithHoles>,
+) -> FootprintData {
+    let (origin, rotation) = at(item);
+    let position = Position::new(origin, rotation);
+
+    // Fields are properties since KiCad 8; older files keep reference and value in fp_text
+    let mut fields = BTreeMap::new();
+    for text in item.children("fp_text") {
+        match text.atom(0) {
+            Some("reference") => fields.insert("Reference".to_string(), text.atom(1).unwrap_or_default().to_string()),
+            Some("value") => fields.insert("Value".to_string(), text.atom(1).unwrap_or_default().to_string()),
+            _ => None,
+        };
+    }
+    for property in item.children("property") {
+        if let (Some(name), Some(text)) = (property.atom(0), property.atom(1)) {
+            // The library id is already the footprint name
+            if name != "Footprint" {
+                fields.insert(name.to_string(), text.to_string());
+            }
+        }
+    }
+
+    let id = uuid(item);
+    let reference = fields.get("Reference").cloned()
+        .unwrap_or_else(|| format!("REF_{}", id.get(..6).unwrap_or("")));
+    let value = fields.get("Value").cloned().unwrap_or_else(|| "UNKNOWN".to_string());
+    let description = fields.get("Description").map(String::as_str)
+        .or_else(|| item.value("descr"))
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+    let datasheet = fields.get("Datasheet").filter(|s| !s.is_empty() && *s != "~").cloned();
+
+    let attributes = item.child("attr");
+    let attribute = |name: &str| attributes.is_some_and(|attr| attr.has_flag(name));
+    let locked = item.has_flag("locked") || item.child("locked").is_some_and(|locked| locked.atom(0) != Some("no"));
+
+    let net_ties: Vec<Vec<String>> = item.child("net_tie_pad_groups")
+        .map(|groups| groups.args().iter()
+            .filter_map(SExpr::as_atom)
+            .map(|group| group.split(',').map(|pad| pad.trim().to_string()).filter(|pad| !pad.is_empty()).collect::<Vec<_>>())
+            .filter(|pads| !pads.is_empty())
+            .collect())
+        .unwrap_or_default();
+
+    let mut pads = Vec::new();
+    for pad_item in item.children("pad") {
+        let pad = pad(pad_item, nets);
+        let layers: Vec<PcbLayer> = layer_list(pad_item, copper_layers).into_iter().filter(PcbLayer::is_copper).collect();
+        let outline = if layers.is_empty() { None } else { pad_outline(pad_item, &pad, &position) };
+        if let Some(polygon) = outline {
+            for layer in layers {
+                pad_shapes.insert((pad.id.clone(), layer), polygon.clone());
+            }
+        }
+        pads.push(pad);
+    }
+
+    let courtyards = item.args().iter()
+        .filter(|child| child.name().is_some_and(|name| name.starts_with("fp_")))
+        .filter(|child| matches!(layer(child), PcbLayer::FCrtYd | PcbLayer::BCrtYd))
+        .filter_map(|child| Some((layer(child), graphic(child)?)))
+        .collect();
+
+    FootprintData {
+        id,
+        reference,
+        value,
+        footprint_name: item.atom(0).unwrap_or_default().to_string(),
+        position: origin,
+        rotation,
+        layer: layer(item),
+        description,
+        datasheet,
+        fields,
+        exclude_from_bom: attribute("exclude_from_bom"),
+        do_not_populate: attribute("dnp"),
+        locked,
+        not_in_schematic: attribute("board_only"),
+        exclude_from_position_files: attribute("exclude_from_pos_files"),
+        exempt_from_courtyard: attribute("allow_missing_courtyard"),
+        mounting_style: if attribute("smd") {
+            MountingStyle::Smd
+        } else if attribute("through_hole") {
+            MountingStyle::ThroughHole
+        } else {
+            MountingStyle::Unspecified
+        },
+        net_ties,
+        overrides: overrides(item),
+        models: item.children("model").map(model).collect(),
+        pads,
+        courtyards,
+    }
+}
+
+fn overrides(item: &SExpr) -> DesignRuleOverrides {
+    DesignRuleOverrides {
+        solder_mask_margin: length(item, "solder_mask_margin"),
+        solder_paste_margin: length(item, "solder_paste_margin"),
+        solder_paste_margin_ratio: item.child("solder_paste_margin_ratio")
+            .or_else(|| item.child("solder_paste_ratio"))
+            .and_then(|ratio| ratio.number(0)),
+        copper_clearance: length(item, "clearance"),
+        zone_connection: item.value("zone_connect").and_then(|style| match style {
+            "0" => Some(ZoneConnection::None),
+            "1" => Some(ZoneConnection::Thermal),
+            "2" => Some(ZoneConnection::Full),
+            "3" => Some(ZoneConnection::PthThermal),
+            _ => None,
+        }),
+    }
+}
+
+fn model(item: &SExpr) -> Model3D {
+    let xyz = |name: &str| {
+        let values = item.child(name).and_then(|child| child.child("xyz"));
+        let component = |index| values.and_then(|xyz| xyz.number(index));
+        [component(0), component(1), component(2)]
+    };
+    let [sx, sy, sz] = xyz("scale").map(|scale| scale.unwrap_or(1.0));
+    let [rx, ry, rz] = xyz("rotate").map(|degrees| Angle::from_degrees(degrees.unwrap_or(0.0)));
+    let [ox, oy, oz] = xyz("offset").map(|offset| mm(offset.unwrap_or(0.0)));
+    Model3D {
+        filename: item.atom(0).unwrap_or_default().to_string(),
+        scale: (sx, sy, sz),
+        rotation: (rx, ry, rz),
+        offset: (ox, oy, oz),
+        visible: !item.has_flag("hide") && item.value("hide") != Some("yes"),
+        opacity: item.child("opacity").and_then(|opacity| opacity.number(0)).unwrap_or(1.0),
+    }
+}
+
+fn pad(item: &SExpr, nets: &Nets) -> Pad {
+    // (drill 0.8) or (drill oval 1.0 2.0), taking the narrow side of slots
+    let drill = item.child("drill").and_then(|drill| match drill.atom(0) {
+        Some("oval") => Some(drill.number(1)?.min(drill.number(2).unwrap_or(f64::MAX))),
+        _ => drill.number(0),
+    });
+    Pad {
+        id: uuid(item),
+        number: item.atom(0).unwrap_or_default().to_string(),
+        kind: match item.atom(1) {
+            Some("smd") => PadKind::Smd,
+            Some("connect") => PadKind::EdgeConnector,
+            Some("np_thru_hole") => PadKind::Npth,
+            _ => PadKind::Pth,
+        },
+        offset: at(item).0,
+        net: nets.name(item),
+        drill: drill.filter(|drill| *drill > 0.0).map(mm),
+        size: point(item, "size").unwrap_or_default(),
+        shapes: Vec::new(),
+        clearance: length(item, "clearance"),
+    }
+}
+
+/// The copper outline of a pad, in board coordinates
+fn pad_outline(item: &SExpr, pad: &Pad, footprint: &Position) -> Option<PolygonWithHoles> {
+    if pad.size.x <= Length::ZERO || pad.size.y <= Length::ZERO {
+        return None;
+    }
+
+    let (w, h) = (pad.size.x.nm() as f64 / 2.0, pad.size.y.nm() as f64 / 2.0);
+    let unrotated = match item.atom(2) {
+        Some("trapezoid") => PolygonWithHoles { outline: trapezoid(item, w, h), holes: Vec::new() },
+        Some("custom") => custom_outline(item, w, h)?,
+        shape => {
+            let radius = match shape {
+                Some("circle") => w,
+                Some("oval") => w.min(h),
+                Some("roundrect") => item.child("roundrect_rratio").and_then(|ratio| ratio.number(0)).unwrap_or(0.25) * 2.0 * w.min(h),
+                _ => 0.0,
+            };
+            PolygonWithHoles { outline: rounded_rectangle(w, h, radius), holes: Vec::new() }
+        }
+    };
+
+    let (_, angle) = at(item);
+    let center = pad.board_position(footprint);
+    let place = |offset: Point| (center + offset).rotated(angle, &center);
+    Some(PolygonWithHoles {
+        outline: unrotated.outline.map_points(&place),
+        holes: unrotated.holes.iter().map(|hole| hole.map_points(&place)).collect(),
+    })
+}
+
+/// A rectangle of half-size `w` × `h` around the origin with corners rounded by `radius`
+fn rounded_rectangle(w: f64, h: f64, radius: f64) -> PolyLine {
+    // Corners inset by the radius, each rounded by a quarter arc, going around clockwise on screen
+    let (ix, iy) = (w - radius, h - radius);
+    let nodes = if radius <= 0.0 {
+        [(-w, -h), (w, -h), (w, h), (-w, h)].map(|(x, y)| PolyLineNode::Point(local(x, y))).to_vec()
+    } else {
+        let diagonal = radius * std::f64::consts::FRAC_1_SQRT_2;
+        [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(corner, (sx, sy))| {
+                let (cx, cy) = (sx * ix, sy * iy);
+                // The arc runs from the corner's preceding edge to its following one
+                let (start, end) = if corner % 2 == 0 {
+                    (local(sx * w, cy), local(cx, sy * h))
+                } else {
+                    (local(cx, sy * h), local(sx * w, cy))
+                };
+                PolyLineNode::Arc { start, mid: local(cx + sx * diagonal, cy + sy * diagonal), end }
+            })
+            .collect()
+    };
+    PolyLine { nodes, closed: true }
+}
+
+/// A trapezoid pad as KiCad builds it: `(rect_delta dx dy)` lengthens the left edge by `dx` and
+/// the bottom edge by `dy`, shortening the opposite edges by as much
+fn trapezoid(item: &SExpr, w: f64, h: f64) -> PolyLine {
+    let delta = item.child("rect_delta");
+    let half_delta = |index| delta.and_then(|delta| delta.number(index)).map_or(0.0, |d| mm(d).nm() as f64 / 2.0);
+    let (dx, dy) = (half_delta(0), half_delta(1));
+    let corners = [(-w + dy, -h - dx), (w - dy, -h + dx), (w + dy, h - dx), (-w - dy, h + dx)];
+    PolyLine { nodes: corners.map(|(x, y)| PolyLineNode::Point(local(x, y))).to_vec(), closed: true }
+}
+
+/// A custom pad: the union of its anchor pad (a circle unless `(anchor rect)`) and its
+/// `(primitives ..)`, which are drawn around the pad center before the pad is rotated. Curves are
+/// approximated with the default `Tessellation`.
+fn custom_outline(item: &SExpr, w: f64, h: f64) -> Option<PolygonWithHoles> {
+    let anchor_radius = match item.child("options").and_then(|options| options.value("anchor")) {
+        Some("rect") => 0.0,
+        _ => w,
+    };
+    let tessellation = Tessellation::default();
+    let anchor = PolygonWithHoles { outline: rounded_rectangle(w, h, anchor_radius), holes: Vec::new() };
+    let copper = item.child("primitives").map(SExpr::args).unwrap_or_default()
+        .iter()
+        .filter_map(graphic)
+        // Polygon primitives are always filled
+        .map(|primitive| Graphic { filled: primitive.filled || matches!(primitive.shape, GraphicShape::Polygon(_)), ..primitive })
+        .fold(anchor.to_shape(&tessellation), |copper, primitive| copper.union(&primitive.to_shape(&tessellation)));
+    // KiCad keeps a custom pad in one piece, so a primitive off on its own is not part of it
+    copper.polygons.0
+        .iter()
+        .max_by(|a, b| a.unsigned_area().total_cmp(&b.unsigned_area()))
+        .map(PolygonWithHoles::from_polygon)
+}
+
+fn local(x: f64, y: f64) -> Point {
+    Point::from_nm(x.round() as i64, y.round() as i64)
+}
+
+// ===== Routing =====
+
+fn via(item: &SExpr, nets: &Nets) -> ViaData {
+    let layers: Vec<PcbLayer> = item.child("layers").map(SExpr::args).unwrap_or_default()
+        .iter()
+        .filter_map(|name| name.as_atom().and_then(PcbLayer::from_name))
+        .collect();
+    ViaData {
+        id: uuid(item),
+        position: at(item).0,
+        drill: length(item, "drill").unwrap_or(Length::ZERO),
+        diameter: length(item, "size").unwrap_or(Length::ZERO),
+        via_type: if item.has_flag("micro") {
+            ViaType::Micro
+        } else if item.has_flag("blind") {
+            ViaType::BlindBuried
+        } else {
+            ViaType::Through
+        },
+        start_layer: layers.first().copied().unwrap_or(PcbLayer::FCu),
+        end_layer: layers.last().copied().unwrap_or(PcbLayer::BCu),
+        net: nets.name(item),
+    }
+}
+
+/// A zone, or `None` for teardrops, which KiCad regenerates from the routing
+fn zone(item: &SExpr, nets: &Nets, copper_layers: &[PcbLayer]) -> Option<ZoneData> {
+    if item.child("attr").is_some_and(|attr| attr.child("teardrop").is_some()) {
+        debug!("Skipping teardrop zone {}", uuid(item));
+        return None;
+    }
+    let layers = layer_list(item, copper_layers);
+    let kind = if item.child("keepout").is_some() {
+        ZoneKind::RuleArea
+    } else if layers.iter().any(PcbLayer::is_copper) {
+        ZoneKind::Copper
+    } else {
+        ZoneKind::Graphic
+    };
+    // As in KiCad's ZONE::AddPolygon, the first polygon is the outline and the rest are cutouts
+    let mut contours = item.children("polygon")
+        .filter_map(|polygon| polygon.child("pts"))
+        .map(|pts| poly_line(pts, true));
+    let polygons = contours.next()
+        .map(|outline| PolygonWithHoles { outline, holes: contours.collect() })
+        .into_iter()
+        .collect();
+    let net = (kind == ZoneKind::Copper)
+        .then(|| item.value("net_name").filter(|name| !name.is_empty()).map(str::to_string).or_else(|| nets.name(item)))
+        .flatten();
+    Some(ZoneData {
+        id: uuid(item),
+        name: item.value("name").unwrap_or_default().to_string(),
+        kind,
+        layers,
+        outline: PolySet { polygons },
+        priority: item.value("priority").and_then(|priority| priority.parse().ok()).unwrap_or(0),
+        filled: item.child("filled_polygon").is_some(),
+        net,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Pads;
+    use crate::world::PcbWorld;
+
+    const BOARD: &str = r#"
+        (kicad_pcb (version 20240108) (generator "pcbnew")
+          (layers
+            (0 "F.Cu" signal "Top")
+            (31 "B.Cu" signal)
+            (44 "Edge.Cuts" user))
+          (setup (pad_to_mask_clearance 0) (aux_axis_origin 90 60))
+          (net 0 "")
+          (net 1 "GND")
+          (net 2 "VCC")
+          (footprint "Resistor_SMD:R_0603_1608Metric"
+            (layer "F.Cu")
+            (uuid "fp-r1")
+            (at 100 50 90)
+            (property "Reference" "R1" (at 0 -1.4 90) (layer "F.SilkS"))
+            (property "Value" "10k" (at 0 1.4 90) (layer "F.Fab"))
+            (property "Footprint" "Resistor_SMD:R_0603_1608Metric")
+            (property "Datasheet" "~")
+            (property "MPN" "RC0603FR-0710KL")
+            (attr smd dnp)
+            (fp_rect (start -1.5 -0.7) (end 1.5 0.7) (stroke (width 0.05) (type solid)) (fill none) (layer "F.CrtYd"))
+            (pad "1" smd roundrect (at -0.8 0 90) (size 0.8 0.95) (layers "F.Cu" "F.Paste" "F.Mask")
+              (roundrect_rratio 0.25) (net 2 "VCC") (uuid "pad-1"))
+            (pad "2" smd rect (at 0.8 0 90) (size 0.8 0.95) (layers "F.Cu" "F.Paste" "F.Mask")
+              (net 1 "GND") (uuid "pad-2"))
+            (model "${KICAD8_3DMODEL_DIR}/Resistor_SMD.3dshapes/R_0603_1608Metric.wrl"
+              (offset (xyz 0 0 0)) (scale (xyz 1 1 1)) (rotate (xyz 0 0 0))))
+          (gr_rect (start 90 40) (end 110 60) (stroke (width 0.1) (type default)) (fill none) (layer "Edge.Cuts") (uuid "edge"))
+          (segment (start 100 50.8) (end 105 50.8) (width 0.25) (layer "F.Cu") (net 2) (uuid "trk"))
+          (arc (start 105 50.8) (mid 106 51.2) (end 106.4 52.2) (width 0.25) (layer "F.Cu") (net 2) (uuid "arc"))
+          (via (at 106.4 52.2) (size 0.6) (drill 0.3) (layers "F.Cu" "B.Cu") (net 2) (uuid "via"))
+          (zone (net 1) (net_name "GND") (layers "F&B.Cu") (uuid "zone") (name "ground") (priority 2)
+            (polygon (pts (xy 90 40) (xy 110 40) (xy 110 60) (xy 90 60)))
+            (filled_polygon (layer "F.Cu") (pts (xy 90 40) (xy 110 40) (xy 110 60))))
+          (zone (net 0) (net_name "") (layer "F.Cu") (uuid "tear") (attr (teardrop (type padvia)))
+            (polygon (pts (xy 0 0) (xy 1 0) (xy 1 1)))))
+    "#;
+
+    #[test]
+    fn parses_board_items() {
+        let board = BoardFile::parse(BOARD).unwrap();
+        assert_eq!(board.layer_names.names.get(&PcbLayer::FCu).map(String::as_str), Some("Top"));
+        assert_eq!(board.nets.len(), 3);
+        assert_eq!(board.aux_origin, Point::from_mm(90.0, 60.0));
+
+        let r1 = &board.footprints[0];
+        assert_eq!((r1.reference.as_str(), r1.value.as_str(), r1.id.as_str()), ("R1", "10k", "fp-r1"));
+        assert_eq!(r1.rotation, Angle::from_degrees(90.0));
+        assert!(r1.do_not_populate && !r1.exclude_from_bom);
+        assert_eq!(r1.mounting_style, MountingStyle::Smd);
+        assert_eq!(r1.datasheet, None);
+        assert_eq!(r1.fields.get("MPN").map(String::as_str), Some("RC0603FR-0710KL"));
+        assert!(!r1.fields.contains_key("Footprint"));
+        assert_eq!(r1.pads[0].net.as_deref(), Some("VCC"));
+        assert_eq!(r1.pads[1].offset, Point::from_mm(0.8, 0.0));
+        assert_eq!(r1.courtyards.len(), 1);
+        assert_eq!(r1.models.len(), 1);
+
+        // Pad 2 sits 0.8 mm along the footprint's X axis, which points up once rotated by 90°
+        let pad2 = board.pad_shapes[&("pad-2".to_string(), PcbLayer::FCu)].outline.points(&Default::default());
+        let center = Point::from_mm(100.0, 49.2);
+        assert_eq!(pad2.len(), 4);
+        assert!(pad2.iter().all(|corner| (corner.distance(&center).mm() - 0.8f64.hypot(0.95) / 2.0).abs() < 1e-5));
+        // A rounded rectangle loses (4 - π) r² to its corners
+        assert!(!board.pad_shapes.contains_key(&("pad-1".to_string(), PcbLayer::BCu)));
+        let pad1 = board.pad_shapes[&("pad-1".to_string(), PcbLayer::FCu)].to_shape(&Default::default()).area_mm2();
+        assert!((pad1 - (0.8 * 0.95 - (4.0 - std::f64::consts::PI) * 0.2 * 0.2)).abs() < 5e-3);
+
+        assert_eq!(board.shapes.len(), 1);
+        assert_eq!(board.shapes[0].layer, PcbLayer::EdgeCuts);
+        assert_eq!(board.tracks.len(), 2);
+        assert_eq!(board.tracks[1].mid, Some(Point::from_mm(106.0, 51.2)));
+        assert_eq!(board.tracks[0].net.as_deref(), Some("VCC"));
+        assert_eq!((board.vias[0].drill, board.vias[0].start_layer), (Length::from_mm(0.3), PcbLayer::FCu));
+
+        assert_eq!(board.zones.len(), 1);
+        let zone = &board.zones[0];
+        assert_eq!((zone.kind, zone.priority, zone.filled), (ZoneKind::Copper, 2, true));
+        assert_eq!(zone.layers, vec![PcbLayer::FCu, PcbLayer::BCu]);
+        assert_eq!(zone.net.as_deref(), Some("GND"));
+
+        assert!(BoardFile::parse("(kicad_sch)").is_err());
+    }
+
+    #[test]
+    fn zone_cutouts_become_holes() {
+        let board = BoardFile::parse(r#"
+            (kicad_pcb (version 20240108)
+              (layers (0 "F.Cu" signal))
+              (net 0 "")
+              (net 1 "GND")
+              (zone (net 1) (net_name "GND") (layer "F.Cu") (uuid "zone")
+                (polygon (pts (xy 0 0) (xy 10 0) (xy 10 10) (xy 0 10)))
+                (polygon (pts (xy 2 2) (xy 4 2) (xy 4 4) (xy 2 4)))
+                (polygon (pts (xy 6 6) (xy 8 6) (xy 8 8) (xy 6 8)))))
+        "#).unwrap();
+        let outline = &board.zones[0].outline;
+        assert_eq!(outline.polygons.len(), 1);
+        assert_eq!(outline.polygons[0].holes.len(), 2);
+        let area = outline.polygons[0].to_shape(&Default::default()).area_mm2();
+        assert!((area - 92.0).abs() < 1e-9, "{}", area);
+    }
+
+    #[test]
+    fn pad_copper_matches_kicads_pad_polygons() {
+        let board = BoardFile::parse(r#"
+            (kicad_pcb (version 20240108)
+              (layers (0 "F.Cu" signal) (1 "In1.Cu" signal) (31 "B.Cu" signal) (38 "B.Mask" user))
+              (net 0 "")
+              (footprint "Test:Pads" (layer "F.Cu") (uuid "fp") (at 50 50)
+                (property "Reference" "U1")
+                (pad "1" smd trapezoid (at -2 0) (size 2 1) (rect_delta 0 0.4) (layers "F.Cu" "F.Mask") (uuid "trapezoid"))
+                (pad "2" thru_hole circle (at 2 0) (size 1.6 1.6) (drill 0.8) (layers "*.Cu" "*.Mask") (uuid "through"))
+                (pad "3" smd custom (at 0 3) (size 0.5 0.5) (layers "F.Cu")
+                  (options (clearance outline) (anchor rect))
+                  (primitives (gr_poly (pts (xy -0.25 -0.25) (xy 1.25 -0.25) (xy 1.25 0.25) (xy -0.25 0.25)) (width 0)))
+                  (uuid "custom"))))
+        "#).unwrap();
+
+        // What KiCad's GetPadShapeAsPolygon gives for each pad on each of its copper layers
+        let polygon = |nodes: Vec<PolyLineNode>| PolygonWithHoles { outline: PolyLine { nodes, closed: true }, holes: Vec::new() };
+        let corners = |corners: &[(f64, f64)]| {
+            polygon(corners.iter().map(|&(x, y)| PolyLineNode::Point(Point::from_mm(x, y))).collect())
+        };
+        let circle = polygon(vec![
+            PolyLineNode::Arc { start: Point::from_mm(51.2, 50.0), mid: Point::from_mm(52.0, 49.2), end: Point::from_mm(52.8, 50.0) },
+            PolyLineNode::Arc { start: Point::from_mm(52.8, 50.0), mid: Point::from_mm(52.0, 50.8), end: Point::from_mm(51.2, 50.0) },
+        ]);
+        let mut kicad = HashMap::new();
+        kicad.insert(("trapezoid".to_string(), PcbLayer::FCu), corners(&[(47.2, 49.5), (48.8, 49.5), (49.2, 50.5), (46.8, 50.5)]));
+        for layer in [PcbLayer::FCu, PcbLayer::InCu(1), PcbLayer::BCu] {
+            kicad.insert(("through".to_string(), layer), circle.clone());
+        }
+        kicad.insert(("custom".to_string(), PcbLayer::FCu), corners(&[(49.75, 52.75), (51.25, 52.75), (51.25, 53.25), (49.75, 53.25)]));
+
+        let mut live = PcbWorld::new();
+        for footprint in board.footprints.clone() {
+            live.spawn_footprint(footprint);
+        }
+        live.apply_pad_shapes(&kicad);
+        let mut file = PcbWorld::from_board(board);
+
+        let live_pads = live.world.query::<&Pads>().single(&live.world).pads.clone();
+        let file_pads = file.world.query::<&Pads>().single(&file.world).pads.clone();
+        assert_eq!(live_pads.len(), 3);
+        for (live_pad, file_pad) in live_pads.iter().zip(&file_pads) {
+            let layers = |pad: &Pad| pad.shapes.iter().map(|(layer, _)| *layer).collect::<Vec<_>>();
+            assert_eq!(layers(file_pad), layers(live_pad), "pad {}", live_pad.number);
+            for ((_, live_shape), (_, file_shape)) in live_pad.shapes.iter().zip(&file_pad.shapes) {
+                // Arcs are split at different points, each segment within 5 µm of the curve
+                let difference = live_shape.xor(file_shape).area_mm2();
+                assert!(difference < 1e-2, "pad {} differs by {} mm²", live_pad.number, difference);
+            }
+        }
+    }
+}
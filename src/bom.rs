@@ -0,0 +1,443 @@
+//! Bill of materials
+//!
+//! Footprints are grouped into BOM lines by value, footprint and manufacturer part number, so two
+//! "10k" 0603 resistors with different MPNs end up on separate lines. Footprints flagged
+//! `exclude_from_bom` are always left out; `do_not_populate` ones are left out unless
+//! `BomOptions::include_dnp` is set, in which case they get lines of their own marked DNP.
+//!
+//! Columns are configurable and exported as CSV, JSON or an Excel workbook (.xlsx). Options can be
+//! written in Rust or loaded from TOML:
+//!
+//! ```toml
+//! columns = ["Reference", "Quantity", "Value", "Footprint", "MPN", "LCSC"]
+//! include_dnp = true
+//! ```
+//!
+//! Column names other than the built-in ones are read from footprint fields.
+//!
+//! Values, MPNs, manufacturers and DNP flags are taken from `EffectivePart` when a variant has been applied.
+
+use bevy_ecs::prelude::*;
+use eyre::{Result, WrapErr};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use tracing::{info, instrument};
+use crate::components::*;
+use crate::variant::EffectivePart;
+
+/// Field names tried, in order, for the manufacturer part number
+pub const MPN_FIELDS: &[&str] = &["MPN", "Manufacturer Part Number", "Manufacturer_Part_Number", "PartNumber", "Part Number"];
+/// Field names tried, in order, for the manufacturer
+pub const MANUFACTURER_FIELDS: &[&str] = &["Manufacturer", "MFR", "Manufacturer_Name"];
+
+/// A BOM column
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(from = "String")]
+pub enum BomColumn {
+    References,
+    Quantity,
+    Value,
+    Footprint,
+    Mpn,
+    Manufacturer,
+    Description,
+    Dnp,
+    /// Any other footprint field, by name
+    Field(String),
+}
+
+impl BomColumn {
+    /// Heading used in exports
+    pub fn header(&self) -> &str {
+        match self {
+            BomColumn::References => "Reference",
+            BomColumn::Quantity => "Quantity",
+            BomColumn::Value => "Value",
+            BomColumn::Footprint => "Footprint",
+            BomColumn::Mpn => "MPN",
+            BomColumn::Manufacturer => "Manufacturer",
+            BomColumn::Description => "Description",
+            BomColumn::Dnp => "DNP",
+            BomColumn::Field(name) => name,
+        }
+    }
+}
+
+impl From<&str> for BomColumn {
+    /// Parse a column heading; built-in names are matched case-insensitively
+    fn from(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "reference" | "references" | "designator" => BomColumn::References,
+            "quantity" | "qty" => BomColumn::Quantity,
+            "value" => BomColumn::Value,
+            "footprint" => BomColumn::Footprint,
+            "mpn" => BomColumn::Mpn,
+            "manufacturer" => BomColumn::Manufacturer,
+            "description" => BomColumn::Description,
+            "dnp" => BomColumn::Dnp,
+            _ => BomColumn::Field(name.to_string()),
+        }
+    }
+}
+
+impl From<String> for BomColumn {
+    fn from(name: String) -> Self {
+        BomColumn::from(name.as_str())
+    }
+}
+
+/// What goes into a BOM and how it is laid out
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct BomOptions {
+    pub columns: Vec<BomColumn>,
+    /// List do-not-populate parts on lines of their own instead of leaving them out
+    pub include_dnp: bool,
+}
+
+impl Default for BomOptions {
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                BomColumn::References,
+                BomColumn::Quantity,
+                BomColumn::Value,
+                BomColumn::Footprint,
+                BomColumn::Mpn,
+                BomColumn::Manufacturer,
+            ],
+            include_dnp: false,
+        }
+    }
+}
+
+impl BomOptions {
+    /// Parse options from TOML; missing keys keep their defaults
+    pub fn from_toml_str(config: &str) -> Result<Self> {
+        toml::from_str(config).wrap_err("Invalid BOM config")
+    }
+
+    /// Load options from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read BOM config {}", path.display()))?;
+        Self::from_toml_str(&config)
+            .wrap_err_with(|| format!("Failed to load BOM config {}", path.display()))
+    }
+}
+
+/// Export format for `Bom::export`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BomFormat {
+    Csv,
+    /// An array of objects keyed by column heading
+    Json,
+    /// Excel workbook (Office Open XML .xlsx) with a single "BOM" sheet
+    Xlsx,
+}
+
+impl BomFormat {
+    /// Conventional file extension, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BomFormat::Csv => "csv",
+            BomFormat::Json => "json",
+            BomFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// One BOM line: identical parts and the references they are placed at
+#[derive(Debug, Clone, PartialEq)]
+pub struct BomLine {
+    pub references: Vec<String>,
+    pub value: String,
+    pub footprint: String,
+    pub mpn: Option<String>,
+    /// Shared by every part on the line; unset if they disagree
+    pub manufacturer: Option<String>,
+    pub dnp: bool,
+    /// Fields shared by every part on the line; fields that differ between parts are left out
+    pub fields: Fields,
+    pub entities: Vec<Entity>,
+}
+
+impl BomLine {
+    pub fn quantity(&self) -> usize {
+        self.references.len()
+    }
+
+    /// The line's text in `column`
+    pub fn cell(&self, column: &BomColumn) -> String {
+        let field = |names: &[&str]| self.fields.get_any(names).unwrap_or_default().to_string();
+        match column {
+            BomColumn::References => self.references.join(", "),
+            BomColumn::Quantity => self.quantity().to_string(),
+            BomColumn::Value => self.value.clone(),
+            BomColumn::Footprint => self.footprint.clone(),
+            BomColumn::Mpn => self.mpn.clone().unwrap_or_default(),
+            BomColumn::Manufacturer => self.manufacturer.clone().unwrap_or_default(),
+            BomColumn::Description => field(&["Description"]),
+            BomColumn::Dnp => if self.dnp { "DNP".to_string() } else { String::new() },
+            BomColumn::Field(name) => field(&[name]),
+        }
+    }
+}
+
+/// A grouped bill of materials, ready to export
+#[derive(Debug, Clone, Default)]
+pub struct Bom {
+    pub columns: Vec<BomColumn>,
+    pub lines: Vec<BomLine>,
+}
+
+impl Bom {
+    /// Total number of parts to place
+    pub fn part_count(&self) -> usize {
+        self.lines.iter().filter(|line| !line.dnp).map(BomLine::quantity).sum()
+    }
+
+    /// Headings and cell text of every line, in column order
+    pub fn rows(&self) -> (Vec<&str>, Vec<Vec<String>>) {
+        let headers = self.columns.iter().map(BomColumn::header).collect();
+        let rows = self.lines
+            .iter()
+            .map(|line| self.columns.iter().map(|column| line.cell(column)).collect())
+            .collect();
+        (headers, rows)
+    }
+
+    /// Render the BOM in `format`, as the contents of a file
+    pub fn export(&self, format: BomFormat) -> Result<Vec<u8>> {
+        match format {
+            BomFormat::Csv => Ok(self.to_csv()?.into_bytes()),
+            BomFormat::Json => Ok(self.to_json()?.into_bytes()),
+            BomFormat::Xlsx => self.to_xlsx(),
+        }
+    }
+
+    /// Write the BOM to `path` in `format`
+    pub fn write(&self, path: impl AsRef<Path>, format: BomFormat) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.export(format)?)
+            .wrap_err_with(|| format!("Failed to write BOM {}", path.display()))
+    }
+
+    pub fn to_csv(&self) -> Result<String> {
+        let (headers, rows) = self.rows();
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(&headers)?;
+        for row in rows {
+            writer.write_record(&row)?;
+        }
+        let bytes = writer.into_inner().wrap_err("Failed to flush BOM CSV")?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let lines: Vec<serde_json::Value> = self.lines
+            .iter()
+            .map(|line| {
+                let object = self.columns
+                    .iter()
+                    .map(|column| {
+                        let value = match column {
+                            BomColumn::Quantity => line.quantity().into(),
+                            BomColumn::Dnp => line.dnp.into(),
+                            _ => line.cell(column).into(),
+                        };
+                        (column.header().to_string(), value)
+                    })
+                    .collect();
+                serde_json::Value::Object(object)
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&lines)?)
+    }
+
+    /// The BOM as an .xlsx workbook, with a bold heading row and numeric quantities
+    pub fn to_xlsx(&self) -> Result<Vec<u8>> {
+        let (headers, rows) = self.rows();
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        sheet.set_name("BOM")?;
+        let bold = Format::new().set_bold();
+        for (col, header) in headers.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, *header, &bold)?;
+        }
+        let quantity = self.columns.iter().position(|column| *column == BomColumn::Quantity);
+        for (row, (line, cells)) in self.lines.iter().zip(&rows).enumerate() {
+            let row = row as u32 + 1;
+            for (col, cell) in cells.iter().enumerate() {
+                if Some(col) == quantity {
+                    sheet.write_number(row, col as u16, line.quantity() as f64)?;
+                } else {
+                    sheet.write_string(row, col as u16, cell)?;
+                }
+            }
+        }
+        workbook.save_to_buffer().wrap_err("Failed to build BOM workbook")
+    }
+}
+
+impl fmt::Display for Bom {
+    /// The BOM as CSV
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_csv().map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Compare reference designators so that R2 sorts before R10
+pub fn compare_references(a: &str, b: &str) -> Ordering {
+    fn split(reference: &str) -> (&str, Option<u64>, &str) {
+        let prefix_end = reference.find(|c: char| c.is_ascii_digit()).unwrap_or(reference.len());
+        let (prefix, rest) = reference.split_at(prefix_end);
+        let digits_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (digits, suffix) = rest.split_at(digits_end);
+        (prefix, digits.parse().ok(), suffix)
+    }
+    split(a).cmp(&split(b)).then_with(|| a.cmp(b))
+}
+
+/// Group the world's footprints into a BOM
+#[instrument(skip(world))]
+pub fn generate_bom(world: &mut World, options: &BomOptions) -> Bom {
+    type Key = (String, String, Option<String>, bool);
+    type Part<'a> = (Entity, &'a ComponentInfo, Option<&'a Fields>, Option<String>);
+    let mut groups: BTreeMap<Key, Vec<Part>> = BTreeMap::new();
+    let mut excluded = 0;
+
+    let mut query = world.query::<(Entity, &ComponentInfo, Option<&Fields>, Option<&ComponentFlags>, Option<&EffectivePart>)>();
+    for (entity, info, fields, flags, part) in query.iter(world) {
+        let part = EffectivePart::resolve(part, info, fields, flags);
+        if flags.is_some_and(|flags| flags.exclude_from_bom) || (part.dnp && !options.include_dnp) {
+            excluded += 1;
+            continue;
+        }
+        let key = (part.value, info.footprint_name.clone(), part.mpn, part.dnp);
+        groups.entry(key).or_default().push((entity, info, fields, part.manufacturer));
+    }
+
+    let mut lines: Vec<BomLine> = groups
+        .into_iter()
+        .map(|((value, footprint, mpn, dnp), mut parts)| {
+            parts.sort_by(|a, b| compare_references(&a.1.reference, &b.1.reference));
+            // Keep only the fields every part agrees on
+            let mut fields = parts[0].2.map(|fields| fields.fields.clone()).unwrap_or_default();
+            for (_, _, other, _) in &parts[1..] {
+                fields.retain(|name, value| other.and_then(|other| other.fields.get(name)) == Some(value));
+            }
+            let manufacturer = parts[0].3.clone()
+                .filter(|manufacturer| parts.iter().all(|part| part.3.as_ref() == Some(manufacturer)));
+            BomLine {
+                references: parts.iter().map(|(_, info, _, _)| info.reference.clone()).collect(),
+                value,
+                footprint,
+                mpn,
+                manufacturer,
+                dnp,
+                fields: Fields { fields },
+                entities: parts.iter().map(|(entity, _, _, _)| *entity).collect(),
+            }
+        })
+        .collect();
+    lines.sort_by(|a, b| a.dnp.cmp(&b.dnp).then_with(|| compare_references(&a.references[0], &b.references[0])));
+
+    let bom = Bom { columns: options.columns.clone(), lines };
+    info!("BOM has {} lines, {} parts; {} footprints excluded", bom.lines.len(), bom.part_count(), excluded);
+    bom
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::part;
+
+    const R0603: &str = "Resistor_SMD:R_0603_1608Metric";
+
+    /// Three identical Yageo resistors, one with another MPN, a DNP capacitor and a mounting hole
+    fn board() -> World {
+        let mut world = World::new();
+        let yageo = [("MPN", "RC0603FR-0710KL"), ("Manufacturer", "Yageo"), ("LCSC", "C98220")];
+        part("R10").value("10k").footprint(R0603).fields(&yageo).spawn(&mut world);
+        part("R2").value("10k").footprint(R0603).fields(&yageo).spawn(&mut world);
+        part("R1").value("10k").footprint(R0603).fields(&[("MPN", "RC0603FR-0710KL"), ("Manufacturer", "Yageo")]).spawn(&mut world);
+        part("R3").value("10k").footprint(R0603).fields(&[("MPN", "ERJ-3EKF1002V")]).spawn(&mut world);
+        part("C1").value("100n").footprint("Capacitor_SMD:C_0402")
+            .flags(ComponentFlags { do_not_populate: true, ..Default::default() })
+            .spawn(&mut world);
+        part("H1").value("MountingHole").footprint("MountingHole:M3")
+            .flags(ComponentFlags { exclude_from_bom: true, ..Default::default() })
+            .spawn(&mut world);
+        world
+    }
+
+    fn bom_with_dnp(world: &mut World) -> Bom {
+        let options = BomOptions::from_toml_str(r#"
+            columns = ["Reference", "Qty", "Value", "MPN", "DNP"]
+            include_dnp = true
+        "#).unwrap();
+        generate_bom(world, &options)
+    }
+
+    #[test]
+    fn groups_by_value_footprint_and_mpn() {
+        let bom = generate_bom(&mut board(), &BomOptions::default());
+        assert_eq!(bom.lines.len(), 2);
+        assert_eq!(bom.lines[0].references, vec!["R1", "R2", "R10"]);
+        assert_eq!(bom.lines[0].cell(&BomColumn::Manufacturer), "Yageo");
+        // LCSC is only set on two of the three parts
+        assert_eq!(bom.lines[0].cell(&BomColumn::Field("LCSC".to_string())), "");
+        assert_eq!(bom.lines[1].references, vec!["R3"]);
+        assert_eq!(bom.part_count(), 4);
+    }
+
+    #[test]
+    fn lists_dnp_parts_on_their_own_lines() {
+        let bom = bom_with_dnp(&mut board());
+        assert_eq!(bom.lines.len(), 3);
+        assert!(bom.lines[2].dnp);
+        assert_eq!(bom.part_count(), 4);
+        assert_eq!(bom.to_csv().unwrap(), concat!(
+            "Reference,Quantity,Value,MPN,DNP\n",
+            "\"R1, R2, R10\",3,10k,RC0603FR-0710KL,\n",
+            "R3,1,10k,ERJ-3EKF1002V,\n",
+            "C1,1,100n,,DNP\n",
+        ));
+    }
+
+    #[test]
+    fn exports_json_with_typed_values() {
+        let bom = bom_with_dnp(&mut board());
+        let json: serde_json::Value = serde_json::from_str(&bom.to_json().unwrap()).unwrap();
+        assert_eq!(json[0]["Quantity"], 3);
+        assert_eq!(json[2]["DNP"], true);
+    }
+
+    #[test]
+    fn exports_xlsx_workbook() {
+        let xlsx = bom_with_dnp(&mut board()).to_xlsx().unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(xlsx)).unwrap();
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut archive.by_name(name).unwrap(), &mut text).unwrap();
+            text
+        };
+        assert!(read("xl/workbook.xml").contains("name=\"BOM\""));
+        assert!(read("xl/sharedStrings.xml").contains("<t>RC0603FR-0710KL</t>"));
+        // Quantities are numbers, not shared strings
+        assert!(read("xl/worksheets/sheet1.xml").contains("<c r=\"B2\"><v>3</v></c>"));
+    }
+
+    #[test]
+    fn references_sort_naturally() {
+        let mut references = vec!["R10", "C2", "R2", "R1A", "R1"];
+        references.sort_by(|a, b| compare_references(a, b));
+        assert_eq!(references, vec!["C2", "R1", "R1A", "R2", "R10"]);
+    }
+}
@@ -0,0 +1,381 @@
+//! Data-driven component classification
+//!
+//! A `ClassificationRegistry` holds an ordered list of rules that match footprints by reference
+//! designator, footprint library id, field values and pad count. The first matching rule assigns
+//! a class name, which is recorded in a `Classification` component and, when a marker type is
+//! registered for that class, also inserted as a marker component (`Resistor`, `Capacitor`, ...).
+//!
+//! Rules can be written in Rust or loaded from a TOML file:
+//!
+//! ```toml
+//! [[rules]]
+//! class = "Resistor"
+//! reference = "^R[0-9]+$"
+//!
+//! [[rules]]
+//! class = "PowerInductor"
+//! footprint = "Inductor_SMD:*"
+//! fields = { Type = "Power*" }
+//! min_pads = 2
+//! ```
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::world::EntityWorldMut;
+use eyre::{Result, WrapErr};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::{debug, info, instrument};
+use crate::components::*;
+
+/// A classification rule as written in a config file
+///
+/// All conditions that are present must match; a rule without conditions matches everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClassificationRule {
+    /// Class name to assign, e.g. "Resistor" or a user-defined class
+    pub class: String,
+    /// Regular expression matched against the reference designator
+    pub reference: Option<String>,
+    /// Glob matched against the footprint library id ("Library:Footprint")
+    pub footprint: Option<String>,
+    /// Globs matched against field values by field name (case-insensitive names)
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Exact pad count
+    pub pads: Option<usize>,
+    /// Minimum pad count
+    pub min_pads: Option<usize>,
+    /// Maximum pad count
+    pub max_pads: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClassificationConfig {
+    #[serde(default)]
+    rules: Vec<ClassificationRule>,
+}
+
+/// A rule with its reference pattern compiled
+#[derive(Debug)]
+struct CompiledRule {
+    rule: ClassificationRule,
+    reference: Option<Regex>,
+}
+
+/// The data a rule is matched against
+#[derive(Debug, Clone, Copy)]
+pub struct ClassifyTarget<'a> {
+    pub reference: &'a str,
+    pub footprint: &'a str,
+    pub fields: Option<&'a Fields>,
+    pub pad_count: Option<usize>,
+}
+
+/// The class assigned to an entity by the registry
+#[derive(Component, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Classification {
+    pub class: String,
+}
+
+/// Inserts and removes the marker component of a class
+#[derive(Clone, Copy)]
+struct Marker {
+    insert: fn(&mut EntityWorldMut),
+    remove: fn(&mut EntityWorldMut),
+}
+
+/// Ordered classification rules and the marker components for each class
+#[derive(Resource)]
+pub struct ClassificationRegistry {
+    rules: Vec<CompiledRule>,
+    markers: HashMap<String, Marker>,
+}
+
+impl ClassificationRegistry {
+    /// Create a registry with no rules and the built-in marker components registered
+    pub fn new() -> Self {
+        let mut registry = Self {
+            rules: Vec::new(),
+            markers: HashMap::new(),
+        };
+        registry.register_marker::<Resistor>("Resistor");
+        registry.register_marker::<Capacitor>("Capacitor");
+        registry.register_marker::<Inductor>("Inductor");
+        registry.register_marker::<Diode>("Diode");
+        registry.register_marker::<Transistor>("Transistor");
+        registry.register_marker::<IntegratedCircuit>("IntegratedCircuit");
+        registry.register_marker::<Connector>("Connector");
+        registry.register_marker::<Crystal>("Crystal");
+        registry.register_marker::<Switch>("Switch");
+        registry.register_marker::<TestPoint>("TestPoint");
+        registry
+    }
+
+    /// Create a registry with rules for the common KiCad reference designator prefixes
+    pub fn with_default_rules() -> Self {
+        let mut registry = Self::new();
+        let defaults = [
+            ("TestPoint", "^TP[0-9]"),
+            ("Switch", "^SW[0-9]"),
+            ("Diode", "^(D|LED)[0-9]"),
+            ("Resistor", "^(R|RN)[0-9]"),
+            ("Capacitor", "^C[0-9]"),
+            ("Inductor", "^(L|FB)[0-9]"),
+            ("Transistor", "^Q[0-9]"),
+            ("IntegratedCircuit", "^(U|IC)[0-9]"),
+            ("Connector", "^(J|P|CN)[0-9]"),
+            ("Crystal", "^(Y|X)[0-9]"),
+        ];
+        for (class, reference) in defaults {
+            registry
+                .add_rule(ClassificationRule {
+                    class: class.to_string(),
+                    reference: Some(reference.to_string()),
+                    ..Default::default()
+                })
+                .expect("built-in classification rules are valid");
+        }
+        registry
+    }
+
+    /// Create a registry from TOML rules, with the built-in markers registered
+    pub fn from_toml_str(config: &str) -> Result<Self> {
+        let config: ClassificationConfig = toml::from_str(config)
+            .wrap_err("Invalid classification config")?;
+        let mut registry = Self::new();
+        for rule in config.rules {
+            registry.add_rule(rule)?;
+        }
+        Ok(registry)
+    }
+
+    /// Load a registry from a TOML rules file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read classification config {}", path.display()))?;
+        Self::from_toml_str(&config)
+            .wrap_err_with(|| format!("Failed to load classification config {}", path.display()))
+    }
+
+    /// Append a rule; rules are tried in the order they were added
+    pub fn add_rule(&mut self, rule: ClassificationRule) -> Result<()> {
+        let reference = rule.reference.as_deref()
+            .map(Regex::new)
+            .transpose()
+            .wrap_err_with(|| format!("Invalid reference pattern in rule for class {}", rule.class))?;
+        self.rules.push(CompiledRule { rule, reference });
+        Ok(())
+    }
+
+    /// Insert marker component `T` on entities classified as `class`
+    ///
+    /// Registering a class that already has a marker replaces it.
+    pub fn register_marker<T: Component + Default>(&mut self, class: &str) {
+        self.markers.insert(class.to_string(), Marker {
+            insert: |entity| {
+                entity.insert(T::default());
+            },
+            remove: |entity| {
+                entity.remove::<T>();
+            },
+        });
+    }
+
+    /// Number of rules in the registry
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Find the class of the first rule matching `target`
+    pub fn classify(&self, target: &ClassifyTarget) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|compiled| compiled.matches(target))
+            .map(|compiled| compiled.rule.class.as_str())
+    }
+
+    /// Classify every footprint in the world, inserting `Classification` and any registered
+    /// marker components. Returns the number of entities classified.
+    ///
+    /// Applying again, e.g. with different rules, first removes the `Classification` of every
+    /// entity and the markers registered in this registry, so an entity keeps no marker from a
+    /// class it no longer has.
+    #[instrument(skip(self, world))]
+    pub fn apply(&self, world: &mut World) -> usize {
+        let mut classified = world.query_filtered::<Entity, With<Classification>>();
+        let previous: Vec<Entity> = classified.iter(world).collect();
+        for entity in previous {
+            let mut entity = world.entity_mut(entity);
+            for marker in self.markers.values() {
+                (marker.remove)(&mut entity);
+            }
+            entity.remove::<Classification>();
+        }
+
+        let mut query = world.query::<(Entity, &ComponentInfo, Option<&Fields>, Option<&Pads>)>();
+        let assignments: Vec<(Entity, String)> = query
+            .iter(world)
+            .filter_map(|(entity, info, fields, pads)| {
+                let target = ClassifyTarget {
+                    reference: &info.reference,
+                    footprint: &info.footprint_name,
+                    fields,
+                    pad_count: pads.map(|pads| pads.pads.len()),
+                };
+                self.classify(&target).map(|class| {
+                    debug!("Classified {} as {}", info.reference, class);
+                    (entity, class.to_string())
+                })
+            })
+            .collect();
+
+        for (entity, class) in &assignments {
+            let mut entity = world.entity_mut(*entity);
+            if let Some(marker) = self.markers.get(class) {
+                (marker.insert)(&mut entity);
+            }
+            entity.insert(Classification { class: class.clone() });
+        }
+
+        info!("Classified {} components", assignments.len());
+        assignments.len()
+    }
+}
+
+impl Default for ClassificationRegistry {
+    fn default() -> Self {
+        Self::with_default_rules()
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, target: &ClassifyTarget) -> bool {
+        if let Some(reference) = &self.reference {
+            if !reference.is_match(target.reference) {
+                return false;
+            }
+        }
+        if let Some(footprint) = &self.rule.footprint {
+            if !glob_match(footprint, target.footprint) {
+                return false;
+            }
+        }
+        for (name, pattern) in &self.rule.fields {
+            match target.fields.and_then(|fields| fields.get(name)) {
+                Some(value) if glob_match(pattern, value) => {}
+                _ => return false,
+            }
+        }
+        if self.rule.pads.is_some() || self.rule.min_pads.is_some() || self.rule.max_pads.is_some() {
+            let Some(count) = target.pad_count else {
+                return false;
+            };
+            if self.rule.pads.is_some_and(|pads| count != pads)
+                || self.rule.min_pads.is_some_and(|min| count < min)
+                || self.rule.max_pads.is_some_and(|max| count > max)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Match `text` against a glob with `*` (any run of characters) and `?` (one character)
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matching() {
+        assert!(glob_match("Resistor_SMD:*", "Resistor_SMD:R_0603_1608Metric"));
+        assert!(glob_match("*:C_0?0?_*", "Capacitor_SMD:C_0402_1005Metric"));
+        assert!(!glob_match("Resistor_SMD:*", "Resistor_THT:R_Axial"));
+    }
+
+    #[test]
+    fn rules_from_toml_apply_in_order() {
+        let registry = ClassificationRegistry::from_toml_str(r#"
+            [[rules]]
+            class = "PowerInductor"
+            footprint = "Inductor_SMD:*"
+            fields = { type = "Power*" }
+
+            [[rules]]
+            class = "Inductor"
+            reference = "^L[0-9]"
+        "#).unwrap();
+
+        let mut fields = Fields::default();
+        fields.fields.insert("Type".to_string(), "Power shielded".to_string());
+        let target = ClassifyTarget {
+            reference: "L1",
+            footprint: "Inductor_SMD:L_1210",
+            fields: Some(&fields),
+            pad_count: Some(2),
+        };
+        assert_eq!(registry.classify(&target), Some("PowerInductor"));
+        assert_eq!(registry.classify(&ClassifyTarget { fields: None, ..target }), Some("Inductor"));
+        assert_eq!(registry.classify(&ClassifyTarget { reference: "R1", fields: None, ..target }), None);
+    }
+
+    #[test]
+    fn reapplying_replaces_previous_classes() {
+        let mut world = World::new();
+        let spawn = |world: &mut World, reference: &str| {
+            world.spawn(ComponentInfo {
+                reference: reference.to_string(),
+                value: String::new(),
+                footprint_name: String::new(),
+            }).id()
+        };
+        let r1 = spawn(&mut world, "R1");
+        let c1 = spawn(&mut world, "C1");
+        assert_eq!(ClassificationRegistry::with_default_rules().apply(&mut world), 2);
+        assert!(world.entity(r1).contains::<Resistor>());
+        assert!(world.entity(c1).contains::<Capacitor>());
+
+        let registry = ClassificationRegistry::from_toml_str(r#"
+            [[rules]]
+            class = "Capacitor"
+            reference = "^R"
+        "#).unwrap();
+        assert_eq!(registry.apply(&mut world), 1);
+        let r1 = world.entity(r1);
+        assert!(!r1.contains::<Resistor>());
+        assert!(r1.contains::<Capacitor>());
+        assert_eq!(r1.get::<Classification>().unwrap().class, "Capacitor");
+        let c1 = world.entity(c1);
+        assert!(!c1.contains::<Capacitor>());
+        assert!(!c1.contains::<Classification>());
+    }
+}
@@ -4,12 +4,23 @@
 
 use eyre::{Result, WrapErr};
 use nng::{Protocol, Socket};
-use protobuf::{EnumOrUnknown, Message, MessageFull};
+use protobuf::{Enum, EnumOrUnknown, Message, MessageFull};
 use protobuf::well_known_types::any::Any;
+use protobuf::well_known_types::empty::Empty;
 use rand::distributions::{Alphanumeric, DistString};
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::path::Path;
 use thiserror::Error;
 use tracing::{debug, error, info, instrument, warn};
+use crate::actions::{ActionStatus, PcbAction};
+use crate::components::{DesignRuleOverrides, Model3D, MountingStyle, Pad as PadInfo, PadKind, Position, ViaType as ViaKind, ZoneConnection, ZoneKind};
+use crate::drc::{MarkerStyle, NetClassRules, Violation};
+use crate::geometry;
+use crate::layers::{LayerNames, PcbLayer};
+use crate::snippet::{Snippet, SnippetPlacement};
+use crate::units::{Angle, Length, Point};
+use crate::view::{Appearance, BoardFlip, InactiveLayerDisplay, NetColorDisplay, RatsnestDisplay, ViewPreset};
 
 // Include generated protobuf modules (same as kicad-rs approach)
 mod protos {
@@ -22,7 +33,10 @@ use protos::base_types::*;
 use protos::editor_commands::*;
 use protos::envelope::*;
 use protos::board_types::*;
+use protos::board_commands::*;
 use protos::enums::*;
+use protos::project_commands::{GetNetClasses, NetClassesResponse};
+use protos::project_settings::NetClass;
 
 #[derive(Error, Debug)]
 pub enum KiCadError {
@@ -75,6 +89,15 @@ impl Default for KiCadConnectionConfig {
     }
 }
 
+/// Options for `KiCadClient::save_copy_of_document`
+#[derive(Debug, Clone, Default)]
+pub struct SaveCopyOptions {
+    /// Replace the destination file(s) if they exist
+    pub overwrite: bool,
+    /// Also write a project file next to the copy, so it opens with its own settings
+    pub include_project: bool,
+}
+
 /// Main client for communicating with KiCad
 pub struct KiCadClient {
     socket: Socket,
@@ -210,6 +233,78 @@ impl KiCadClient {
         })
     }
     
+    /// Get the open board as `.kicad_pcb` text in a single request, for parsing with
+    /// `board_file::BoardFile::parse`
+    #[instrument(skip(self))]
+    pub async fn save_document_to_string(&mut self) -> Result<String> {
+        info!("Requesting board document");
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = SaveDocumentToString::new();
+        request.document = Some(doc.clone()).into();
+        
+        let response: SavedDocumentResponse = self.send_request(request)?;
+        info!("Retrieved {} bytes of board document", response.contents.len());
+        Ok(response.contents)
+    }
+    
+    /// Save the open board to its file, as File > Save does
+    #[instrument(skip(self))]
+    pub async fn save_document(&mut self) -> Result<()> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = SaveDocument::new();
+        request.document = Some(doc.clone()).into();
+        
+        let _: Empty = self.send_request(request)?;
+        info!("Saved board");
+        Ok(())
+    }
+    
+    /// Save the open board, committed changes included, to `path` while pcbnew keeps editing
+    /// the original file. Relative paths are resolved against this process's working directory,
+    /// not KiCad's.
+    #[instrument(skip(self, path, options), fields(path = %path.as_ref().display()))]
+    pub async fn save_copy_of_document(&mut self, path: impl AsRef<Path>, options: &SaveCopyOptions) -> Result<()> {
+        let path = std::path::absolute(path.as_ref())
+            .wrap_err_with(|| format!("Failed to resolve {}", path.as_ref().display()))?;
+        let path = path.to_str()
+            .ok_or_else(|| KiCadError::ApiError(format!("Path is not valid UTF-8: {}", path.display())))?
+            .to_string();
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = SaveCopyOfDocument::new();
+        request.document = Some(doc.clone()).into();
+        request.path = path.clone();
+        let mut save_options = SaveOptions::new();
+        save_options.overwrite = options.overwrite;
+        save_options.include_project = options.include_project;
+        request.options = Some(save_options).into();
+        
+        let _: Empty = self.send_request(request)?;
+        info!("Saved a copy of the board to {}", path);
+        Ok(())
+    }
+    
+    /// Reload the open board from its file, discarding every unsaved change
+    #[instrument(skip(self))]
+    pub async fn revert_document(&mut self) -> Result<()> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = RevertDocument::new();
+        request.document = Some(doc.clone()).into();
+        
+        let _: Empty = self.send_request(request)?;
+        info!("Reverted board");
+        Ok(())
+    }
+    
     /// Get all footprints from the current board
     #[instrument(skip(self))]
     pub async fn get_footprints(&mut self) -> Result<Vec<FootprintData>> {
@@ -239,6 +334,693 @@ impl KiCadClient {
         Ok(footprints)
     }
     
+    /// Get all nets from the current board
+    #[instrument(skip(self))]
+    pub async fn get_nets(&mut self) -> Result<Vec<NetData>> {
+        info!("Requesting net list");
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetNets::new();
+        request.board = Some(doc.clone()).into();
+        
+        let response: NetsResponse = self.send_request(request)?;
+        let nets: Vec<NetData> = response.nets.iter()
+            .map(|net| NetData {
+                code: net.code.get_or_default().value,
+                name: net.name.clone(),
+            })
+            .collect();
+        
+        info!("Retrieved {} nets", nets.len());
+        Ok(nets)
+    }
+    
+    /// Get user-visible layer names from the board stackup
+    ///
+    /// Only layers whose name differs from the canonical KiCad name are included.
+    #[instrument(skip(self))]
+    pub async fn get_layer_names(&mut self) -> Result<LayerNames> {
+        info!("Requesting board stackup");
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetBoardStackup::new();
+        request.board = Some(doc.clone()).into();
+        
+        let response: BoardStackupResponse = self.send_request(request)?;
+        let mut names = LayerNames::default();
+        for stackup_layer in &response.stackup.get_or_default().layers {
+            let layer = PcbLayer::from(stackup_layer.layer.enum_value_or_default());
+            if layer != PcbLayer::Undefined
+                && !stackup_layer.user_name.is_empty()
+                && stackup_layer.user_name != layer.canonical_name()
+            {
+                debug!("Layer {} is named {}", layer, stackup_layer.user_name);
+                names.names.insert(layer, stackup_layer.user_name.clone());
+            }
+        }
+        
+        Ok(names)
+    }
+    
+    /// Get all board-level graphic shapes (lines, arcs, rectangles, circles, polygons, curves)
+    #[instrument(skip(self))]
+    pub async fn get_board_shapes(&mut self) -> Result<Vec<BoardShapeData>> {
+        info!("Requesting board graphic shapes");
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        request.types.push(KiCadObjectType::KOT_PCB_SHAPE.into());
+        
+        let response: GetItemsResponse = self.send_request(request)?;
+        let mut shapes = Vec::new();
+        for item in &response.items {
+            let Some(shape) = Any::unpack::<BoardGraphicShape>(item)? else {
+                continue;
+            };
+            shapes.push(BoardShapeData {
+                id: shape.id.get_or_default().value.clone(),
+                layer: PcbLayer::from(shape.layer.enum_value_or_default()),
+                graphic: shape.shape.get_or_default().into(),
+                net: shape.net.as_ref().map(|net| net.name.clone()).filter(|name| !name.is_empty()),
+            });
+        }
+        
+        info!("Retrieved {} board shapes", shapes.len());
+        Ok(shapes)
+    }
+    
+    /// Get the copper outline of pads on a layer, keyed by pad UUID
+    ///
+    /// Pads without copper on the layer are omitted by KiCad.
+    #[instrument(skip(self, pad_ids), fields(count = pad_ids.len()))]
+    pub async fn get_pad_shapes(&mut self, pad_ids: &[String], layer: PcbLayer) -> Result<HashMap<String, geometry::PolygonWithHoles>> {
+        info!("Requesting {} pad shapes on {}", pad_ids.len(), layer);
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetPadShapeAsPolygon::new();
+        request.board = Some(doc.clone()).into();
+        request.layer = BoardLayer::from(layer).into();
+        for id in pad_ids {
+            let mut kiid = KIID::new();
+            kiid.value = id.clone();
+            request.pads.push(kiid);
+        }
+        
+        let response: PadShapeAsPolygonResponse = self.send_request(request)?;
+        let shapes: HashMap<String, geometry::PolygonWithHoles> = response.pads.iter()
+            .zip(&response.polygons)
+            .map(|(pad, polygon)| (pad.value.clone(), polygon.into()))
+            .collect();
+        
+        info!("Retrieved {} pad shapes", shapes.len());
+        Ok(shapes)
+    }
+    
+    /// Get all copper tracks, including arc tracks
+    #[instrument(skip(self))]
+    pub async fn get_tracks(&mut self) -> Result<Vec<TrackData>> {
+        info!("Requesting tracks");
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        request.types.push(KiCadObjectType::KOT_PCB_TRACE.into());
+        request.types.push(KiCadObjectType::KOT_PCB_ARC.into());
+        
+        let response: GetItemsResponse = self.send_request(request)?;
+        let mut tracks = Vec::new();
+        for item in &response.items {
+            if let Some(track) = Any::unpack::<Track>(item)? {
+                tracks.push(TrackData {
+                    id: track.id.get_or_default().value.clone(),
+                    layer: PcbLayer::from(track.layer.enum_value_or_default()),
+                    start: Point::from(track.start.get_or_default()),
+                    mid: None,
+                    end: Point::from(track.end.get_or_default()),
+                    width: Length::from_nm(track.width.get_or_default().value_nm),
+                    net: net_name(&track.net),
+                });
+            } else if let Some(arc) = Any::unpack::<Arc>(item)? {
+                tracks.push(TrackData {
+                    id: arc.id.get_or_default().value.clone(),
+                    layer: PcbLayer::from(arc.layer.enum_value_or_default()),
+                    start: Point::from(arc.start.get_or_default()),
+                    mid: Some(Point::from(arc.mid.get_or_default())),
+                    end: Point::from(arc.end.get_or_default()),
+                    width: Length::from_nm(arc.width.get_or_default().value_nm),
+                    net: net_name(&arc.net),
+                });
+            }
+        }
+        
+        info!("Retrieved {} tracks", tracks.len());
+        Ok(tracks)
+    }
+    
+    /// Get all vias
+    #[instrument(skip(self))]
+    pub async fn get_vias(&mut self) -> Result<Vec<ViaData>> {
+        info!("Requesting vias");
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        request.types.push(KiCadObjectType::KOT_PCB_VIA.into());
+        
+        let response: GetItemsResponse = self.send_request(request)?;
+        let mut vias = Vec::new();
+        for item in &response.items {
+            let Some(via) = Any::unpack::<Via>(item)? else {
+                continue;
+            };
+            let stack = via.pad_stack.get_or_default();
+            let drill = stack.drill.get_or_default();
+            vias.push(ViaData {
+                id: via.id.get_or_default().value.clone(),
+                position: Point::from(via.position.get_or_default()),
+                drill: pad_drill_diameter(drill),
+                diameter: stack.copper_layers
+                    .iter()
+                    .map(|layer| Length::from_nm(layer.size.get_or_default().x_nm))
+                    .max()
+                    .unwrap_or(Length::ZERO),
+                via_type: match via.type_.enum_value_or_default() {
+                    ViaType::VT_BLIND_BURIED => ViaKind::BlindBuried,
+                    ViaType::VT_MICRO => ViaKind::Micro,
+                    _ => ViaKind::Through,
+                },
+                start_layer: PcbLayer::from(drill.start_layer.enum_value_or_default()),
+                end_layer: PcbLayer::from(drill.end_layer.enum_value_or_default()),
+                net: net_name(&via.net),
+            });
+        }
+        
+        info!("Retrieved {} vias", vias.len());
+        Ok(vias)
+    }
+    
+    /// Get all copper zones and rule areas. Teardrops are left out, since KiCad regenerates
+    /// them from the routing.
+    #[instrument(skip(self))]
+    pub async fn get_zones(&mut self) -> Result<Vec<ZoneData>> {
+        info!("Requesting zones");
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        request.types.push(KiCadObjectType::KOT_PCB_ZONE.into());
+        
+        let response: GetItemsResponse = self.send_request(request)?;
+        let mut zones = Vec::new();
+        for item in &response.items {
+            let Some(zone) = Any::unpack::<Zone>(item)? else {
+                continue;
+            };
+            let kind = match zone.type_.enum_value_or_default() {
+                ZoneType::ZT_COPPER => ZoneKind::Copper,
+                ZoneType::ZT_RULE_AREA => ZoneKind::RuleArea,
+                ZoneType::ZT_GRAPHICAL => ZoneKind::Graphic,
+                ZoneType::ZT_TEARDROP | ZoneType::ZT_UNKNOWN => continue,
+            };
+            zones.push(ZoneData {
+                id: zone.id.get_or_default().value.clone(),
+                name: zone.name.clone(),
+                kind,
+                layers: zone.layers.iter().map(|layer| PcbLayer::from(layer.enum_value_or_default())).collect(),
+                outline: geometry::PolySet::from(zone.outline.get_or_default()),
+                priority: zone.priority,
+                filled: zone.filled,
+                net: zone.has_copper_settings().then(|| net_name(&zone.copper_settings().net)).flatten(),
+            });
+        }
+        
+        info!("Retrieved {} zones", zones.len());
+        Ok(zones)
+    }
+    
+    /// Get the netclasses defined in the project
+    #[instrument(skip(self))]
+    pub async fn get_net_classes(&mut self) -> Result<Vec<NetClassRules>> {
+        info!("Requesting netclasses");
+        
+        let response: NetClassesResponse = self.send_request(GetNetClasses::new())?;
+        let classes: Vec<NetClassRules> = response.net_classes.iter().map(NetClassRules::from).collect();
+        
+        info!("Retrieved {} netclasses", classes.len());
+        Ok(classes)
+    }
+    
+    /// Get the effective netclass of each of the given nets, keyed by net name
+    #[instrument(skip(self, nets), fields(count = nets.len()))]
+    pub async fn get_net_class_for_nets(&mut self, nets: &[String]) -> Result<HashMap<String, NetClassRules>> {
+        info!("Requesting netclasses for {} nets", nets.len());
+        
+        let mut request = GetNetClassForNets::new();
+        for name in nets {
+            let mut net = Net::new();
+            net.name = name.clone();
+            request.net.push(net);
+        }
+        
+        let response: NetClassForNetsResponse = self.send_request(request)?;
+        let classes: HashMap<String, NetClassRules> = response.classes.iter()
+            .map(|(net, class)| (net.clone(), NetClassRules::from(class)))
+            .collect();
+        
+        info!("Retrieved netclasses for {} nets", classes.len());
+        Ok(classes)
+    }
+    
+    /// Replace the editor selection with the given items, e.g. the `items` of DRC violations.
+    /// Returns the number of items KiCad selected.
+    #[instrument(skip(self, ids), fields(count = ids.len()))]
+    pub async fn select_items(&mut self, ids: &[String]) -> Result<usize> {
+        info!("Selecting {} items", ids.len());
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut clear = ClearSelection::new();
+        clear.header = Some(ItemHeader::new()).into();
+        clear.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        let _: Empty = self.send_request(clear)?;
+        
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        
+        let mut request = AddToSelection::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        for id in ids {
+            let mut kiid = KIID::new();
+            kiid.value = id.clone();
+            request.items.push(kiid);
+        }
+        
+        let response: SelectionResponse = self.send_request(request)?;
+        info!("Selected {} items", response.items.len());
+        Ok(response.items.len())
+    }
+    
+    /// Start a commit; changes made until `end_commit` form a single undo step in KiCad
+    #[instrument(skip(self))]
+    pub async fn begin_commit(&mut self) -> Result<String> {
+        let response: BeginCommitResponse = self.send_request(BeginCommit::new())?;
+        let id = response.id.get_or_default().value.clone();
+        debug!("Began commit {}", id);
+        Ok(id)
+    }
+    
+    /// Finish a commit started with `begin_commit`, keeping its changes or dropping them
+    #[instrument(skip(self))]
+    pub async fn end_commit(&mut self, id: &str, keep: bool, message: &str) -> Result<()> {
+        let mut request = EndCommit::new();
+        let mut kiid = KIID::new();
+        kiid.value = id.to_string();
+        request.id = Some(kiid).into();
+        request.action = if keep { CommitAction::CMA_COMMIT } else { CommitAction::CMA_DROP }.into();
+        request.message = message.to_string();
+        
+        let _: EndCommitResponse = self.send_request(request)?;
+        debug!("Ended commit {} ({})", id, if keep { "kept" } else { "dropped" });
+        Ok(())
+    }
+    
+    /// Submit a pcbnew tool action, as the matching menu entry or hotkey would. KiCad runs it
+    /// asynchronously, so `ActionStatus::Ok` means submitted rather than finished.
+    #[instrument(skip(self), fields(action = %action))]
+    pub async fn run_action(&mut self, action: &PcbAction) -> Result<ActionStatus> {
+        let mut request = RunAction::new();
+        request.action = action.name().to_string();
+        
+        let response: RunActionResponse = self.send_request(request)?;
+        let status = ActionStatus::from(response.status.enum_value_or_default());
+        if status.is_ok() {
+            debug!("Submitted action {}", action);
+        } else {
+            warn!("Action {} not run: {}", action, status);
+        }
+        Ok(status)
+    }
+    
+    /// Redraw the PCB editor, e.g. after changes it does not pick up by itself
+    #[instrument(skip(self))]
+    pub async fn refresh_editor(&mut self) -> Result<()> {
+        let mut request = RefreshEditor::new();
+        request.frame = FrameType::FT_PCB_EDITOR.into();
+        
+        let _: Empty = self.send_request(request)?;
+        debug!("Refreshed PCB editor");
+        Ok(())
+    }
+    
+    /// Get the layers shown in the PCB editor
+    #[instrument(skip(self))]
+    pub async fn get_visible_layers(&mut self) -> Result<Vec<PcbLayer>> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetVisibleLayers::new();
+        request.board = Some(doc.clone()).into();
+        
+        let response: BoardLayers = self.send_request(request)?;
+        Ok(response.layers.iter().map(|layer| PcbLayer::from(layer.enum_value_or_default())).collect())
+    }
+    
+    /// Show exactly `layers` in the PCB editor
+    #[instrument(skip(self, layers), fields(count = layers.len()))]
+    pub async fn set_visible_layers(&mut self, layers: &[PcbLayer]) -> Result<()> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = SetVisibleLayers::new();
+        request.board = Some(doc.clone()).into();
+        request.layers = layers.iter().map(|&layer| BoardLayer::from(layer).into()).collect();
+        
+        let _: Empty = self.send_request(request)?;
+        debug!("Showing {} layers", layers.len());
+        Ok(())
+    }
+    
+    /// Get the layer the PCB editor draws on
+    #[instrument(skip(self))]
+    pub async fn get_active_layer(&mut self) -> Result<PcbLayer> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetActiveLayer::new();
+        request.board = Some(doc.clone()).into();
+        
+        let response: BoardLayerResponse = self.send_request(request)?;
+        Ok(PcbLayer::from(response.layer.enum_value_or_default()))
+    }
+    
+    /// Make `layer` the layer the PCB editor draws on
+    #[instrument(skip(self))]
+    pub async fn set_active_layer(&mut self, layer: PcbLayer) -> Result<()> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = SetActiveLayer::new();
+        request.board = Some(doc.clone()).into();
+        request.layer = BoardLayer::from(layer).into();
+        
+        let _: Empty = self.send_request(request)?;
+        debug!("Active layer is now {}", layer);
+        Ok(())
+    }
+    
+    /// Get the appearance settings of the PCB editor
+    #[instrument(skip(self))]
+    pub async fn get_appearance(&mut self) -> Result<Appearance> {
+        let response: BoardEditorAppearanceSettings = self.send_request(GetBoardEditorAppearanceSettings::new())?;
+        Ok(Appearance::from(&response))
+    }
+    
+    /// Change the appearance settings of the PCB editor
+    #[instrument(skip(self))]
+    pub async fn set_appearance(&mut self, appearance: &Appearance) -> Result<()> {
+        let mut request = SetBoardEditorAppearanceSettings::new();
+        request.settings = Some(BoardEditorAppearanceSettings::from(appearance)).into();
+        
+        let _: Empty = self.send_request(request)?;
+        Ok(())
+    }
+    
+    /// The editor's whole view as a preset, e.g. to restore it after `apply_view_preset`
+    #[instrument(skip(self))]
+    pub async fn current_view(&mut self) -> Result<ViewPreset> {
+        let layers = self.get_visible_layers().await?;
+        let active_layer = self.get_active_layer().await?;
+        let appearance = self.get_appearance().await?;
+        Ok(ViewPreset {
+            layers: Some(layers),
+            active_layer: Some(active_layer),
+            inactive_layers: Some(appearance.inactive_layers),
+            net_colors: Some(appearance.net_colors),
+            flip: Some(appearance.flip),
+            ratsnest: Some(appearance.ratsnest),
+        })
+    }
+    
+    /// Switch the editor to the settings a preset sets. Returns the view as it was before, which
+    /// can be applied to undo the switch.
+    #[instrument(skip(self, preset))]
+    pub async fn apply_view_preset(&mut self, preset: &ViewPreset) -> Result<ViewPreset> {
+        let previous = self.current_view().await?;
+        
+        if let Some(layers) = &preset.layers {
+            self.set_visible_layers(layers).await?;
+        }
+        if let Some(layer) = preset.active_layer {
+            self.set_active_layer(layer).await?;
+        }
+        if preset.sets_appearance() {
+            let current = previous.apply_to(Appearance::default());
+            self.set_appearance(&preset.apply_to(current)).await?;
+        }
+        Ok(previous)
+    }
+    
+    /// Delete items by UUID. Returns the number KiCad reported as deleted.
+    #[instrument(skip(self, ids), fields(count = ids.len()))]
+    pub async fn delete_items(&mut self, ids: &[String]) -> Result<usize> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = DeleteItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        for id in ids {
+            let mut kiid = KIID::new();
+            kiid.value = id.clone();
+            request.item_ids.push(kiid);
+        }
+        
+        let response: DeleteItemsResponse = self.send_request(request)?;
+        let deleted = response.deleted_items.iter()
+            .filter(|result| result.status.enum_value_or_default() == ItemDeletionStatus::IDS_OK)
+            .count();
+        debug!("Deleted {} of {} items", deleted, ids.len());
+        Ok(deleted)
+    }
+    
+    /// Create items from packed board item messages. Returns the number KiCad created.
+    fn create_items(&mut self, doc: &DocumentSpecifier, items: Vec<Any>) -> Result<usize> {
+        let count = items.len();
+        let mut request = CreateItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        request.items = items;
+        
+        let response: CreateItemsResponse = self.send_request(request)?;
+        let created = response.created_items.iter()
+            .filter(|result| result.status.code.enum_value_or_default() == ItemStatusCode::ISC_OK)
+            .count();
+        if created < count {
+            warn!("KiCad created {} of {} items", created, count);
+        }
+        Ok(created)
+    }
+    
+    /// Get the pcbnew selection as clipboard text, e.g. for `Snippet::new`
+    #[instrument(skip(self))]
+    pub async fn save_selection_to_string(&mut self) -> Result<String> {
+        let response: SavedSelectionResponse = self.send_request(SaveSelectionToString::new())?;
+        info!("Saved {} selected items", response.ids.len());
+        Ok(response.contents)
+    }
+    
+    /// Paste clipboard text into the open board, as the editor's Paste action does. Returns the
+    /// number of items KiCad created.
+    #[instrument(skip(self, contents), fields(bytes = contents.len()))]
+    pub async fn parse_and_create_items(&mut self, contents: &str) -> Result<usize> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = ParseAndCreateItemsFromString::new();
+        request.document = Some(doc.clone()).into();
+        request.contents = contents.to_string();
+        
+        let response: CreateItemsResponse = self.send_request(request)?;
+        let created = response.created_items.iter()
+            .filter(|result| result.status.code.enum_value_or_default() == ItemStatusCode::ISC_OK)
+            .count();
+        info!("Created {} items from text", created);
+        Ok(created)
+    }
+    
+    /// Paste a copy of a snippet placed per `placement`, as a single commit. Returns the number
+    /// of items created.
+    #[instrument(skip(self, snippet, placement), fields(name = %snippet.name))]
+    pub async fn place_snippet(&mut self, snippet: &Snippet, placement: &SnippetPlacement) -> Result<usize> {
+        let contents = snippet.instantiate(placement)?;
+        let commit = self.begin_commit().await?;
+        match self.parse_and_create_items(&contents).await {
+            Ok(created) => {
+                self.end_commit(&commit, true, &format!("Place snippet {}", snippet.name)).await?;
+                Ok(created)
+            }
+            Err(e) => {
+                self.end_commit(&commit, false, "").await?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Replace the violation markers on the board with markers for `violations`, as a single
+    /// commit. The markers of a previous run (tagged labels and their circles, see
+    /// `MarkerStyle`) are removed first, so passing no violations just clears them; other
+    /// drawings on the marker layer are left alone. Returns the number of marker items removed
+    /// and created.
+    #[instrument(skip(self, violations, style), fields(count = violations.len(), layer = %style.layer))]
+    pub async fn replace_violation_markers(&mut self, violations: &[Violation], style: &MarkerStyle) -> Result<(usize, usize)> {
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?.clone();
+        
+        // Find the markers of a previous run
+        let mut request = GetItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        request.types.push(KiCadObjectType::KOT_PCB_SHAPE.into());
+        request.types.push(KiCadObjectType::KOT_PCB_TEXT.into());
+        let response: GetItemsResponse = self.send_request(request)?;
+        
+        let marker_layer = BoardLayer::from(style.layer);
+        let mut old_markers = Vec::new();
+        let mut label_positions = Vec::new();
+        let mut circles = Vec::new();
+        for item in &response.items {
+            if let Some(shape) = Any::unpack::<BoardGraphicShape>(item)? {
+                if shape.layer.enum_value_or_default() == marker_layer {
+                    circles.push((shape.id.get_or_default().value.clone(), geometry::Graphic::from(shape.shape.get_or_default())));
+                }
+            } else if let Some(text) = Any::unpack::<BoardText>(item)? {
+                let label = text.text.get_or_default();
+                if text.layer.enum_value_or_default() == marker_layer && style.is_label(&label.text) {
+                    old_markers.push(text.id.get_or_default().value.clone());
+                    label_positions.push(Point::from(label.position.get_or_default()));
+                }
+            }
+        }
+        // Circles only count as markers when drawn with one of the labels
+        old_markers.extend(circles.into_iter()
+            .filter(|(_, circle)| label_positions.iter().any(|&position| style.is_circle_of(circle, position)))
+            .map(|(id, _)| id));
+        
+        if old_markers.is_empty() && violations.is_empty() {
+            return Ok((0, 0));
+        }
+        
+        let mut markers = Vec::new();
+        for violation in violations {
+            let mut circle = BoardGraphicShape::new();
+            circle.shape = Some(GraphicShape::from(&style.circle(violation))).into();
+            circle.layer = marker_layer.into();
+            markers.push(Any::pack(&circle)?);
+            
+            let mut attributes = TextAttributes::new();
+            attributes.size = Some(Vector2::from(Point::new(style.text_size, style.text_size))).into();
+            attributes.stroke_width = Some(Distance::from(style.text_size / 8)).into();
+            attributes.horizontal_alignment = HorizontalAlignment::HA_LEFT.into();
+            attributes.vertical_alignment = VerticalAlignment::VA_BOTTOM.into();
+            attributes.visible = true;
+            let mut text = Text::new();
+            text.position = Some(Vector2::from(style.label_position(violation))).into();
+            text.attributes = Some(attributes).into();
+            text.text = style.label(violation);
+            let mut label = BoardText::new();
+            label.text = Some(text).into();
+            label.layer = marker_layer.into();
+            markers.push(Any::pack(&label)?);
+        }
+        
+        let commit = self.begin_commit().await?;
+        let result = async {
+            let removed = if old_markers.is_empty() { 0 } else { self.delete_items(&old_markers).await? };
+            let created = if markers.is_empty() { 0 } else { self.create_items(&doc, markers)? };
+            Ok::<_, eyre::Report>((removed, created))
+        }.await;
+        
+        match result {
+            Ok((removed, created)) => {
+                self.end_commit(&commit, true, "Update kicad-ecs DRC markers").await?;
+                info!("Removed {} and created {} marker items on {}", removed, created, style.layer);
+                Ok((removed, created))
+            }
+            Err(e) => {
+                self.end_commit(&commit, false, "").await?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Move footprints to new positions, identified by UUID
+    ///
+    /// Positions are written back in nanometers exactly as stored in the `Position` component.
+    /// Returns the number of footprints KiCad reported as updated.
+    #[instrument(skip(self, moves), fields(count = moves.len()))]
+    pub async fn update_footprint_positions(&mut self, moves: &[(String, Position)]) -> Result<usize> {
+        info!("Updating {} footprint positions", moves.len());
+        
+        let docs = self.get_open_documents().await?;
+        let doc = docs.first().ok_or(KiCadError::NoBoardOpen)?;
+        
+        let mut request = GetItems::new();
+        request.header = Some(ItemHeader::new()).into();
+        request.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        request.types.push(KiCadObjectType::KOT_PCB_FOOTPRINT.into());
+        let response: GetItemsResponse = self.send_request(request)?;
+        
+        let mut update = UpdateItems::new();
+        update.header = Some(ItemHeader::new()).into();
+        update.header.as_mut().unwrap().document = Some(doc.clone()).into();
+        
+        for item in &response.items {
+            let Some(mut footprint) = Any::unpack::<FootprintInstance>(item)? else {
+                continue;
+            };
+            let id = &footprint.id.get_or_default().value;
+            if let Some((_, position)) = moves.iter().find(|(uuid, _)| uuid == id) {
+                footprint.position = Some(Vector2::from(position.point())).into();
+                footprint.orientation = Some(protos::base_types::Angle::from(position.rotation)).into();
+                update.items.push(Any::pack(&footprint)?);
+            }
+        }
+        
+        if update.items.len() < moves.len() {
+            warn!("{} footprints to move were not found on the board", moves.len() - update.items.len());
+        }
+        
+        let response: UpdateItemsResponse = self.send_request(update)?;
+        let updated = response.updated_items.iter()
+            .filter(|result| result.status.code.enum_value_or_default() == ItemStatusCode::ISC_OK)
+            .count();
+        
+        info!("Updated {} footprints", updated);
+        Ok(updated)
+    }
+    
     /// Unpack a footprint from an Any message
     fn unpack_footprint(&self, any: &Any) -> Result<FootprintData> {
         let footprint_instance: FootprintInstance = Any::unpack(any)?
@@ -268,35 +1050,333 @@ impl KiCadClient {
             .and_then(|board_text| board_text.text.as_ref())
             .map(|text| text.text.clone())
             .filter(|s| !s.is_empty());
+        
+        // KiCad uses "~" for an empty datasheet field
+        let datasheet = footprint_instance.datasheet_field.as_ref()
+            .and_then(field_text)
+            .filter(|s| !s.is_empty() && s != "~");
+        
+        // Collect every field by name: the mandatory ones on the instance, plus user fields
+        // (MPN, Manufacturer, LCSC, ...) stored as Field items in the definition
+        let mut fields = BTreeMap::new();
+        let mandatory = [
+            ("Reference", &footprint_instance.reference_field),
+            ("Value", &footprint_instance.value_field),
+            ("Datasheet", &footprint_instance.datasheet_field),
+            ("Description", &footprint_instance.description_field),
+        ];
+        for (default_name, field) in mandatory {
+            if let Some(field) = field.as_ref() {
+                let name = if field.name.is_empty() { default_name.to_string() } else { field.name.clone() };
+                fields.insert(name, field_text(field).unwrap_or_default());
+            }
+        }
+        let definition = footprint_instance.definition.get_or_default();
+        let mut models = Vec::new();
+        let mut pads = Vec::new();
+        let mut courtyards = Vec::new();
+        for item in &definition.items {
+            if let Ok(Some(field)) = Any::unpack::<Field>(item) {
+                if !field.name.is_empty() {
+                    fields.insert(field.name.clone(), field_text(&field).unwrap_or_default());
+                }
+            } else if let Ok(Some(pad)) = Any::unpack::<Pad>(item) {
+                pads.push(unpack_pad(&pad));
+            } else if let Ok(Some(model)) = Any::unpack::<Footprint3DModel>(item) {
+                models.push(unpack_model(&model));
+            } else if let Ok(Some(shape)) = Any::unpack::<BoardGraphicShape>(item) {
+                let layer = PcbLayer::from(shape.layer.enum_value_or_default());
+                if matches!(layer, PcbLayer::FCrtYd | PcbLayer::BCrtYd) {
+                    courtyards.push((layer, geometry::Graphic::from(shape.shape.get_or_default())));
+                }
+            }
+        }
+        
+        let lib_id = definition.id.get_or_default();
+        let footprint_name = if lib_id.library_nickname.is_empty() {
+            lib_id.entry_name.clone()
+        } else {
+            format!("{}:{}", lib_id.library_nickname, lib_id.entry_name)
+        };
+        
+        let net_ties = definition.net_ties.iter()
+            .map(|tie| tie.pad_number.clone())
+            .filter(|pads| !pads.is_empty())
+            .collect();
 
         Ok(FootprintData {
             id: footprint_instance.id.get_or_default().value.clone(),
             reference,
             value,
-            footprint_name: footprint_instance.definition.get_or_default().id.get_or_default().entry_name.clone(),
-            position: (
-                position.x_nm as f64 / 1_000_000.0, // Convert nm to mm
-                position.y_nm as f64 / 1_000_000.0,
-            ),
-            rotation: orientation.value_degrees,
-            layer: self.layer_to_string(footprint_instance.layer.enum_value_or_default()),
+            footprint_name,
+            position: Point::from(position),
+            rotation: Angle::from_degrees(orientation.value_degrees),
+            layer: PcbLayer::from(footprint_instance.layer.enum_value_or_default()),
             description,
+            datasheet,
+            fields,
             exclude_from_bom: attributes.exclude_from_bill_of_materials,
             do_not_populate: attributes.do_not_populate,
             locked: footprint_instance.locked.enum_value_or_default() == LockedState::LS_LOCKED,
+            not_in_schematic: attributes.not_in_schematic,
+            exclude_from_position_files: attributes.exclude_from_position_files,
+            exempt_from_courtyard: attributes.exempt_from_courtyard_requirement,
+            mounting_style: match attributes.mounting_style.enum_value_or_default() {
+                FootprintMountingStyle::FMS_THROUGH_HOLE => MountingStyle::ThroughHole,
+                FootprintMountingStyle::FMS_SMD => MountingStyle::Smd,
+                _ => MountingStyle::Unspecified,
+            },
+            net_ties,
+            overrides: unpack_overrides(footprint_instance.overrides.get_or_default()),
+            models,
+            pads,
+            courtyards,
         })
     }
-    
-    /// Convert board layer enum to string
-    fn layer_to_string(&self, layer: BoardLayer) -> String {
+}
+
+/// Convert footprint design rule overrides, keeping only the values that are set
+fn unpack_overrides(overrides: &FootprintDesignRuleOverrides) -> DesignRuleOverrides {
+    DesignRuleOverrides {
+        solder_mask_margin: overrides.solder_mask.as_ref()
+            .and_then(|mask| mask.solder_mask_margin.as_ref())
+            .map(Length::from),
+        solder_paste_margin: overrides.solder_paste.as_ref()
+            .and_then(|paste| paste.solder_paste_margin.as_ref())
+            .map(Length::from),
+        solder_paste_margin_ratio: overrides.solder_paste.as_ref()
+            .and_then(|paste| paste.solder_paste_margin_ratio.as_ref())
+            .map(|ratio| ratio.value),
+        copper_clearance: overrides.copper_clearance.as_ref().map(Length::from),
+        zone_connection: zone_connection(overrides.zone_connection.enum_value_or_default()),
+    }
+}
+
+fn zone_connection(style: ZoneConnectionStyle) -> Option<ZoneConnection> {
+    match style {
+        ZoneConnectionStyle::ZCS_NONE => Some(ZoneConnection::None),
+        ZoneConnectionStyle::ZCS_THERMAL => Some(ZoneConnection::Thermal),
+        ZoneConnectionStyle::ZCS_FULL => Some(ZoneConnection::Full),
+        ZoneConnectionStyle::ZCS_PTH_THERMAL => Some(ZoneConnection::PthThermal),
+        // Inherited means the board rules apply, as for a footprint without an override
+        ZoneConnectionStyle::ZCS_INHERITED | ZoneConnectionStyle::ZCS_UNKNOWN => None,
+    }
+}
+
+/// Convert a footprint pad
+fn unpack_pad(pad: &Pad) -> PadInfo {
+    let net = pad.net.as_ref()
+        .map(|net| net.name.clone())
+        .filter(|name| !name.is_empty());
+    PadInfo {
+        id: pad.id.get_or_default().value.clone(),
+        number: pad.number.clone(),
+        kind: match pad.type_.enum_value_or_default() {
+            PadType::PT_SMD => PadKind::Smd,
+            PadType::PT_EDGE_CONNECTOR => PadKind::EdgeConnector,
+            PadType::PT_NPTH => PadKind::Npth,
+            _ => PadKind::Pth,
+        },
+        offset: Point::from(pad.position.get_or_default()),
+        net,
+        drill: pad_drill(pad),
+        size: pad_size(pad),
+        shapes: Vec::new(),
+        clearance: pad.copper_clearance_override.as_ref()
+            .map(Length::from)
+            .filter(|clearance| *clearance > Length::ZERO),
+    }
+}
+
+/// Hole diameter of a pad, taking the narrow side of slots
+fn pad_drill(pad: &Pad) -> Option<Length> {
+    let drill = pad_drill_diameter(pad.pad_stack.drill.get_or_default());
+    (drill > Length::ZERO).then_some(drill)
+}
+
+/// Narrow side of a drilled hole
+fn pad_drill_diameter(drill: &DrillProperties) -> Length {
+    let diameter = drill.diameter.get_or_default();
+    Length::from_nm(diameter.x_nm.min(diameter.y_nm))
+}
+
+/// Name of a net, or `None` for unconnected items
+fn net_name(net: &protobuf::MessageField<Net>) -> Option<String> {
+    net.as_ref().map(|net| net.name.clone()).filter(|name| !name.is_empty())
+}
+
+/// Largest copper shape of a pad over all its layers
+fn pad_size(pad: &Pad) -> Point {
+    pad.pad_stack.copper_layers
+        .iter()
+        .map(|layer| Point::from(layer.size.get_or_default()))
+        .max_by_key(|size| size.x.nm() as i128 * size.y.nm() as i128)
+        .unwrap_or_default()
+}
+
+/// Convert a footprint 3D model reference
+fn unpack_model(model: &Footprint3DModel) -> Model3D {
+    let scale = model.scale.get_or_default();
+    let rotation = model.rotation.get_or_default();
+    let offset = model.offset.get_or_default();
+    Model3D {
+        filename: model.filename.clone(),
+        scale: (scale.x_nm, scale.y_nm, scale.z_nm),
+        rotation: (
+            Angle::from_degrees(rotation.x_nm),
+            Angle::from_degrees(rotation.y_nm),
+            Angle::from_degrees(rotation.z_nm),
+        ),
+        offset: (
+            Length::from_nm(offset.x_nm.round() as i64),
+            Length::from_nm(offset.y_nm.round() as i64),
+            Length::from_nm(offset.z_nm.round() as i64),
+        ),
+        visible: model.visible,
+        opacity: model.opacity,
+    }
+}
+
+/// Text content of a footprint field
+fn field_text(field: &Field) -> Option<String> {
+    field.text.as_ref()
+        .and_then(|board_text| board_text.text.as_ref())
+        .map(|text| text.text.clone())
+}
+
+impl From<BoardLayer> for PcbLayer {
+    fn from(layer: BoardLayer) -> Self {
         match layer {
-            BoardLayer::BL_F_Cu => "F.Cu".to_string(),
-            BoardLayer::BL_B_Cu => "B.Cu".to_string(),
-            _ => format!("{:?}", layer),
+            BoardLayer::BL_F_Cu => PcbLayer::FCu,
+            BoardLayer::BL_B_Cu => PcbLayer::BCu,
+            BoardLayer::BL_F_Adhes => PcbLayer::FAdhes,
+            BoardLayer::BL_B_Adhes => PcbLayer::BAdhes,
+            BoardLayer::BL_F_Paste => PcbLayer::FPaste,
+            BoardLayer::BL_B_Paste => PcbLayer::BPaste,
+            BoardLayer::BL_F_SilkS => PcbLayer::FSilkS,
+            BoardLayer::BL_B_SilkS => PcbLayer::BSilkS,
+            BoardLayer::BL_F_Mask => PcbLayer::FMask,
+            BoardLayer::BL_B_Mask => PcbLayer::BMask,
+            BoardLayer::BL_Dwgs_User => PcbLayer::DwgsUser,
+            BoardLayer::BL_Cmts_User => PcbLayer::CmtsUser,
+            BoardLayer::BL_Eco1_User => PcbLayer::Eco1User,
+            BoardLayer::BL_Eco2_User => PcbLayer::Eco2User,
+            BoardLayer::BL_Edge_Cuts => PcbLayer::EdgeCuts,
+            BoardLayer::BL_Margin => PcbLayer::Margin,
+            BoardLayer::BL_F_CrtYd => PcbLayer::FCrtYd,
+            BoardLayer::BL_B_CrtYd => PcbLayer::BCrtYd,
+            BoardLayer::BL_F_Fab => PcbLayer::FFab,
+            BoardLayer::BL_B_Fab => PcbLayer::BFab,
+            BoardLayer::BL_Rescue => PcbLayer::Rescue,
+            BoardLayer::BL_UNKNOWN | BoardLayer::BL_UNDEFINED | BoardLayer::BL_UNSELECTED => PcbLayer::Undefined,
+            // Inner copper and user layers are numbered consecutively in the proto
+            other => match other.value() {
+                v @ 4..=33 => PcbLayer::InCu((v - 3) as u8),
+                v @ 53..=61 => PcbLayer::User((v - 52) as u8),
+                _ => PcbLayer::Undefined,
+            },
         }
     }
 }
 
+impl From<PcbLayer> for BoardLayer {
+    fn from(layer: PcbLayer) -> Self {
+        match layer {
+            PcbLayer::FCu => BoardLayer::BL_F_Cu,
+            // Inner copper and user layers are numbered consecutively in the proto
+            PcbLayer::InCu(n) if (1..=PcbLayer::MAX_INNER_COPPER).contains(&n) => {
+                BoardLayer::from_i32(3 + n as i32).unwrap_or(BoardLayer::BL_UNDEFINED)
+            }
+            PcbLayer::BCu => BoardLayer::BL_B_Cu,
+            PcbLayer::FAdhes => BoardLayer::BL_F_Adhes,
+            PcbLayer::BAdhes => BoardLayer::BL_B_Adhes,
+            PcbLayer::FPaste => BoardLayer::BL_F_Paste,
+            PcbLayer::BPaste => BoardLayer::BL_B_Paste,
+            PcbLayer::FSilkS => BoardLayer::BL_F_SilkS,
+            PcbLayer::BSilkS => BoardLayer::BL_B_SilkS,
+            PcbLayer::FMask => BoardLayer::BL_F_Mask,
+            PcbLayer::BMask => BoardLayer::BL_B_Mask,
+            PcbLayer::DwgsUser => BoardLayer::BL_Dwgs_User,
+            PcbLayer::CmtsUser => BoardLayer::BL_Cmts_User,
+            PcbLayer::Eco1User => BoardLayer::BL_Eco1_User,
+            PcbLayer::Eco2User => BoardLayer::BL_Eco2_User,
+            PcbLayer::EdgeCuts => BoardLayer::BL_Edge_Cuts,
+            PcbLayer::Margin => BoardLayer::BL_Margin,
+            PcbLayer::FCrtYd => BoardLayer::BL_F_CrtYd,
+            PcbLayer::BCrtYd => BoardLayer::BL_B_CrtYd,
+            PcbLayer::FFab => BoardLayer::BL_F_Fab,
+            PcbLayer::BFab => BoardLayer::BL_B_Fab,
+            PcbLayer::User(n) if (1..=PcbLayer::MAX_USER).contains(&n) => {
+                BoardLayer::from_i32(52 + n as i32).unwrap_or(BoardLayer::BL_UNDEFINED)
+            }
+            PcbLayer::Rescue => BoardLayer::BL_Rescue,
+            PcbLayer::InCu(_) | PcbLayer::User(_) | PcbLayer::Undefined => BoardLayer::BL_UNDEFINED,
+        }
+    }
+}
+
+impl From<RunActionStatus> for ActionStatus {
+    fn from(status: RunActionStatus) -> Self {
+        match status {
+            RunActionStatus::RAS_OK => ActionStatus::Ok,
+            RunActionStatus::RAS_INVALID => ActionStatus::Invalid,
+            RunActionStatus::RAS_FRAME_NOT_OPEN => ActionStatus::FrameNotOpen,
+            RunActionStatus::RAS_UNKNOWN => ActionStatus::Unknown,
+        }
+    }
+}
+
+impl From<&BoardEditorAppearanceSettings> for Appearance {
+    fn from(settings: &BoardEditorAppearanceSettings) -> Self {
+        // Unknown modes fall back to the editor defaults
+        Self {
+            inactive_layers: match settings.inactive_layer_display.enum_value_or_default() {
+                InactiveLayerDisplayMode::ILDM_DIMMED => InactiveLayerDisplay::Dimmed,
+                InactiveLayerDisplayMode::ILDM_HIDDEN => InactiveLayerDisplay::Hidden,
+                _ => InactiveLayerDisplay::Normal,
+            },
+            net_colors: match settings.net_color_display.enum_value_or_default() {
+                NetColorDisplayMode::NCDM_RATSNEST => NetColorDisplay::Ratsnest,
+                NetColorDisplayMode::NCDM_OFF => NetColorDisplay::Off,
+                _ => NetColorDisplay::All,
+            },
+            flip: match settings.board_flip.enum_value_or_default() {
+                BoardFlipMode::BFM_FLIPPED_X => BoardFlip::Flipped,
+                _ => BoardFlip::Normal,
+            },
+            ratsnest: match settings.ratsnest_display.enum_value_or_default() {
+                RatsnestDisplayMode::RDM_VISIBLE_LAYERS => RatsnestDisplay::VisibleLayers,
+                _ => RatsnestDisplay::AllLayers,
+            },
+        }
+    }
+}
+
+impl From<&Appearance> for BoardEditorAppearanceSettings {
+    fn from(appearance: &Appearance) -> Self {
+        let mut settings = BoardEditorAppearanceSettings::new();
+        settings.inactive_layer_display = match appearance.inactive_layers {
+            InactiveLayerDisplay::Normal => InactiveLayerDisplayMode::ILDM_NORMAL,
+            InactiveLayerDisplay::Dimmed => InactiveLayerDisplayMode::ILDM_DIMMED,
+            InactiveLayerDisplay::Hidden => InactiveLayerDisplayMode::ILDM_HIDDEN,
+        }.into();
+        settings.net_color_display = match appearance.net_colors {
+            NetColorDisplay::All => NetColorDisplayMode::NCDM_ALL,
+            NetColorDisplay::Ratsnest => NetColorDisplayMode::NCDM_RATSNEST,
+            NetColorDisplay::Off => NetColorDisplayMode::NCDM_OFF,
+        }.into();
+        settings.board_flip = match appearance.flip {
+            BoardFlip::Normal => BoardFlipMode::BFM_NORMAL,
+            BoardFlip::Flipped => BoardFlipMode::BFM_FLIPPED_X,
+        }.into();
+        settings.ratsnest_display = match appearance.ratsnest {
+            RatsnestDisplay::AllLayers => RatsnestDisplayMode::RDM_ALL_LAYERS,
+            RatsnestDisplay::VisibleLayers => RatsnestDisplayMode::RDM_VISIBLE_LAYERS,
+        }.into();
+        settings
+    }
+}
+
 /// KiCad version information
 #[derive(Debug, Clone)]
 pub struct KiCadVersionInfo {
@@ -332,20 +1412,307 @@ pub struct FootprintData {
     pub reference: String,
     pub value: String,
     pub footprint_name: String,
-    pub position: (f64, f64),  // x, y in millimeters
-    pub rotation: f64,         // degrees
-    pub layer: String,
+    pub position: Point,
+    pub rotation: Angle,
+    pub layer: PcbLayer,
     pub description: Option<String>,
+    pub datasheet: Option<String>,
+    pub fields: BTreeMap<String, String>,  // field name -> text, including Reference/Value
     pub exclude_from_bom: bool,
     pub do_not_populate: bool,
     pub locked: bool,
+    pub not_in_schematic: bool,
+    pub exclude_from_position_files: bool,
+    pub exempt_from_courtyard: bool,
+    pub mounting_style: MountingStyle,
+    pub net_ties: Vec<Vec<String>>,  // pad numbers per net tie group
+    pub overrides: DesignRuleOverrides,
+    pub models: Vec<Model3D>,
+    pub pads: Vec<PadInfo>,
+    pub courtyards: Vec<(PcbLayer, geometry::Graphic)>,  // courtyard layer graphics, relative to the footprint
+}
+
+/// Net data from KiCad
+#[derive(Debug, Clone)]
+pub struct NetData {
+    pub code: i32,
+    pub name: String,
+}
+
+/// A board-level graphic shape from KiCad
+#[derive(Debug, Clone)]
+pub struct BoardShapeData {
+    pub id: String,
+    pub layer: PcbLayer,
+    pub graphic: geometry::Graphic,
+    pub net: Option<String>,
+}
+
+/// A copper track or arc track from KiCad
+#[derive(Debug, Clone)]
+pub struct TrackData {
+    pub id: String,
+    pub layer: PcbLayer,
+    pub start: Point,
+    pub mid: Option<Point>,  // set for arc tracks
+    pub end: Point,
+    pub width: Length,
+    pub net: Option<String>,
+}
+
+/// A via from KiCad
+#[derive(Debug, Clone)]
+pub struct ViaData {
+    pub id: String,
+    pub position: Point,
+    pub drill: Length,
+    pub diameter: Length,  // largest copper pad over all layers
+    pub via_type: ViaKind,
+    pub start_layer: PcbLayer,
+    pub end_layer: PcbLayer,
+    pub net: Option<String>,
+}
+
+/// A zone from KiCad
+#[derive(Debug, Clone)]
+pub struct ZoneData {
+    pub id: String,
+    pub name: String,
+    pub kind: ZoneKind,
+    pub layers: Vec<PcbLayer>,
+    pub outline: geometry::PolySet,
+    pub priority: u32,
+    pub filled: bool,
+    pub net: Option<String>,  // copper zones only
 }
 
 // Coordinate conversion utilities (KiCad uses nanometers internally)
 pub fn to_mm(nanometers: i64) -> f64 {
-    nanometers as f64 / 1_000_000.0
+    Length::from_nm(nanometers).mm()
 }
 
 pub fn from_mm(millimeters: f64) -> i64 {
-    (millimeters * 1_000_000.0) as i64
-}
\ No newline at end of file
+    Length::from_mm(millimeters).nm()
+}
+
+impl From<&Vector2> for Point {
+    fn from(v: &Vector2) -> Self {
+        Point::from_nm(v.x_nm, v.y_nm)
+    }
+}
+
+impl From<Point> for Vector2 {
+    fn from(p: Point) -> Self {
+        let mut v = Vector2::new();
+        v.x_nm = p.x.nm();
+        v.y_nm = p.y.nm();
+        v
+    }
+}
+
+impl From<&Distance> for Length {
+    fn from(d: &Distance) -> Self {
+        Length::from_nm(d.value_nm)
+    }
+}
+
+impl From<Length> for Distance {
+    fn from(l: Length) -> Self {
+        let mut d = Distance::new();
+        d.value_nm = l.nm();
+        d
+    }
+}
+
+impl From<&protos::base_types::Angle> for Angle {
+    fn from(a: &protos::base_types::Angle) -> Self {
+        Angle::from_degrees(a.value_degrees)
+    }
+}
+
+impl From<Angle> for protos::base_types::Angle {
+    fn from(a: Angle) -> Self {
+        let mut angle = protos::base_types::Angle::new();
+        angle.value_degrees = a.degrees();
+        angle
+    }
+}
+impl From<&PolyLine> for geometry::PolyLine {
+    fn from(line: &PolyLine) -> Self {
+        let nodes = line.nodes.iter()
+            .filter_map(|node| match node.geometry.as_ref()? {
+                poly_line_node::Geometry::Point(point) => Some(geometry::PolyLineNode::Point(point.into())),
+                poly_line_node::Geometry::Arc(arc) => Some(geometry::PolyLineNode::Arc {
+                    start: arc.start.get_or_default().into(),
+                    mid: arc.mid.get_or_default().into(),
+                    end: arc.end.get_or_default().into(),
+                }),
+            })
+            .collect();
+        geometry::PolyLine { nodes, closed: line.closed }
+    }
+}
+
+impl From<&PolygonWithHoles> for geometry::PolygonWithHoles {
+    fn from(polygon: &PolygonWithHoles) -> Self {
+        geometry::PolygonWithHoles {
+            outline: polygon.outline.get_or_default().into(),
+            holes: polygon.holes.iter().map(geometry::PolyLine::from).collect(),
+        }
+    }
+}
+
+impl From<&PolySet> for geometry::PolySet {
+    fn from(set: &PolySet) -> Self {
+        geometry::PolySet {
+            polygons: set.polygons.iter().map(geometry::PolygonWithHoles::from).collect(),
+        }
+    }
+}
+
+impl From<&GraphicShape> for geometry::Graphic {
+    fn from(graphic: &GraphicShape) -> Self {
+        use protos::base_types::graphic_shape::Geometry;
+        
+        let point = |v: &protobuf::MessageField<Vector2>| Point::from(v.get_or_default());
+        let shape = match graphic.geometry.as_ref() {
+            Some(Geometry::Segment(s)) => geometry::GraphicShape::Segment { start: point(&s.start), end: point(&s.end) },
+            Some(Geometry::Rectangle(r)) => geometry::GraphicShape::Rectangle {
+                top_left: point(&r.top_left),
+                bottom_right: point(&r.bottom_right),
+            },
+            Some(Geometry::Arc(a)) => geometry::GraphicShape::Arc { start: point(&a.start), mid: point(&a.mid), end: point(&a.end) },
+            Some(Geometry::Circle(c)) => geometry::GraphicShape::Circle {
+                center: point(&c.center),
+                radius_point: point(&c.radius_point),
+            },
+            Some(Geometry::Polygon(set)) => geometry::GraphicShape::Polygon(set.into()),
+            Some(Geometry::Bezier(b)) => geometry::GraphicShape::Bezier {
+                start: point(&b.start),
+                control1: point(&b.control1),
+                control2: point(&b.control2),
+                end: point(&b.end),
+            },
+            None => geometry::GraphicShape::Polygon(geometry::PolySet::default()),
+        };
+        let attributes = graphic.attributes.get_or_default();
+        geometry::Graphic {
+            shape,
+            stroke_width: Length::from(attributes.stroke.width.get_or_default()),
+            filled: attributes.fill.fill_type.enum_value_or_default() == GraphicFillType::GFT_FILLED,
+        }
+    }
+}
+
+impl From<&NetClass> for NetClassRules {
+    fn from(class: &NetClass) -> Self {
+        let board = class.board.get_or_default();
+        let distance = |value: &protobuf::MessageField<Distance>| value.as_ref().map(|d| Length::from_nm(d.value_nm));
+        let via_stack = board.via_stack.as_ref();
+        Self {
+            name: class.name.clone(),
+            clearance: distance(&board.clearance),
+            track_width: distance(&board.track_width),
+            via_diameter: via_stack
+                .and_then(|stack| stack.copper_layers.first())
+                .map(|layer| Length::from_nm(layer.size.get_or_default().x_nm))
+                .filter(|size| *size > Length::ZERO),
+            via_drill: via_stack
+                .map(|stack| pad_drill_diameter(stack.drill.get_or_default()))
+                .filter(|drill| *drill > Length::ZERO),
+            diff_pair_width: distance(&board.diff_pair_track_width),
+            diff_pair_gap: distance(&board.diff_pair_gap),
+        }
+    }
+}
+
+impl From<&geometry::PolyLine> for PolyLine {
+    fn from(line: &geometry::PolyLine) -> Self {
+        let mut result = PolyLine::new();
+        result.closed = line.closed;
+        for node in &line.nodes {
+            let mut proto_node = PolyLineNode::new();
+            proto_node.geometry = Some(match node {
+                geometry::PolyLineNode::Point(point) => poly_line_node::Geometry::Point(Vector2::from(*point)),
+                geometry::PolyLineNode::Arc { start, mid, end } => {
+                    let mut arc = ArcStartMidEnd::new();
+                    arc.start = Some(Vector2::from(*start)).into();
+                    arc.mid = Some(Vector2::from(*mid)).into();
+                    arc.end = Some(Vector2::from(*end)).into();
+                    poly_line_node::Geometry::Arc(arc)
+                }
+            });
+            result.nodes.push(proto_node);
+        }
+        result
+    }
+}
+
+impl From<&geometry::PolySet> for PolySet {
+    fn from(set: &geometry::PolySet) -> Self {
+        let mut result = PolySet::new();
+        for polygon in &set.polygons {
+            let mut proto_polygon = PolygonWithHoles::new();
+            proto_polygon.outline = Some(PolyLine::from(&polygon.outline)).into();
+            proto_polygon.holes = polygon.holes.iter().map(PolyLine::from).collect();
+            result.polygons.push(proto_polygon);
+        }
+        result
+    }
+}
+
+impl From<&geometry::Graphic> for GraphicShape {
+    fn from(graphic: &geometry::Graphic) -> Self {
+        use protos::base_types::graphic_shape::Geometry;
+        
+        let vector = |point: &Point| -> protobuf::MessageField<Vector2> { Some(Vector2::from(*point)).into() };
+        let mut result = GraphicShape::new();
+        result.geometry = Some(match &graphic.shape {
+            geometry::GraphicShape::Segment { start, end } => {
+                let mut segment = GraphicSegmentAttributes::new();
+                segment.start = vector(start);
+                segment.end = vector(end);
+                Geometry::Segment(segment)
+            }
+            geometry::GraphicShape::Rectangle { top_left, bottom_right } => {
+                let mut rectangle = GraphicRectangleAttributes::new();
+                rectangle.top_left = vector(top_left);
+                rectangle.bottom_right = vector(bottom_right);
+                Geometry::Rectangle(rectangle)
+            }
+            geometry::GraphicShape::Arc { start, mid, end } => {
+                let mut arc = GraphicArcAttributes::new();
+                arc.start = vector(start);
+                arc.mid = vector(mid);
+                arc.end = vector(end);
+                Geometry::Arc(arc)
+            }
+            geometry::GraphicShape::Circle { center, radius_point } => {
+                let mut circle = GraphicCircleAttributes::new();
+                circle.center = vector(center);
+                circle.radius_point = vector(radius_point);
+                Geometry::Circle(circle)
+            }
+            geometry::GraphicShape::Polygon(set) => Geometry::Polygon(PolySet::from(set)),
+            geometry::GraphicShape::Bezier { start, control1, control2, end } => {
+                let mut bezier = GraphicBezierAttributes::new();
+                bezier.start = vector(start);
+                bezier.control1 = vector(control1);
+                bezier.control2 = vector(control2);
+                bezier.end = vector(end);
+                Geometry::Bezier(bezier)
+            }
+        });
+        
+        let mut attributes = GraphicAttributes::new();
+        let mut stroke = StrokeAttributes::new();
+        stroke.width = Some(Distance::from(graphic.stroke_width)).into();
+        stroke.style = StrokeLineStyle::SLS_SOLID.into();
+        attributes.stroke = Some(stroke).into();
+        let mut fill = GraphicFillAttributes::new();
+        fill.fill_type = if graphic.filled { GraphicFillType::GFT_FILLED } else { GraphicFillType::GFT_UNFILLED }.into();
+        attributes.fill = Some(fill).into();
+        result.attributes = Some(attributes).into();
+        result
+    }
+}
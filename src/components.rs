@@ -1,17 +1,91 @@
 //! ECS Components for KiCad PCB data
 
 use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use crate::geometry::{arc_points, PolySet, Shape, Tessellation};
+use crate::layers::{PcbLayer, Side};
+use crate::mounting::HoleFit;
+use crate::units::{Angle, Length, Point};
 
 /// Position and orientation of a PCB component
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Position {
-    pub x: f64,          // millimeters
-    pub y: f64,          // millimeters  
-    pub rotation: f64,   // degrees
+    pub x: Length,
+    pub y: Length,
+    pub rotation: Angle,
+}
+
+impl Position {
+    pub fn new(point: Point, rotation: Angle) -> Self {
+        Self { x: point.x, y: point.y, rotation }
+    }
+
+    /// The position as a point, without rotation
+    pub fn point(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+}
+
+/// Axis-aligned bounding box of an item in board coordinates
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    /// The smallest box containing both corners, in any order
+    pub fn new(a: Point, b: Point) -> Self {
+        Self {
+            min: Point::new(a.x.min(b.x), a.y.min(b.y)),
+            max: Point::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    /// A square box of the given size centered on a point
+    pub fn around(center: Point, size: Length) -> Self {
+        let half = size / 2;
+        Self::new(
+            Point::new(center.x - half, center.y - half),
+            Point::new(center.x + half, center.y + half),
+        )
+    }
+
+    pub fn width(&self) -> Length {
+        self.max.x - self.min.x
+    }
+
+    pub fn height(&self) -> Length {
+        self.max.y - self.min.y
+    }
+
+    pub fn center(&self) -> Point {
+        Point::new((self.min.x + self.max.x) / 2, (self.min.y + self.max.y) / 2)
+    }
+
+    /// Whether a point lies inside or on the edge of this box
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    /// Whether two boxes overlap or touch
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+
+    /// This box grown by `margin` on every side
+    pub fn expanded(&self, margin: Length) -> Self {
+        Self {
+            min: Point::new(self.min.x - margin, self.min.y - margin),
+            max: Point::new(self.max.x + margin, self.max.y + margin),
+        }
+    }
 }
 
 /// Basic component information
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentInfo {
     pub reference: String,        // R1, C2, U3, etc.
     pub value: String,           // 10k, 100nF, etc.
@@ -19,52 +93,432 @@ pub struct ComponentInfo {
 }
 
 /// Component description/comment
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentDescription {
     pub description: String,
 }
 
+/// Datasheet link or path
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Datasheet {
+    pub url: String,
+}
+
+/// All footprint fields by name, including Reference, Value and user fields like MPN
+#[derive(Component, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fields {
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Fields {
+    /// Look up a field by name, ignoring case ("MPN", "mpn" and "Mpn" all match)
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .get(name)
+            .or_else(|| {
+                self.fields
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value)
+            })
+            .map(|value| value.as_str())
+    }
+
+    /// Look up the first non-empty field among several candidate names
+    pub fn get_any(&self, names: &[&str]) -> Option<&str> {
+        names
+            .iter()
+            .filter_map(|name| self.get(name))
+            .find(|value| !value.is_empty() && *value != "~")
+    }
+}
+
 /// Layer information
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Layer {
-    pub layer_name: String,  // F.Cu, B.Cu, etc.
+    pub id: PcbLayer,
+}
+
+impl Layer {
+    /// Which side of the board the item is on, if any
+    pub fn side(&self) -> Option<Side> {
+        self.id.side()
+    }
+
+    /// Whether the item is on a copper layer
+    pub fn is_copper(&self) -> bool {
+        self.id.is_copper()
+    }
 }
 
 /// Component flags and attributes
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ComponentFlags {
     pub exclude_from_bom: bool,
     pub do_not_populate: bool,
     pub locked: bool,
+    pub not_in_schematic: bool,
+    pub exclude_from_position_files: bool,
+    pub exempt_from_courtyard: bool,
+}
+
+/// How a footprint is mounted, as set in its attributes
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MountingStyle {
+    ThroughHole,
+    Smd,
+    Unspecified,
+}
+
+/// Groups of pad numbers that are allowed to short different nets together
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct NetTies {
+    pub groups: Vec<Vec<String>>,
+}
+
+impl NetTies {
+    /// The index of the group a pad number belongs to
+    pub fn group_of(&self, pad: &str) -> Option<usize> {
+        self.groups.iter().position(|group| group.iter().any(|number| number == pad))
+    }
+}
+
+/// How copper zones connect to pads
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ZoneConnection {
+    None,
+    Thermal,
+    Full,
+    /// Thermal reliefs for plated through holes, solid for SMD pads
+    PthThermal,
+}
+
+/// Footprint-level design rule overrides; `None` means the board rules apply
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DesignRuleOverrides {
+    pub solder_mask_margin: Option<Length>,
+    pub solder_paste_margin: Option<Length>,
+    pub solder_paste_margin_ratio: Option<f64>,
+    pub copper_clearance: Option<Length>,
+    pub zone_connection: Option<ZoneConnection>,
+}
+
+impl DesignRuleOverrides {
+    /// Whether any rule is overridden
+    pub fn is_empty(&self) -> bool {
+        self.solder_mask_margin.is_none()
+            && self.solder_paste_margin.is_none()
+            && self.solder_paste_margin_ratio.is_none()
+            && self.copper_clearance.is_none()
+            && self.zone_connection.is_none()
+    }
+}
+
+/// A 3D model attached to a footprint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Model3D {
+    pub filename: String,
+    pub scale: (f64, f64, f64),
+    pub rotation: (Angle, Angle, Angle),         // around X, Y, Z
+    pub offset: (Length, Length, Length),        // from footprint origin
+    pub visible: bool,
+    pub opacity: f64,                            // 0.0 to 1.0
+}
+
+/// 3D models attached to a footprint
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Models3D {
+    pub models: Vec<Model3D>,
 }
 
 /// Unique identifier
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
 pub struct ComponentId {
     pub uuid: String,
 }
 
-/// Mounting hole specific data
-#[derive(Component, Debug, Clone)]
+/// Electrical type of a pad
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PadKind {
+    /// Plated through hole
+    Pth,
+    Smd,
+    EdgeConnector,
+    /// Non-plated through hole
+    Npth,
+}
+
+/// A single footprint pad
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pad {
+    pub id: String,
+    pub number: String,      // 1, 2, A4, etc.; empty for unnumbered mechanical pads
+    pub kind: PadKind,
+    pub offset: Point,       // relative to the footprint origin, as reported by KiCad
+    pub net: Option<String>,
+    pub drill: Option<Length>, // hole diameter (narrow side for slots)
+    pub size: Point,         // largest copper shape size over all layers
+    pub shapes: Vec<(PcbLayer, Shape)>, // copper outline per layer in board coordinates, once loaded
+    pub clearance: Option<Length>, // copper clearance override of this pad
+}
+
+impl Pad {
+    /// Position of the pad on the board, given its footprint's position
+    pub fn board_position(&self, footprint: &Position) -> Point {
+        (footprint.point() + self.offset).rotated(footprint.rotation, &footprint.point())
+    }
+
+    /// Whether the pad has a drilled hole
+    pub fn is_through_hole(&self) -> bool {
+        matches!(self.kind, PadKind::Pth | PadKind::Npth)
+    }
+
+    /// The copper outline on a layer, if loaded
+    pub fn shape_on(&self, layer: PcbLayer) -> Option<&Shape> {
+        self.shapes.iter().find(|(on, _)| *on == layer).map(|(_, shape)| shape)
+    }
+}
+
+/// The pads of a footprint
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Pads {
+    pub pads: Vec<Pad>,
+}
+
+/// Mounting hole specific data, derived from the hole's pad by `mounting::detect_mounting_hole`
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MountingHole {
-    pub diameter_mm: f64,
-    pub screw_size: String,  // M2, M3, M4, etc.
+    pub diameter: Length,
+    pub plated: bool,
+    pub pad_diameter: Option<Length>,  // copper pad around a plated hole
+    pub annulus: Option<Length>,       // copper ring width
+    pub screw_size: Option<String>,    // M3, #4-40, etc.; None when the hole matches no screw
+    pub fit: Option<HoleFit>,
+    pub keepout_diameter: Length,      // washer footprint, at least the pad
+}
+
+/// Courtyard areas of a footprint in board coordinates, assembled from its courtyard layer
+/// graphics
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Courtyard {
+    pub front: Option<Shape>,
+    pub back: Option<Shape>,
+}
+
+impl Courtyard {
+    /// The courtyard on one side of the board
+    pub fn on(&self, side: Side) -> Option<&Shape> {
+        match side {
+            Side::Front => self.front.as_ref(),
+            Side::Back => self.back.as_ref(),
+        }
+    }
+}
+
+/// Height of a part above the board surface
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Height {
+    pub height: Length,
+}
+
+// ===== Electrical values =====
+// Parsed from `ComponentInfo.value` by `values::apply_parsed_values`
+
+/// Resistance in ohms
+#[derive(Component, Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Resistance {
+    pub ohms: f64,
+}
+
+/// Capacitance in farads
+#[derive(Component, Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Capacitance {
+    pub farads: f64,
+}
+
+/// Inductance in henries
+#[derive(Component, Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Inductance {
+    pub henries: f64,
+}
+
+/// Rated voltage in volts
+#[derive(Component, Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct VoltageRating {
+    pub volts: f64,
+}
+
+/// Value tolerance in percent (±)
+#[derive(Component, Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Tolerance {
+    pub percent: f64,
+}
+
+/// Ceramic capacitor dielectric (EIA temperature characteristic)
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Dielectric {
+    /// C0G, also written NP0
+    C0G,
+    X5R,
+    X6S,
+    X7R,
+    X7S,
+    X7T,
+    X8R,
+    Y5V,
+    Z5U,
 }
 
 // ===== Component type markers =====
 
 /// Marker component for resistors
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Resistor;
 
 /// Marker component for capacitors
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Capacitor;
 
+/// Marker component for inductors and ferrite beads
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Inductor;
+
+/// Marker component for diodes and LEDs
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Diode;
+
+/// Marker component for transistors
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transistor;
+
 /// Marker component for integrated circuits
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IntegratedCircuit;
 
 /// Marker component for connectors
-#[derive(Component, Debug, Clone)]
-pub struct Connector;
\ No newline at end of file
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Connector;
+
+/// Marker component for crystals and oscillators
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Crystal;
+
+/// Marker component for switches
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Switch;
+
+/// Marker component for test points
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestPoint;
+
+
+// ===== Routing and graphics =====
+
+/// A copper track segment, or an arc track when `mid` is set; the entity also has `Layer` and
+/// usually `Net`
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub start: Point,
+    pub mid: Option<Point>,
+    pub end: Point,
+    pub width: Length,
+}
+
+impl Track {
+    /// Centerline of the track, with arcs approximated per `tessellation`
+    pub fn path(&self, tessellation: &Tessellation) -> Vec<Point> {
+        match self.mid {
+            Some(mid) => arc_points(self.start, mid, self.end, tessellation),
+            None => vec![self.start, self.end],
+        }
+    }
+
+    /// Copper covered by the track
+    pub fn to_shape(&self, tessellation: &Tessellation) -> Shape {
+        Shape::stroke(&self.path(tessellation), false, self.width, tessellation)
+    }
+}
+
+/// How far a via reaches through the stackup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ViaType {
+    Through,
+    BlindBuried,
+    Micro,
+}
+
+/// A via; its center is the entity's `Position`
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Via {
+    pub drill: Length,
+    pub diameter: Length,
+    pub via_type: ViaType,
+    pub start_layer: PcbLayer,
+    pub end_layer: PcbLayer,
+}
+
+impl Via {
+    /// Width of the copper ring around the hole
+    pub fn annular_ring(&self) -> Length {
+        (self.diameter - self.drill) / 2
+    }
+
+    /// Copper pad of the via around `center`
+    pub fn to_shape(&self, center: Point, tessellation: &Tessellation) -> Shape {
+        Shape::stroke(&[center], false, self.diameter, tessellation)
+    }
+
+    /// The outermost copper layers the via connects, front-most first
+    pub fn layer_span(&self) -> (PcbLayer, PcbLayer) {
+        (self.start_layer.min(self.end_layer), self.start_layer.max(self.end_layer))
+    }
+}
+
+/// What a zone is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ZoneKind {
+    Copper,
+    RuleArea,
+    Graphic,
+}
+
+/// A copper pour or rule area; copper zones also have `Net`
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Zone {
+    pub name: String,
+    pub kind: ZoneKind,
+    pub layers: Vec<PcbLayer>,
+    pub outline: PolySet,
+    pub priority: u32,
+    pub filled: bool,
+}
+
+/// Marker for board-level graphics on a silkscreen layer
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Silkscreen;
+
+
+// ===== Connectivity =====
+
+/// An electrical net
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Net {
+    pub name: String,
+}
+
+/// A symbol pin or footprint pad, identified by reference and number
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PinRef {
+    pub reference: String,  // R1, U3, etc.
+    pub number: String,     // 1, 2, A4, etc.
+}
+
+/// Pins connected to a net
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct NetPins {
+    pub pins: Vec<PinRef>,
+}
+
+/// Marker component for nets derived from the schematic
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct SchematicNet;
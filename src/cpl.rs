@@ -0,0 +1,323 @@
+//! Pick-and-place (component placement list) export
+//!
+//! Placement files list each part's reference, center and rotation, one file per board side.
+//! Coordinates are given relative to a chosen origin with the Y axis pointing up, as assemblers
+//! expect, rather than KiCad's downward Y. The origin is KiCad's board origin, the drill/place
+//! origin set in the board editor (`"aux"`, from the `AuxOrigin` resource) or the lower left
+//! corner of the board outline.
+//!
+//! Footprint libraries don't always draw parts in the orientation an assembler's feeders hold
+//! them, so rotation offsets can be configured per footprint glob. The first matching offset is
+//! added to the footprint rotation on the front side and subtracted on the back, where the part
+//! is seen mirrored. Offsets can be loaded from TOML:
+//!
+//! ```toml
+//! origin = "outline_lower_left"
+//!
+//! [[rotation_offsets]]
+//! footprint = "Package_TO_SOT_SMD:SOT-23*"
+//! degrees = 180
+//! ```
+
+use bevy_ecs::prelude::*;
+use eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
+use crate::bom::compare_references;
+use crate::classify::glob_match;
+use crate::components::*;
+use crate::layers::Side;
+use crate::outline::BoardOutline;
+use crate::units::{Angle, Point};
+use crate::variant::EffectivePart;
+
+/// The point placement coordinates are measured from
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CplOrigin {
+    /// KiCad's absolute board coordinates
+    #[default]
+    Board,
+    /// The drill/place (auxiliary axis) origin of the board, see `AuxOrigin`
+    Aux,
+    /// The lower left corner of the board outline's bounding box
+    OutlineLowerLeft,
+}
+
+/// The board's drill/place (auxiliary axis) origin in board coordinates
+///
+/// Inserted when a board is loaded from a `.kicad_pcb` file or document; KiCad's API does not
+/// report it for boards loaded item by item.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct AuxOrigin {
+    pub point: Point,
+}
+
+/// Placement file layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CplFormat {
+    /// KiCad's CSV position file: Ref, Val, Package, PosX, PosY, Rot, Side
+    KiCad,
+    /// JLCPCB's CPL: Designator, Mid X, Mid Y, Layer, Rotation
+    Jlcpcb,
+    /// Designator, Value, Footprint, X, Y, Rotation, Side
+    Generic,
+}
+
+/// A rotation correction for footprints matching a glob
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RotationOffset {
+    /// Glob matched against the footprint library id ("Library:Footprint")
+    pub footprint: String,
+    pub degrees: f64,
+}
+
+/// What goes into a placement file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct CplOptions {
+    pub origin: CplOrigin,
+    /// Tried in order; the first match applies
+    pub rotation_offsets: Vec<RotationOffset>,
+    /// Place do-not-populate parts too
+    pub include_dnp: bool,
+}
+
+impl CplOptions {
+    /// Parse options from TOML; missing keys keep their defaults
+    pub fn from_toml_str(config: &str) -> Result<Self> {
+        toml::from_str(config).wrap_err("Invalid placement config")
+    }
+
+    /// Load options from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read placement config {}", path.display()))?;
+        Self::from_toml_str(&config)
+            .wrap_err_with(|| format!("Failed to load placement config {}", path.display()))
+    }
+
+    /// The rotation offset for a footprint library id
+    pub fn rotation_offset(&self, footprint: &str) -> Angle {
+        self.rotation_offsets
+            .iter()
+            .find(|offset| glob_match(&offset.footprint, footprint))
+            .map(|offset| Angle::from_degrees(offset.degrees))
+            .unwrap_or(Angle::ZERO)
+    }
+}
+
+/// One placed part
+#[derive(Debug, Clone, PartialEq)]
+pub struct CplEntry {
+    pub entity: Entity,
+    pub reference: String,
+    pub value: String,
+    pub footprint: String,
+    /// Relative to the origin, Y up
+    pub position: Point,
+    /// Footprint rotation with any offset applied, in [0, 360)
+    pub rotation: Angle,
+    pub side: Side,
+}
+
+impl CplEntry {
+    /// The footprint name without its library
+    pub fn package(&self) -> &str {
+        self.footprint.rsplit(':').next().unwrap_or(&self.footprint)
+    }
+}
+
+/// A component placement list for both sides of a board
+#[derive(Debug, Clone, Default)]
+pub struct Cpl {
+    pub entries: Vec<CplEntry>,
+}
+
+impl Cpl {
+    /// Parts on one side, in reference order
+    pub fn side(&self, side: Side) -> impl Iterator<Item = &CplEntry> {
+        self.entries.iter().filter(move |entry| entry.side == side)
+    }
+
+    /// The placement file for one side
+    pub fn to_csv(&self, format: CplFormat, side: Side) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        match format {
+            CplFormat::KiCad => writer.write_record(["Ref", "Val", "Package", "PosX", "PosY", "Rot", "Side"])?,
+            CplFormat::Jlcpcb => writer.write_record(["Designator", "Mid X", "Mid Y", "Layer", "Rotation"])?,
+            CplFormat::Generic => writer.write_record(["Designator", "Value", "Footprint", "X", "Y", "Rotation", "Side"])?,
+        }
+        for entry in self.side(side) {
+            let (x, y) = (entry.position.x.mm(), entry.position.y.mm());
+            let rotation = format!("{:.1}", entry.rotation.degrees());
+            let record = match format {
+                CplFormat::KiCad => vec![
+                    entry.reference.clone(), entry.value.clone(), entry.package().to_string(),
+                    format!("{:.4}", x), format!("{:.4}", y), format!("{:.4}", entry.rotation.degrees()),
+                    side_name(side).to_lowercase(),
+                ],
+                CplFormat::Jlcpcb => vec![
+                    entry.reference.clone(), format!("{:.4}mm", x), format!("{:.4}mm", y),
+                    side_name(side).to_string(), rotation,
+                ],
+                CplFormat::Generic => vec![
+                    entry.reference.clone(), entry.value.clone(), entry.footprint.clone(),
+                    format!("{:.4}", x), format!("{:.4}", y), rotation, side_name(side).to_string(),
+                ],
+            };
+            writer.write_record(&record)?;
+        }
+        let bytes = writer.into_inner().wrap_err("Failed to flush placement CSV")?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    /// Write `<stem>-top.csv` and `<stem>-bottom.csv` into `dir`, skipping a side without parts.
+    /// Returns the paths written.
+    pub fn write_sides(&self, dir: impl AsRef<Path>, stem: &str, format: CplFormat) -> Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+        for side in [Side::Front, Side::Back] {
+            if self.side(side).next().is_none() {
+                continue;
+            }
+            let path = dir.as_ref().join(format!("{}-{}.csv", stem, side_name(side).to_lowercase()));
+            std::fs::write(&path, self.to_csv(format, side)?)
+                .wrap_err_with(|| format!("Failed to write placement file {}", path.display()))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Front => "Top",
+        Side::Back => "Bottom",
+    }
+}
+
+/// Resolve an origin to board coordinates, from the world's `AuxOrigin` and `BoardOutline`
+pub fn origin_point(origin: CplOrigin, world: &World) -> Result<Point> {
+    match origin {
+        CplOrigin::Board => Ok(Point::ORIGIN),
+        CplOrigin::Aux => world
+            .get_resource::<AuxOrigin>()
+            .map(|aux| aux.point)
+            .ok_or_else(|| eyre!("Placement origin is the drill/place origin, but the board has none loaded")),
+        CplOrigin::OutlineLowerLeft => world
+            .get_resource::<BoardOutline>()
+            .and_then(BoardOutline::bounding_box)
+            // Y grows downward in KiCad, so the lower edge is the maximum Y
+            .map(|bounds| Point::new(bounds.min.x, bounds.max.y))
+            .ok_or_else(|| eyre!("Placement origin is the board outline, but there is no outline")),
+    }
+}
+
+/// Build the placement list of the world's footprints. Footprints flagged
+/// `exclude_from_position_files`, and DNP ones unless `include_dnp` is set, are left out. DNP
+/// flags and values come from `EffectivePart` when a variant has been applied.
+#[instrument(skip(world))]
+pub fn generate_cpl(world: &mut World, options: &CplOptions) -> Result<Cpl> {
+    let origin = origin_point(options.origin, world)?;
+    let mut excluded = 0;
+
+    let mut query = world.query::<(Entity, &ComponentInfo, &Position, &Layer, Option<&ComponentFlags>, Option<&EffectivePart>)>();
+    let mut entries: Vec<CplEntry> = query
+        .iter(world)
+        .filter_map(|(entity, info, position, layer, flags, part)| {
+            let part = EffectivePart::resolve(part, info, None, flags);
+            let Some(side) = layer.side() else {
+                warn!("{} is on {}, which is on neither side; left out of the placement list", info.reference, layer.id.canonical_name());
+                excluded += 1;
+                return None;
+            };
+            if flags.is_some_and(|flags| flags.exclude_from_position_files) || (part.dnp && !options.include_dnp) {
+                excluded += 1;
+                return None;
+            }
+            let offset = options.rotation_offset(&info.footprint_name);
+            let rotation = match side {
+                Side::Front => position.rotation + offset,
+                Side::Back => position.rotation - offset,
+            };
+            Some(CplEntry {
+                entity,
+                reference: info.reference.clone(),
+                value: part.value,
+                footprint: info.footprint_name.clone(),
+                position: Point::new(position.x - origin.x, origin.y - position.y),
+                rotation: rotation.normalized(),
+                side,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| compare_references(&a.reference, &b.reference));
+
+    info!("Placement list has {} parts; {} footprints excluded", entries.len(), excluded);
+    Ok(Cpl { entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::part;
+    use crate::layers::PcbLayer;
+
+    const SOT23: &str = "Package_TO_SOT_SMD:SOT-23";
+
+    /// Two SOT-23s, one per side, a resistor and a test point excluded from position files,
+    /// with the drill/place origin at (100, 100) mm
+    fn board() -> World {
+        let mut world = World::new();
+        part("Q1").value("v").footprint(SOT23).at(Point::from_mm(110.0, 90.0)).rotated(90.0).spawn(&mut world);
+        part("Q2").value("v").footprint(SOT23).at(Point::from_mm(120.0, 95.0)).rotated(90.0).layer(PcbLayer::BCu).spawn(&mut world);
+        part("R1").value("v").footprint("Resistor_SMD:R_0603").at(Point::from_mm(105.0, 80.0)).spawn(&mut world);
+        part("TP1").value("v").footprint("TestPoint:TP").at(Point::from_mm(100.0, 80.0))
+            .flags(ComponentFlags { exclude_from_position_files: true, ..Default::default() })
+            .spawn(&mut world);
+        world.insert_resource(AuxOrigin { point: Point::from_mm(100.0, 100.0) });
+        world
+    }
+
+    fn options() -> CplOptions {
+        CplOptions::from_toml_str(r#"
+            origin = "aux"
+
+            [[rotation_offsets]]
+            footprint = "Package_TO_SOT_SMD:SOT-23*"
+            degrees = 180
+        "#).unwrap()
+    }
+
+    #[test]
+    fn places_front_parts_from_aux_origin() {
+        let cpl = generate_cpl(&mut board(), &options()).unwrap();
+        assert_eq!(cpl.entries.len(), 3);
+        // Y points up from the origin; the SOT-23 offset adds to the front rotation
+        assert_eq!(cpl.to_csv(CplFormat::Jlcpcb, Side::Front).unwrap(), concat!(
+            "Designator,Mid X,Mid Y,Layer,Rotation\n",
+            "Q1,10.0000mm,10.0000mm,Top,270.0\n",
+            "R1,5.0000mm,20.0000mm,Top,0.0\n",
+        ));
+    }
+
+    #[test]
+    fn subtracts_rotation_offsets_on_the_back() {
+        let cpl = generate_cpl(&mut board(), &options()).unwrap();
+        assert_eq!(cpl.to_csv(CplFormat::KiCad, Side::Back).unwrap(), concat!(
+            "Ref,Val,Package,PosX,PosY,Rot,Side\n",
+            "Q2,v,SOT-23,20.0000,5.0000,270.0000,bottom\n",
+        ));
+    }
+
+    #[test]
+    fn origins_need_their_source() {
+        let mut world = board();
+        assert!(generate_cpl(&mut world, &CplOptions { origin: CplOrigin::OutlineLowerLeft, ..Default::default() }).is_err());
+        world.remove_resource::<AuxOrigin>();
+        assert!(generate_cpl(&mut world, &options()).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Decoupling capacitor analysis
+//!
+//! For every power pin of an `IntegratedCircuit`, the `Capacitor`s between the pin's rail and a
+//! ground net are ranked by distance from the pin. For each, the number of vias on the routed
+//! path to the pin is found by walking the rail's copper (tracks, vias and pads that touch),
+//! taking the path with the fewest vias. The result is stored on the IC as a `Decoupling`
+//! component, and pins with no capacitor on their rail, or whose nearest capacitor is too far or
+//! too many vias away, are reported as `Violation`s.
+//!
+//! Power pins are recognized by net name (see `values::is_power_net`), since pads carry no
+//! electrical pin type.
+
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tracing::debug;
+use crate::components::{Capacitance, Capacitor, ComponentId, ComponentInfo, IntegratedCircuit, Pads, Position};
+use crate::drc::{collect_copper, CopperItem, CopperKind, FootprintQuery, Severity, TrackQuery, ViaQuery, Violation};
+use crate::units::{Length, Point};
+use crate::values::{format_si, is_ground_net, is_power_net};
+
+pub const DECOUPLING_MISSING: &str = "decoupling_missing";
+pub const DECOUPLING_DISTANCE: &str = "decoupling_distance";
+
+/// Limits used by `decoupling_rule`
+#[derive(Resource, Debug, Clone)]
+pub struct DecouplingRules {
+    /// Furthest a power pin's nearest capacitor may be, pad center to pad center
+    pub max_distance: Length,
+    /// Most vias allowed on the routed path between a power pin and its nearest capacitor
+    pub max_vias: usize,
+    /// How many of the nearest capacitors to keep per pin in `Decoupling`
+    pub candidates: usize,
+}
+
+impl Default for DecouplingRules {
+    fn default() -> Self {
+        Self {
+            max_distance: Length::from_mm(3.0),
+            max_vias: 2,
+            candidates: 3,
+        }
+    }
+}
+
+/// A capacitor decoupling a power pin
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecouplingCapacitor {
+    pub entity: Entity,
+    pub reference: String,
+    pub farads: Option<f64>,
+    pub distance: Length,     // from the pin to the capacitor's rail pad
+    pub vias: Option<usize>,  // on the routed path to the pin; None when not routed to it
+}
+
+/// A power pin of an IC and its nearest capacitors, nearest first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerPin {
+    pub pad: String,
+    pub net: String,
+    pub position: Point,
+    pub capacitors: Vec<DecouplingCapacitor>,
+}
+
+impl PowerPin {
+    /// The capacitor decoupling this pin, if any
+    pub fn nearest(&self) -> Option<&DecouplingCapacitor> {
+        self.capacitors.first()
+    }
+}
+
+/// Decoupling of each power pin of an IC, written by `decoupling_rule`
+#[derive(Component, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Decoupling {
+    pub pins: Vec<PowerPin>,
+}
+
+/// A capacitor pad on a rail, with the capacitor's other pad on ground
+struct RailCapacitor<'a> {
+    entity: Entity,
+    reference: &'a str,
+    pad_id: &'a str,
+    position: Point,
+    farads: Option<f64>,
+}
+
+/// The copper of one net, as items connected where they touch on a shared layer
+struct RailGraph<'a> {
+    items: Vec<&'a CopperItem>,
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl<'a> RailGraph<'a> {
+    fn new(items: Vec<&'a CopperItem>) -> Self {
+        let mut neighbors = vec![Vec::new(); items.len()];
+        for (i, a) in items.iter().enumerate() {
+            for (j, b) in items.iter().enumerate().skip(i + 1) {
+                if a.shares_layer(b) && a.bounds.intersects(&b.bounds) && a.shape.intersects(&b.shape) {
+                    neighbors[i].push(j);
+                    neighbors[j].push(i);
+                }
+            }
+        }
+        Self { items, neighbors }
+    }
+
+    fn index_of(&self, item_id: &str) -> Option<usize> {
+        self.items.iter().position(|item| item.item_id == item_id)
+    }
+
+    /// Fewest vias on any path from `start` to each item; entering a via costs one
+    fn via_counts(&self, start: usize) -> Vec<Option<usize>> {
+        let mut counts = vec![None; self.items.len()];
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((index, count)) = queue.pop_front() {
+            if counts[index].is_some_and(|best| best <= count) {
+                continue;
+            }
+            counts[index] = Some(count);
+            for &next in &self.neighbors[index] {
+                if self.items[next].kind == CopperKind::Via {
+                    queue.push_back((next, count + 1));
+                } else {
+                    queue.push_front((next, count));
+                }
+            }
+        }
+        counts
+    }
+}
+
+type CapacitorQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static ComponentInfo, &'static Position, &'static Pads, Option<&'static Capacitance>), With<Capacitor>>;
+
+/// Find the decoupling capacitors of every IC power pin, store them as `Decoupling` and report
+/// pins that are missing decoupling or decoupled from too far away
+#[allow(clippy::too_many_arguments)]
+pub fn decoupling_rule(
+    mut commands: Commands,
+    rules: Res<DecouplingRules>,
+    ics: Query<(Entity, &ComponentInfo, &Position, &Pads), With<IntegratedCircuit>>,
+    capacitors: CapacitorQuery,
+    ids: Query<&ComponentId>,
+    tracks: TrackQuery,
+    vias: ViaQuery,
+    footprints: FootprintQuery,
+) {
+    // Capacitors by the rail they decouple
+    let mut rail_capacitors: HashMap<&str, Vec<RailCapacitor>> = HashMap::new();
+    for (entity, info, position, pads, capacitance) in &capacitors {
+        if !pads.pads.iter().any(|pad| pad.net.as_deref().is_some_and(is_ground_net)) {
+            continue;
+        }
+        for pad in &pads.pads {
+            let Some(net) = pad.net.as_deref().filter(|net| !is_ground_net(net)) else { continue };
+            rail_capacitors.entry(net).or_default().push(RailCapacitor {
+                entity,
+                reference: &info.reference,
+                pad_id: &pad.id,
+                position: pad.board_position(position),
+                farads: capacitance.map(|capacitance| capacitance.farads),
+            });
+        }
+    }
+
+    let copper = collect_copper(&tracks, &vias, &footprints);
+    let mut graphs: HashMap<&str, RailGraph> = HashMap::new();
+    let mut pin_count = 0;
+
+    for (ic, info, position, pads) in &ics {
+        let mut pins = Vec::new();
+        for pad in &pads.pads {
+            let Some(net) = pad.net.as_deref().filter(|net| is_power_net(net)) else { continue };
+            pin_count += 1;
+            let pin_position = pad.board_position(position);
+            let graph = graphs.entry(net).or_insert_with(|| {
+                RailGraph::new(copper.iter().filter(|item| item.net.as_deref() == Some(net)).collect())
+            });
+            let via_counts = graph.index_of(&pad.id).map(|start| graph.via_counts(start));
+
+            let mut found: Vec<DecouplingCapacitor> = rail_capacitors
+                .get(net)
+                .into_iter()
+                .flatten()
+                .map(|capacitor| DecouplingCapacitor {
+                    entity: capacitor.entity,
+                    reference: capacitor.reference.to_string(),
+                    farads: capacitor.farads,
+                    distance: pin_position.distance(&capacitor.position),
+                    vias: via_counts
+                        .as_ref()
+                        .zip(graph.index_of(capacitor.pad_id))
+                        .and_then(|(counts, index)| counts[index]),
+                })
+                .collect();
+            found.sort_by_key(|capacitor| capacitor.distance);
+            found.truncate(rules.candidates);
+
+            let pin_items = |extra: Option<&DecouplingCapacitor>| {
+                let mut entities = vec![ic];
+                let mut items = vec![pad.id.clone()];
+                if let Some(capacitor) = extra {
+                    entities.push(capacitor.entity);
+                    items.extend(ids.get(capacitor.entity).map(|id| id.uuid.clone()));
+                }
+                (entities, items)
+            };
+            match found.first() {
+                None => {
+                    let (entities, items) = pin_items(None);
+                    commands.spawn(Violation {
+                        rule: DECOUPLING_MISSING.to_string(),
+                        severity: Severity::Warning,
+                        message: format!("{} pin {} ({}) has no capacitor to ground on its rail", info.reference, pad.number, net),
+                        location: pin_position,
+                        entities,
+                        items,
+                    });
+                }
+                Some(nearest) if nearest.distance > rules.max_distance || nearest.vias.is_some_and(|vias| vias > rules.max_vias) => {
+                    let value = nearest.farads.map(|farads| format!(" ({})", format_si(farads, "F"))).unwrap_or_default();
+                    let vias = nearest.vias.map(|vias| format!(", {} vias", vias)).unwrap_or_default();
+                    let (entities, items) = pin_items(Some(nearest));
+                    commands.spawn(Violation {
+                        rule: DECOUPLING_DISTANCE.to_string(),
+                        severity: Severity::Warning,
+                        message: format!(
+                            "{} pin {} ({}) is decoupled by {}{} at {}{}; at most {} and {} vias allowed",
+                            info.reference, pad.number, net, nearest.reference, value, nearest.distance, vias,
+                            rules.max_distance, rules.max_vias,
+                        ),
+                        location: pin_position,
+                        entities,
+                        items,
+                    });
+                }
+                Some(_) => {}
+            }
+
+            pins.push(PowerPin { pad: pad.number.clone(), net: net.to_string(), position: pin_position, capacitors: found });
+        }
+        commands.entity(ic).insert(Decoupling { pins });
+    }
+    debug!("Analyzed decoupling of {} power pins on {} rails", pin_count, graphs.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+    use crate::fixtures::{pad_with_copper, part, spawn_track, spawn_via};
+    use crate::layers::PcbLayer;
+
+    fn mm(x: f64, y: f64) -> Point {
+        Point::from_mm(x, y)
+    }
+
+    /// A part with a pad per (net, offset), numbered from 1
+    fn spawn_part(world: &mut World, reference: &str, at: Point, pins: &[(&str, Point)]) -> Entity {
+        let pads = pins
+            .iter()
+            .enumerate()
+            .map(|(i, (net, offset))| pad_with_copper(&(i + 1).to_string(), net, *offset, at + *offset, Length::from_mm(1.0)))
+            .collect();
+        part(reference).at(at).pads(pads).spawn(world)
+    }
+
+    fn violations(world: &mut World) -> Vec<(&str, Vec<Entity>)> {
+        let mut query = world.query::<&Violation>();
+        let mut found: Vec<(&str, Vec<Entity>)> = query.iter(world).map(|v| (v.rule.as_str(), v.entities.clone())).collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn follows_the_rail_through_vias_to_a_distant_capacitor() {
+        let mut world = World::new();
+        world.init_resource::<DecouplingRules>();
+        let u1 = spawn_part(&mut world, "U1", mm(0.0, 0.0), &[("VCC", mm(0.0, 0.0)), ("GND", mm(-2.0, 0.0))]);
+        world.entity_mut(u1).insert(IntegratedCircuit);
+        let c1 = spawn_part(&mut world, "C1", mm(2.0, 5.0), &[("VCC", mm(0.0, 0.0)), ("GND", mm(1.0, 0.0))]);
+        world.entity_mut(c1).insert((Capacitor, Capacitance { farads: 100e-9 }));
+
+        // U1 pin 1 reaches C1 through two vias and the back layer
+        spawn_track(&mut world, "t1", "VCC", mm(0.0, 0.0), mm(0.0, 3.0), PcbLayer::FCu);
+        spawn_via(&mut world, "VCC", mm(0.0, 3.0));
+        spawn_track(&mut world, "t2", "VCC", mm(0.0, 3.0), mm(2.0, 3.0), PcbLayer::BCu);
+        spawn_via(&mut world, "VCC", mm(2.0, 3.0));
+        spawn_track(&mut world, "t3", "VCC", mm(2.0, 3.0), mm(2.0, 5.0), PcbLayer::FCu);
+
+        world.run_system_once(decoupling_rule);
+
+        let decoupling = world.get::<Decoupling>(u1).unwrap();
+        assert_eq!(decoupling.pins.len(), 1);
+        let nearest = decoupling.pins[0].nearest().unwrap();
+        assert_eq!((nearest.entity, nearest.vias), (c1, Some(2)));
+        assert_eq!(nearest.distance, mm(0.0, 0.0).distance(&mm(2.0, 5.0)));
+        assert_eq!(violations(&mut world), vec![(DECOUPLING_DISTANCE, vec![u1, c1])]);
+    }
+
+    #[test]
+    fn reports_power_pins_without_capacitors() {
+        let mut world = World::new();
+        world.init_resource::<DecouplingRules>();
+        let u2 = spawn_part(&mut world, "U2", mm(20.0, 0.0), &[("+3V3", mm(0.0, 0.0))]);
+        world.entity_mut(u2).insert(IntegratedCircuit);
+
+        world.run_system_once(decoupling_rule);
+
+        assert!(world.get::<Decoupling>(u2).unwrap().pins[0].capacitors.is_empty());
+        assert_eq!(violations(&mut world), vec![(DECOUPLING_MISSING, vec![u2])]);
+    }
+}
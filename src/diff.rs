@@ -0,0 +1,586 @@
+//! Board comparison
+//!
+//! `BoardDiff::between` compares two worlds, for example the live board against a saved copy or
+//! two revisions of a `.kicad_pcb`. Items are matched by `ComponentId` uuid, which KiCad keeps
+//! stable across edits. It reports:
+//!
+//! - footprints added, removed, moved, rotated, flipped or given another value
+//! - routing changes per net: tracks and vias added, removed or changed, and routed length
+//! - zones added, removed or with a changed outline, layers, net, priority or name (fill state
+//!   is ignored, since refilling changes it without any design change)
+//!
+//! A diff renders as plain text, JSON, or Markdown for pull request comments.
+
+use bevy_ecs::prelude::*;
+use eyre::Result;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Write};
+use tracing::{info, instrument};
+use crate::components::*;
+use crate::geometry::{PolySet, Tessellation};
+use crate::layers::{PcbLayer, Side};
+use crate::units::{Angle, Length, Point};
+
+/// Net name used for routing without a net
+pub const NO_NET: &str = "<no net>";
+
+/// One way a footprint changed
+#[derive(Debug, Clone, PartialEq)]
+pub enum FootprintChange {
+    Added,
+    Removed,
+    Moved { from: Point, to: Point },
+    Rotated { from: Angle, to: Angle },
+    Flipped { to: Side },
+    ValueChanged { from: String, to: String },
+}
+
+impl fmt::Display for FootprintChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FootprintChange::Added => write!(f, "added"),
+            FootprintChange::Removed => write!(f, "removed"),
+            FootprintChange::Moved { from, to } => write!(f, "moved {:.3} → {:.3}", from, to),
+            FootprintChange::Rotated { from, to } => write!(f, "rotated {} → {}", from, to),
+            FootprintChange::Flipped { to: Side::Front } => write!(f, "flipped to front"),
+            FootprintChange::Flipped { to: Side::Back } => write!(f, "flipped to back"),
+            FootprintChange::ValueChanged { from, to } => write!(f, "value {} → {}", from, to),
+        }
+    }
+}
+
+/// The changes to one footprint
+#[derive(Debug, Clone, PartialEq)]
+pub struct FootprintDiff {
+    pub uuid: String,
+    pub reference: String,
+    pub changes: Vec<FootprintChange>,
+}
+
+/// Routing changes on one net
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetRoutingDiff {
+    pub net: String,
+    pub tracks_added: usize,
+    pub tracks_removed: usize,
+    pub tracks_changed: usize,
+    pub vias_added: usize,
+    pub vias_removed: usize,
+    pub vias_changed: usize,
+    /// Total track length before and after
+    pub length_before: Length,
+    pub length_after: Length,
+}
+
+impl fmt::Display for NetRoutingDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "tracks +{} -{} ~{}, vias +{} -{} ~{}, length {:.3} → {:.3}",
+            self.tracks_added, self.tracks_removed, self.tracks_changed,
+            self.vias_added, self.vias_removed, self.vias_changed,
+            self.length_before, self.length_after,
+        )
+    }
+}
+
+/// One way a zone changed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZoneChange {
+    Added,
+    Removed,
+    OutlineChanged,
+    LayersChanged { from: Vec<PcbLayer>, to: Vec<PcbLayer> },
+    NetChanged { from: Option<String>, to: Option<String> },
+    PriorityChanged { from: u32, to: u32 },
+    Renamed { from: String, to: String },
+}
+
+impl fmt::Display for ZoneChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let layers = |layers: &[PcbLayer]| layers.iter().map(PcbLayer::canonical_name).collect::<Vec<_>>().join(",");
+        let net = |net: &Option<String>| net.clone().unwrap_or_else(|| NO_NET.to_string());
+        match self {
+            ZoneChange::Added => write!(f, "added"),
+            ZoneChange::Removed => write!(f, "removed"),
+            ZoneChange::OutlineChanged => write!(f, "outline changed"),
+            ZoneChange::LayersChanged { from, to } => write!(f, "layers {} → {}", layers(from), layers(to)),
+            ZoneChange::NetChanged { from, to } => write!(f, "net {} → {}", net(from), net(to)),
+            ZoneChange::PriorityChanged { from, to } => write!(f, "priority {} → {}", from, to),
+            ZoneChange::Renamed { from, to } => write!(f, "renamed {:?} → {:?}", from, to),
+        }
+    }
+}
+
+/// The changes to one zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneDiff {
+    pub uuid: String,
+    pub name: String,
+    pub net: Option<String>,
+    pub changes: Vec<ZoneChange>,
+}
+
+impl ZoneDiff {
+    /// Name and net, for display
+    pub fn label(&self) -> String {
+        match (&self.net, self.name.is_empty()) {
+            (Some(net), true) => format!("zone on {}", net),
+            (Some(net), false) => format!("zone {:?} on {}", self.name, net),
+            (None, true) => "unnamed zone".to_string(),
+            (None, false) => format!("zone {:?}", self.name),
+        }
+    }
+}
+
+/// Everything that differs between two boards
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BoardDiff {
+    pub footprints: Vec<FootprintDiff>,
+    pub routing: Vec<NetRoutingDiff>,
+    pub zones: Vec<ZoneDiff>,
+}
+
+// ===== Snapshots =====
+
+struct FootprintState {
+    reference: String,
+    value: String,
+    position: Point,
+    rotation: Angle,
+    side: Option<Side>,
+}
+
+#[derive(PartialEq)]
+enum RouteGeometry {
+    Track(Track, PcbLayer),
+    Via(Via, Point),
+}
+
+struct RouteState {
+    net: String,
+    geometry: RouteGeometry,
+    length: Length,
+}
+
+struct ZoneState {
+    name: String,
+    net: Option<String>,
+    layers: Vec<PcbLayer>,
+    outline: PolySet,
+    priority: u32,
+}
+
+struct Snapshot {
+    footprints: HashMap<String, FootprintState>,
+    routes: HashMap<String, RouteState>,
+    zones: HashMap<String, ZoneState>,
+}
+
+impl Snapshot {
+    fn take(world: &mut World) -> Self {
+        let tessellation = world.get_resource::<Tessellation>().copied().unwrap_or_default();
+
+        let mut query = world.query::<(&ComponentId, &ComponentInfo, &Position, Option<&Layer>)>();
+        let footprints = query
+            .iter(world)
+            .map(|(id, info, position, layer)| {
+                (id.uuid.clone(), FootprintState {
+                    reference: info.reference.clone(),
+                    value: info.value.clone(),
+                    position: position.point(),
+                    rotation: position.rotation,
+                    side: layer.and_then(Layer::side),
+                })
+            })
+            .collect();
+
+        let net_name = |net: Option<&Net>| net.map_or(NO_NET.to_string(), |net| net.name.clone());
+        let mut routes = HashMap::new();
+        let mut tracks = world.query::<(&ComponentId, &Track, &Layer, Option<&Net>)>();
+        for (id, track, layer, net) in tracks.iter(world) {
+            let length = track.path(&tessellation).windows(2).map(|pair| pair[0].distance(&pair[1])).sum();
+            let geometry = RouteGeometry::Track(track.clone(), layer.id);
+            routes.insert(id.uuid.clone(), RouteState { net: net_name(net), geometry, length });
+        }
+        let mut vias = world.query::<(&ComponentId, &Via, &Position, Option<&Net>)>();
+        for (id, via, position, net) in vias.iter(world) {
+            let geometry = RouteGeometry::Via(via.clone(), position.point());
+            routes.insert(id.uuid.clone(), RouteState { net: net_name(net), geometry, length: Length::ZERO });
+        }
+
+        let mut query = world.query::<(&ComponentId, &Zone, Option<&Net>)>();
+        let zones = query
+            .iter(world)
+            .map(|(id, zone, net)| {
+                (id.uuid.clone(), ZoneState {
+                    name: zone.name.clone(),
+                    net: net.map(|net| net.name.clone()),
+                    layers: zone.layers.clone(),
+                    outline: zone.outline.clone(),
+                    priority: zone.priority,
+                })
+            })
+            .collect();
+
+        Self { footprints, routes, zones }
+    }
+}
+
+/// Uuids in either map, sorted
+fn all_keys<'a, A, B>(old: &'a HashMap<String, A>, new: &'a HashMap<String, B>) -> BTreeSet<&'a str> {
+    old.keys().chain(new.keys()).map(String::as_str).collect()
+}
+
+fn same_angle(a: Angle, b: Angle) -> bool {
+    let delta = (a - b).normalized().degrees();
+    delta.min(360.0 - delta) < 1e-6
+}
+
+// ===== Comparison =====
+
+impl BoardDiff {
+    /// Compare `old` against `new`
+    #[instrument(skip(old, new))]
+    pub fn between(old: &mut World, new: &mut World) -> Self {
+        let (old, new) = (Snapshot::take(old), Snapshot::take(new));
+        let diff = Self {
+            footprints: diff_footprints(&old, &new),
+            routing: diff_routing(&old, &new),
+            zones: diff_zones(&old, &new),
+        };
+        info!(
+            "Board diff: {} footprints, {} nets, {} zones changed",
+            diff.footprints.len(), diff.routing.len(), diff.zones.len()
+        );
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.footprints.is_empty() && self.routing.is_empty() && self.zones.is_empty()
+    }
+}
+
+fn diff_footprints(old: &Snapshot, new: &Snapshot) -> Vec<FootprintDiff> {
+    let mut diffs: Vec<FootprintDiff> = all_keys(&old.footprints, &new.footprints)
+        .into_iter()
+        .filter_map(|uuid| {
+            let (reference, changes) = match (old.footprints.get(uuid), new.footprints.get(uuid)) {
+                (None, Some(after)) => (&after.reference, vec![FootprintChange::Added]),
+                (Some(before), None) => (&before.reference, vec![FootprintChange::Removed]),
+                (Some(before), Some(after)) => {
+                    let mut changes = Vec::new();
+                    if before.position != after.position {
+                        changes.push(FootprintChange::Moved { from: before.position, to: after.position });
+                    }
+                    if !same_angle(before.rotation, after.rotation) {
+                        changes.push(FootprintChange::Rotated { from: before.rotation, to: after.rotation });
+                    }
+                    if let (Some(from), Some(to)) = (before.side, after.side) {
+                        if from != to {
+                            changes.push(FootprintChange::Flipped { to });
+                        }
+                    }
+                    if before.value != after.value {
+                        changes.push(FootprintChange::ValueChanged { from: before.value.clone(), to: after.value.clone() });
+                    }
+                    (&after.reference, changes)
+                }
+                (None, None) => return None,
+            };
+            (!changes.is_empty()).then(|| FootprintDiff { uuid: uuid.to_string(), reference: reference.clone(), changes })
+        })
+        .collect();
+    diffs.sort_by(|a, b| crate::bom::compare_references(&a.reference, &b.reference));
+    diffs
+}
+
+enum Tally {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl NetRoutingDiff {
+    fn tally(&mut self, route: &RouteState, tally: Tally) {
+        let count = match (&route.geometry, tally) {
+            (RouteGeometry::Track(..), Tally::Added) => &mut self.tracks_added,
+            (RouteGeometry::Track(..), Tally::Removed) => &mut self.tracks_removed,
+            (RouteGeometry::Track(..), Tally::Changed) => &mut self.tracks_changed,
+            (RouteGeometry::Via(..), Tally::Added) => &mut self.vias_added,
+            (RouteGeometry::Via(..), Tally::Removed) => &mut self.vias_removed,
+            (RouteGeometry::Via(..), Tally::Changed) => &mut self.vias_changed,
+        };
+        *count += 1;
+    }
+}
+
+fn diff_routing(old: &Snapshot, new: &Snapshot) -> Vec<NetRoutingDiff> {
+    fn entry<'a>(nets: &'a mut BTreeMap<String, NetRoutingDiff>, name: &str) -> &'a mut NetRoutingDiff {
+        nets.entry(name.to_string()).or_insert_with(|| NetRoutingDiff { net: name.to_string(), ..Default::default() })
+    }
+    let mut nets = BTreeMap::new();
+    for route in old.routes.values() {
+        entry(&mut nets, &route.net).length_before += route.length;
+    }
+    for route in new.routes.values() {
+        entry(&mut nets, &route.net).length_after += route.length;
+    }
+    for uuid in all_keys(&old.routes, &new.routes) {
+        match (old.routes.get(uuid), new.routes.get(uuid)) {
+            (Some(before), Some(after)) if before.net != after.net => {
+                entry(&mut nets, &before.net).tally(before, Tally::Removed);
+                entry(&mut nets, &after.net).tally(after, Tally::Added);
+            }
+            (Some(before), Some(after)) if before.geometry != after.geometry => {
+                entry(&mut nets, &after.net).tally(after, Tally::Changed);
+            }
+            (None, Some(after)) => entry(&mut nets, &after.net).tally(after, Tally::Added),
+            (Some(before), None) => entry(&mut nets, &before.net).tally(before, Tally::Removed),
+            _ => {}
+        }
+    }
+    nets
+        .into_values()
+        .filter(|diff| {
+            diff.tracks_added + diff.tracks_removed + diff.tracks_changed
+                + diff.vias_added + diff.vias_removed + diff.vias_changed > 0
+        })
+        .collect()
+}
+
+fn diff_zones(old: &Snapshot, new: &Snapshot) -> Vec<ZoneDiff> {
+    all_keys(&old.zones, &new.zones)
+        .into_iter()
+        .filter_map(|uuid| {
+            let (zone, changes) = match (old.zones.get(uuid), new.zones.get(uuid)) {
+                (None, Some(after)) => (after, vec![ZoneChange::Added]),
+                (Some(before), None) => (before, vec![ZoneChange::Removed]),
+                (Some(before), Some(after)) => {
+                    let mut changes = Vec::new();
+                    if before.outline != after.outline {
+                        changes.push(ZoneChange::OutlineChanged);
+                    }
+                    if before.layers != after.layers {
+                        changes.push(ZoneChange::LayersChanged { from: before.layers.clone(), to: after.layers.clone() });
+                    }
+                    if before.net != after.net {
+                        changes.push(ZoneChange::NetChanged { from: before.net.clone(), to: after.net.clone() });
+                    }
+                    if before.priority != after.priority {
+                        changes.push(ZoneChange::PriorityChanged { from: before.priority, to: after.priority });
+                    }
+                    if before.name != after.name {
+                        changes.push(ZoneChange::Renamed { from: before.name.clone(), to: after.name.clone() });
+                    }
+                    (after, changes)
+                }
+                (None, None) => return None,
+            };
+            (!changes.is_empty()).then(|| ZoneDiff {
+                uuid: uuid.to_string(),
+                name: zone.name.clone(),
+                net: zone.net.clone(),
+                changes,
+            })
+        })
+        .collect()
+}
+
+// ===== Output =====
+
+impl BoardDiff {
+    /// Plain text, one line per footprint, net and zone
+    pub fn to_text(&self) -> String {
+        if self.is_empty() {
+            return "No changes\n".to_string();
+        }
+        let mut text = String::new();
+        let join = |changes: Vec<String>| changes.join(", ");
+        if !self.footprints.is_empty() {
+            text.push_str("Footprints:\n");
+            for diff in &self.footprints {
+                let _ = writeln!(text, "  {}: {}", diff.reference, join(diff.changes.iter().map(ToString::to_string).collect()));
+            }
+        }
+        if !self.routing.is_empty() {
+            text.push_str("Routing:\n");
+            for diff in &self.routing {
+                let _ = writeln!(text, "  {}: {}", diff.net, diff);
+            }
+        }
+        if !self.zones.is_empty() {
+            text.push_str("Zones:\n");
+            for diff in &self.zones {
+                let _ = writeln!(text, "  {}: {}", diff.label(), join(diff.changes.iter().map(ToString::to_string).collect()));
+            }
+        }
+        text
+    }
+
+    /// Markdown with a table per section, for pull request comments
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::from("## Board changes\n\n");
+        if self.is_empty() {
+            markdown.push_str("No changes.\n");
+            return markdown;
+        }
+        let cell = |text: String| text.replace('|', "\\|");
+        if !self.footprints.is_empty() {
+            let _ = writeln!(markdown, "### Footprints ({})\n\n| Reference | Changes |\n|---|---|", self.footprints.len());
+            for diff in &self.footprints {
+                let changes: Vec<String> = diff.changes.iter().map(ToString::to_string).collect();
+                let _ = writeln!(markdown, "| {} | {} |", cell(diff.reference.clone()), cell(changes.join("; ")));
+            }
+            markdown.push('\n');
+        }
+        if !self.routing.is_empty() {
+            let _ = writeln!(
+                markdown,
+                "### Routing ({} nets)\n\n| Net | Tracks +/-/~ | Vias +/-/~ | Length |\n|---|---|---|---|",
+                self.routing.len()
+            );
+            for diff in &self.routing {
+                let _ = writeln!(
+                    markdown,
+                    "| {} | +{} / -{} / ~{} | +{} / -{} / ~{} | {:.3} → {:.3} |",
+                    cell(diff.net.clone()),
+                    diff.tracks_added, diff.tracks_removed, diff.tracks_changed,
+                    diff.vias_added, diff.vias_removed, diff.vias_changed,
+                    diff.length_before, diff.length_after,
+                );
+            }
+            markdown.push('\n');
+        }
+        if !self.zones.is_empty() {
+            let _ = writeln!(markdown, "### Zones ({})\n\n| Zone | Changes |\n|---|---|", self.zones.len());
+            for diff in &self.zones {
+                let changes: Vec<String> = diff.changes.iter().map(ToString::to_string).collect();
+                let _ = writeln!(markdown, "| {} | {} |", cell(diff.label()), cell(changes.join("; ")));
+            }
+            markdown.push('\n');
+        }
+        markdown
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        let change_list = |changes: Vec<String>| json!(changes);
+        let value = json!({
+            "footprints": self.footprints.iter().map(|diff| json!({
+                "uuid": diff.uuid,
+                "reference": diff.reference,
+                "changes": change_list(diff.changes.iter().map(ToString::to_string).collect()),
+            })).collect::<Vec<_>>(),
+            "routing": self.routing.iter().map(|diff| json!({
+                "net": diff.net,
+                "tracks": { "added": diff.tracks_added, "removed": diff.tracks_removed, "changed": diff.tracks_changed },
+                "vias": { "added": diff.vias_added, "removed": diff.vias_removed, "changed": diff.vias_changed },
+                "length_before_mm": diff.length_before.mm(),
+                "length_after_mm": diff.length_after.mm(),
+            })).collect::<Vec<_>>(),
+            "zones": self.zones.iter().map(|diff| json!({
+                "uuid": diff.uuid,
+                "name": diff.name,
+                "net": diff.net,
+                "changes": change_list(diff.changes.iter().map(ToString::to_string).collect()),
+            })).collect::<Vec<_>>(),
+        });
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+}
+
+impl fmt::Display for BoardDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{part, spawn_track};
+
+    fn mm(x: f64, y: f64) -> Point {
+        Point::from_mm(x, y)
+    }
+
+    /// R1's value changes, R2 is removed, C1 moves and flips, C2 is added; the GND track gets
+    /// longer, a VCC track is added and the GND zone is removed
+    fn boards() -> (World, World) {
+        let mut old = World::new();
+        part("R1").uuid("a").value("10k").at(mm(10.0, 10.0)).spawn(&mut old);
+        part("R2").uuid("b").value("10k").at(mm(20.0, 10.0)).spawn(&mut old);
+        part("C1").uuid("c").value("100n").at(mm(30.0, 10.0)).rotated(90.0).spawn(&mut old);
+        spawn_track(&mut old, "t1", "GND", Point::ORIGIN, mm(10.0, 0.0), PcbLayer::FCu);
+        spawn_track(&mut old, "t2", "VCC", Point::ORIGIN, mm(5.0, 0.0), PcbLayer::FCu);
+        old.spawn((ComponentId { uuid: "z".to_string() }, Zone {
+            name: String::new(),
+            kind: ZoneKind::Copper,
+            layers: vec![PcbLayer::BCu],
+            outline: PolySet::default(),
+            priority: 0,
+            filled: true,
+        }, Net { name: "GND".to_string() }));
+
+        let mut new = World::new();
+        part("R1").uuid("a").value("4k7").at(mm(10.0, 10.0)).spawn(&mut new);
+        // 450° is the same rotation as 90°
+        part("C1").uuid("c").value("100n").at(mm(32.0, 10.0)).rotated(450.0).layer(PcbLayer::BCu).spawn(&mut new);
+        part("C2").uuid("d").value("1u").at(mm(40.0, 10.0)).spawn(&mut new);
+        spawn_track(&mut new, "t1", "GND", Point::ORIGIN, mm(12.0, 0.0), PcbLayer::FCu);
+        spawn_track(&mut new, "t2", "VCC", Point::ORIGIN, mm(5.0, 0.0), PcbLayer::FCu);
+        spawn_track(&mut new, "t3", "VCC", Point::ORIGIN, mm(0.0, 3.0), PcbLayer::FCu);
+        (old, new)
+    }
+
+    fn diff() -> BoardDiff {
+        let (mut old, mut new) = boards();
+        BoardDiff::between(&mut old, &mut new)
+    }
+
+    #[test]
+    fn reports_footprint_changes() {
+        let diff = diff();
+        let footprints: Vec<(&str, &[FootprintChange])> = diff.footprints
+            .iter()
+            .map(|diff| (diff.reference.as_str(), diff.changes.as_slice()))
+            .collect();
+        assert_eq!(footprints, vec![
+            ("C1", &[FootprintChange::Moved { from: mm(30.0, 10.0), to: mm(32.0, 10.0) }, FootprintChange::Flipped { to: Side::Back }][..]),
+            ("C2", &[FootprintChange::Added][..]),
+            ("R1", &[FootprintChange::ValueChanged { from: "10k".to_string(), to: "4k7".to_string() }][..]),
+            ("R2", &[FootprintChange::Removed][..]),
+        ]);
+    }
+
+    #[test]
+    fn reports_routing_changes_per_net() {
+        let diff = diff();
+        assert_eq!(diff.routing.len(), 2);
+        assert_eq!((diff.routing[0].net.as_str(), diff.routing[0].tracks_changed), ("GND", 1));
+        assert_eq!(diff.routing[0].length_after, Length::from_mm(12.0));
+        assert_eq!((diff.routing[1].net.as_str(), diff.routing[1].tracks_added), ("VCC", 1));
+    }
+
+    #[test]
+    fn reports_zone_changes() {
+        assert_eq!(diff().zones[0].changes, vec![ZoneChange::Removed]);
+    }
+
+    #[test]
+    fn renders_text_markdown_and_json() {
+        let diff = diff();
+        assert!(diff.to_text().contains("R1: value 10k → 4k7"));
+        assert!(diff.to_markdown().contains("| GND | +0 / -0 / ~1 | +0 / -0 / ~0 | 10.000 mm → 12.000 mm |"));
+        let json: serde_json::Value = serde_json::from_str(&diff.to_json().unwrap()).unwrap();
+        assert_eq!(json["routing"][1]["tracks"]["added"], 1);
+    }
+
+    #[test]
+    fn everything_is_removed_against_an_empty_board() {
+        let (_, mut new) = boards();
+        let diff = BoardDiff::between(&mut new, &mut World::new());
+        assert!(diff.footprints.iter().all(|diff| diff.changes == [FootprintChange::Removed]));
+    }
+}
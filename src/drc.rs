@@ -0,0 +1,826 @@
+//! Design rule checks
+//!
+//! Each rule is an ordinary bevy system that reads board items and spawns a `Violation` entity
+//! for every problem it finds. Limits come from the `DesignRules` resource: per-net values from
+//! the project's netclasses (see `KiCadClient::get_net_class_for_nets`) and board-wide minimums
+//! from the fields of `DesignRules` itself. `drc_schedule` holds the built-in rules; custom rules
+//! are added to it like any other system.
+//!
+//! Copper checks work on `Shape` components, so pad shapes must have been loaded with
+//! `PcbWorld::apply_pad_shapes` for pads to take part.
+
+use bevy_ecs::prelude::*;
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use tracing::debug;
+use crate::components::{
+    BoundingBox, ComponentId, ComponentInfo, DesignRuleOverrides, Layer, Net, NetTies, Pad, PadKind,
+    Pads, Position, Silkscreen, Track, Via,
+};
+use crate::geometry::{Graphic, GraphicShape, Shape};
+use crate::layers::PcbLayer;
+use crate::outline::BoardOutline;
+use crate::decoupling;
+use crate::placement;
+use crate::units::{Length, Point};
+
+pub const COPPER_CLEARANCE: &str = "copper_clearance";
+pub const TRACK_WIDTH: &str = "track_width";
+pub const ANNULAR_RING: &str = "annular_ring";
+pub const HOLE_TO_HOLE: &str = "hole_to_hole";
+pub const EDGE_CLEARANCE: &str = "edge_clearance";
+pub const SILK_TO_PAD: &str = "silk_to_pad";
+
+/// How serious a violation is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A design rule violation found by a DRC system
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    pub location: Point,
+    pub entities: Vec<Entity>, // the entities holding the offending items
+    pub items: Vec<String>,    // KiCad UUIDs of the offending items (pads, tracks, vias, shapes)
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {} at {}", self.severity, self.rule, self.message, self.location)
+    }
+}
+
+/// The board settings of a netclass; `None` means the netclass leaves the value unset
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NetClassRules {
+    pub name: String,
+    pub clearance: Option<Length>,
+    pub track_width: Option<Length>,
+    pub via_diameter: Option<Length>,
+    pub via_drill: Option<Length>,
+    pub diff_pair_width: Option<Length>,
+    pub diff_pair_gap: Option<Length>,
+}
+
+/// Limits used by the DRC systems
+#[derive(Resource, Debug, Clone)]
+pub struct DesignRules {
+    /// Rules for nets without an entry in `net_classes` and for unconnected items
+    pub default_class: NetClassRules,
+    /// Effective netclass of each net, by net name
+    pub net_classes: HashMap<String, NetClassRules>,
+    /// Clearance floor applied on top of netclass clearances
+    pub min_clearance: Length,
+    /// Width below which a track is an error regardless of its netclass
+    pub min_track_width: Length,
+    pub min_annular_ring: Length,
+    /// Minimum distance between the edges of two drilled holes
+    pub min_hole_to_hole: Length,
+    /// Minimum distance from copper to the board outline
+    pub copper_edge_clearance: Length,
+    /// Minimum distance from silkscreen to pad copper
+    pub silk_clearance: Length,
+}
+
+impl Default for DesignRules {
+    /// KiCad's defaults for a new board
+    fn default() -> Self {
+        Self {
+            default_class: NetClassRules {
+                name: "Default".to_string(),
+                clearance: Some(Length::from_mm(0.2)),
+                track_width: Some(Length::from_mm(0.2)),
+                via_diameter: Some(Length::from_mm(0.6)),
+                via_drill: Some(Length::from_mm(0.3)),
+                diff_pair_width: Some(Length::from_mm(0.2)),
+                diff_pair_gap: Some(Length::from_mm(0.25)),
+            },
+            net_classes: HashMap::new(),
+            min_clearance: Length::ZERO,
+            min_track_width: Length::ZERO,
+            min_annular_ring: Length::from_mm(0.1),
+            min_hole_to_hole: Length::from_mm(0.25),
+            copper_edge_clearance: Length::from_mm(0.5),
+            silk_clearance: Length::from_mm(0.15),
+        }
+    }
+}
+
+impl DesignRules {
+    /// The netclass of a net, falling back to the default class
+    pub fn net_class(&self, net: Option<&str>) -> &NetClassRules {
+        net.and_then(|net| self.net_classes.get(net)).unwrap_or(&self.default_class)
+    }
+
+    /// Required copper clearance around items on a net
+    pub fn clearance(&self, net: Option<&str>) -> Length {
+        self.net_class(net)
+            .clearance
+            .or(self.default_class.clearance)
+            .unwrap_or(Length::ZERO)
+            .max(self.min_clearance)
+    }
+
+    /// Expected track width for a net
+    pub fn track_width(&self, net: Option<&str>) -> Option<Length> {
+        self.net_class(net).track_width.or(self.default_class.track_width)
+    }
+}
+
+// ===== Reporting =====
+
+/// Start of every marker label, which tells markers apart from other text on the marker layer
+pub const MARKER_TAG: &str = "[kicad-ecs]";
+
+// Rounding KiCad may apply to marker coordinates
+const MARKER_TOLERANCE: Length = Length::from_nm(1000);
+
+/// How violations are drawn into the board by `KiCadClient::replace_violation_markers`
+///
+/// Markers are a circle around each violation with the rule name next to it, on a user layer.
+/// Labels start with `MARKER_TAG`; when markers are replaced, only tagged labels and the circles
+/// drawn with them are removed, so other drawings on the layer are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkerStyle {
+    pub layer: PcbLayer,
+    pub radius: Length,
+    pub stroke_width: Length,
+    pub text_size: Length,
+}
+
+impl Default for MarkerStyle {
+    fn default() -> Self {
+        Self {
+            layer: PcbLayer::User(9),
+            radius: Length::from_mm(1.0),
+            stroke_width: Length::from_mm(0.1),
+            text_size: Length::from_mm(0.8),
+        }
+    }
+}
+
+impl MarkerStyle {
+    /// The circle drawn around a violation
+    pub fn circle(&self, violation: &Violation) -> Graphic {
+        let center = violation.location;
+        Graphic {
+            shape: GraphicShape::Circle { center, radius_point: Point::new(center.x + self.radius, center.y) },
+            stroke_width: self.stroke_width,
+            filled: false,
+        }
+    }
+
+    /// Where the label of a violation goes: above and to the right of the circle
+    pub fn label_position(&self, violation: &Violation) -> Point {
+        let offset = self.radius + self.text_size / 2;
+        Point::new(violation.location.x + offset, violation.location.y - offset)
+    }
+
+    /// The label text of a violation
+    pub fn label(&self, violation: &Violation) -> String {
+        format!("{} {}: {}", MARKER_TAG, violation.severity, violation.rule)
+    }
+
+    /// Whether a text is the label of a marker
+    pub fn is_label(&self, text: &str) -> bool {
+        text.starts_with(MARKER_TAG)
+    }
+
+    /// Whether `graphic` is the circle of the marker whose label is at `label_position`
+    pub fn is_circle_of(&self, graphic: &Graphic, label_position: Point) -> bool {
+        let offset = self.radius + self.text_size / 2;
+        let location = Point::new(label_position.x - offset, label_position.y + offset);
+        match graphic.shape {
+            GraphicShape::Circle { center, radius_point } => {
+                center.distance(&location) < MARKER_TOLERANCE
+                    && (center.distance(&radius_point) - self.radius).abs() < MARKER_TOLERANCE
+            }
+            _ => false,
+        }
+    }
+}
+
+// ===== Copper items =====
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CopperKind {
+    Track,
+    Via,
+    Pad(PadKind),
+}
+
+/// A piece of copper gathered from tracks, vias or footprint pads
+pub(crate) struct CopperItem {
+    pub entity: Entity,
+    pub item_id: String,
+    pub description: String,
+    pub kind: CopperKind,
+    pub net: Option<String>,
+    pub layers: (PcbLayer, PcbLayer), // front-most and back-most copper layer the item is on
+    pub shape: Shape,
+    pub bounds: BoundingBox,
+    pub clearance_override: Option<Length>,
+    pub net_tie: Option<usize>, // net tie pad group within the footprint
+}
+
+impl CopperItem {
+    pub fn shares_layer(&self, other: &CopperItem) -> bool {
+        self.layers.0 <= other.layers.1 && other.layers.0 <= self.layers.1
+    }
+
+    /// Whether both items are pads of one net tie group, which may join different nets
+    fn tied_to(&self, other: &CopperItem) -> bool {
+        self.entity == other.entity && self.net_tie.is_some() && self.net_tie == other.net_tie
+    }
+
+    fn clearance(&self, rules: &DesignRules) -> Length {
+        self.clearance_override.unwrap_or_else(|| rules.clearance(self.net.as_deref()))
+    }
+}
+
+pub(crate) type TrackQuery<'w, 's> = Query<'w, 's, (Entity, &'static ComponentId, &'static Track, &'static Layer, &'static Shape, Option<&'static Net>)>;
+pub(crate) type ViaQuery<'w, 's> = Query<'w, 's, (Entity, &'static ComponentId, &'static Via, &'static Position, &'static Shape, Option<&'static Net>)>;
+pub(crate) type FootprintQuery<'w, 's> = Query<'w, 's, (Entity, &'static ComponentInfo, &'static Position, &'static Layer, &'static Pads, Option<&'static DesignRuleOverrides>, Option<&'static NetTies>)>;
+
+fn net_label(net: Option<&str>) -> &str {
+    net.unwrap_or("no net")
+}
+
+pub(crate) fn collect_copper(tracks: &TrackQuery, vias: &ViaQuery, footprints: &FootprintQuery) -> Vec<CopperItem> {
+    let mut items = Vec::new();
+    for (entity, id, track, layer, shape, net) in tracks {
+        let Some(bounds) = shape.bounding_box() else { continue };
+        let net = net.map(|net| net.name.clone());
+        items.push(CopperItem {
+            entity,
+            item_id: id.uuid.clone(),
+            description: format!("track on {} ({}, {})", layer.id, net_label(net.as_deref()), track.width),
+            kind: CopperKind::Track,
+            net,
+            layers: (layer.id, layer.id),
+            shape: shape.clone(),
+            bounds,
+            clearance_override: None,
+            net_tie: None,
+        });
+    }
+    for (entity, id, via, position, shape, net) in vias {
+        let Some(bounds) = shape.bounding_box() else { continue };
+        let net = net.map(|net| net.name.clone());
+        items.push(CopperItem {
+            entity,
+            item_id: id.uuid.clone(),
+            description: format!("via at {} ({})", position.point(), net_label(net.as_deref())),
+            kind: CopperKind::Via,
+            net,
+            layers: via.layer_span(),
+            shape: shape.clone(),
+            bounds,
+            clearance_override: None,
+            net_tie: None,
+        });
+    }
+    for (entity, info, _, _, pads, overrides, net_ties) in footprints {
+        for pad in &pads.pads {
+            for (layer, shape) in &pad.shapes {
+                let Some(bounds) = shape.bounding_box() else { continue };
+                items.push(CopperItem {
+                    entity,
+                    item_id: pad.id.clone(),
+                    description: format!("pad {}-{} ({})", info.reference, pad.number, net_label(pad.net.as_deref())),
+                    kind: CopperKind::Pad(pad.kind),
+                    net: pad.net.clone(),
+                    layers: pad_layer_span(pad, *layer),
+                    shape: shape.clone(),
+                    bounds,
+                    // A pad's own override takes precedence over the footprint's
+                    clearance_override: pad.clearance.or(overrides.and_then(|overrides| overrides.copper_clearance)),
+                    net_tie: net_ties.and_then(|net_ties| net_ties.group_of(&pad.number)),
+                });
+            }
+        }
+    }
+    items
+}
+
+/// The copper layers a pad outline on `layer` stands for. Only outer layer outlines are
+/// loaded, so a through-hole pad's inner layers take the outlines of both outer layers.
+fn pad_layer_span(pad: &Pad, layer: PcbLayer) -> (PcbLayer, PcbLayer) {
+    if !pad.is_through_hole() {
+        return (layer, layer);
+    }
+    let first_inner = PcbLayer::InCu(1);
+    let last_inner = PcbLayer::InCu(PcbLayer::MAX_INNER_COPPER);
+    match (layer, pad.shape_on(PcbLayer::FCu).is_some() && pad.shape_on(PcbLayer::BCu).is_some()) {
+        (PcbLayer::FCu, true) => (PcbLayer::FCu, last_inner),
+        (PcbLayer::BCu, true) => (first_inner, PcbLayer::BCu),
+        _ => (PcbLayer::FCu, PcbLayer::BCu),
+    }
+}
+
+pub(crate) type IndexedBounds = GeomWithData<Rectangle<[i64; 2]>, usize>;
+
+pub(crate) fn aabb(bounds: &BoundingBox) -> AABB<[i64; 2]> {
+    AABB::from_corners([bounds.min.x.nm(), bounds.min.y.nm()], [bounds.max.x.nm(), bounds.max.y.nm()])
+}
+
+/// An R-tree over bounding boxes, each tagged with its index in `bounds`
+pub(crate) fn bounds_tree<'a>(bounds: impl Iterator<Item = &'a BoundingBox>) -> RTree<IndexedBounds> {
+    RTree::bulk_load(
+        bounds
+            .enumerate()
+            .map(|(index, bounds)| {
+                let envelope = aabb(bounds);
+                GeomWithData::new(Rectangle::from_corners(envelope.lower(), envelope.upper()), index)
+            })
+            .collect(),
+    )
+}
+
+/// A point between two items: the middle of their overlap, or of the gap between them
+fn between(a: &BoundingBox, b: &BoundingBox) -> Point {
+    let x = (a.min.x.max(b.min.x) + a.max.x.min(b.max.x)) / 2;
+    let y = (a.min.y.max(b.min.y) + a.max.y.min(b.max.y)) / 2;
+    Point::new(x, y)
+}
+
+fn violation(rule: &str, severity: Severity, message: String, location: Point, involved: &[&CopperItem]) -> Violation {
+    Violation {
+        rule: rule.to_string(),
+        severity,
+        message,
+        location,
+        entities: involved.iter().map(|item| item.entity).collect(),
+        items: involved.iter().map(|item| item.item_id.clone()).collect(),
+    }
+}
+
+// ===== Rules =====
+
+/// Despawn the violations of a previous run
+pub fn clear_violations(mut commands: Commands, violations: Query<Entity, With<Violation>>) {
+    for entity in &violations {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Copper items on different nets that share a layer must be at least the larger of their
+/// clearances apart. Pads of the same footprint are only checked when both are on a net, and
+/// pads of one net tie group are not checked against each other.
+pub fn copper_clearance_rule(
+    mut commands: Commands,
+    rules: Res<DesignRules>,
+    tracks: TrackQuery,
+    vias: ViaQuery,
+    footprints: FootprintQuery,
+) {
+    let items = collect_copper(&tracks, &vias, &footprints);
+    let tree = bounds_tree(items.iter().map(|item| &item.bounds));
+    let max_clearance = items.iter().map(|item| item.clearance(&rules)).max().unwrap_or(Length::ZERO);
+
+    // Through-hole pads have an item per layer; report each pair of items once
+    let mut reported = HashSet::new();
+    for (i, a) in items.iter().enumerate() {
+        let search = aabb(&a.bounds.expanded(max_clearance));
+        for candidate in tree.locate_in_envelope_intersecting(&search) {
+            let j = candidate.data;
+            let b = &items[j];
+            if j <= i || !a.shares_layer(b) {
+                continue;
+            }
+            if a.net.is_some() && a.net == b.net {
+                continue;
+            }
+            if a.entity == b.entity && (a.net.is_none() || b.net.is_none()) {
+                continue;
+            }
+            if a.tied_to(b) {
+                continue;
+            }
+            let required = a.clearance(&rules).max(b.clearance(&rules));
+            let distance = a.shape.distance(&b.shape);
+            if distance < required && reported.insert((&a.item_id, &b.item_id)) {
+                let message = if distance == Length::ZERO {
+                    format!("{} shorts with {}", a.description, b.description)
+                } else {
+                    format!("{} and {} are {} apart; {} required", a.description, b.description, distance, required)
+                };
+                let location = between(&a.bounds, &b.bounds);
+                commands.spawn(violation(COPPER_CLEARANCE, Severity::Error, message, location, &[a, b]));
+            }
+        }
+    }
+    debug!("Copper clearance: {} items, {} violations", items.len(), reported.len());
+}
+
+/// Tracks narrower than the board minimum are errors; tracks narrower than their netclass width
+/// are warnings
+pub fn track_width_rule(
+    mut commands: Commands,
+    rules: Res<DesignRules>,
+    tracks: Query<(Entity, &ComponentId, &Track, &Layer, Option<&Net>)>,
+) {
+    for (entity, id, track, layer, net) in &tracks {
+        let net = net.map(|net| net.name.as_str());
+        let (severity, required) = if track.width < rules.min_track_width {
+            (Severity::Error, rules.min_track_width)
+        } else {
+            match rules.track_width(net) {
+                Some(width) if track.width < width => (Severity::Warning, width),
+                _ => continue,
+            }
+        };
+        commands.spawn(Violation {
+            rule: TRACK_WIDTH.to_string(),
+            severity,
+            message: format!("track on {} ({}) is {} wide; {} required", layer.id, net_label(net), track.width, required),
+            location: track.start,
+            entities: vec![entity],
+            items: vec![id.uuid.clone()],
+        });
+    }
+}
+
+/// The copper ring around via and plated pad holes must be at least `min_annular_ring` wide
+pub fn annular_ring_rule(
+    mut commands: Commands,
+    rules: Res<DesignRules>,
+    vias: Query<(Entity, &ComponentId, &Via, &Position)>,
+    footprints: Query<(Entity, &ComponentInfo, &Position, &Pads)>,
+) {
+    let minimum = rules.min_annular_ring;
+    for (entity, id, via, position) in &vias {
+        let ring = via.annular_ring();
+        if ring < minimum {
+            commands.spawn(Violation {
+                rule: ANNULAR_RING.to_string(),
+                severity: Severity::Error,
+                message: format!("via annular ring is {}; {} required", ring, minimum),
+                location: position.point(),
+                entities: vec![entity],
+                items: vec![id.uuid.clone()],
+            });
+        }
+    }
+    for (entity, info, position, pads) in &footprints {
+        for pad in pads.pads.iter().filter(|pad| pad.kind == PadKind::Pth) {
+            let Some(drill) = pad.drill else { continue };
+            let ring = (pad.size.x.min(pad.size.y) - drill) / 2;
+            if ring < minimum {
+                commands.spawn(Violation {
+                    rule: ANNULAR_RING.to_string(),
+                    severity: Severity::Error,
+                    message: format!("pad {}-{} annular ring is {}; {} required", info.reference, pad.number, ring, minimum),
+                    location: pad.board_position(position),
+                    entities: vec![entity],
+                    items: vec![pad.id.clone()],
+                });
+            }
+        }
+    }
+}
+
+/// Drilled holes must be at least `min_hole_to_hole` apart, edge to edge. Holes within the same
+/// footprint are left to the footprint library.
+pub fn hole_to_hole_rule(
+    mut commands: Commands,
+    rules: Res<DesignRules>,
+    vias: Query<(Entity, &ComponentId, &Via, &Position)>,
+    footprints: Query<(Entity, &ComponentInfo, &Position, &Pads)>,
+) {
+    struct Hole {
+        entity: Entity,
+        item_id: String,
+        description: String,
+        center: Point,
+        diameter: Length,
+    }
+
+    let mut holes: Vec<Hole> = vias
+        .iter()
+        .map(|(entity, id, via, position)| Hole {
+            entity,
+            item_id: id.uuid.clone(),
+            description: format!("via at {}", position.point()),
+            center: position.point(),
+            diameter: via.drill,
+        })
+        .collect();
+    for (entity, info, position, pads) in &footprints {
+        holes.extend(pads.pads.iter().filter_map(|pad| {
+            Some(Hole {
+                entity,
+                item_id: pad.id.clone(),
+                description: format!("pad {}-{}", info.reference, pad.number),
+                center: pad.board_position(position),
+                diameter: pad.drill?,
+            })
+        }));
+    }
+
+    let bounds: Vec<BoundingBox> = holes.iter().map(|hole| BoundingBox::around(hole.center, hole.diameter)).collect();
+    let tree = bounds_tree(bounds.iter());
+    for (i, a) in holes.iter().enumerate() {
+        let search = aabb(&bounds[i].expanded(rules.min_hole_to_hole));
+        for candidate in tree.locate_in_envelope_intersecting(&search) {
+            let j = candidate.data;
+            let b = &holes[j];
+            if j <= i || a.entity == b.entity {
+                continue;
+            }
+            let gap = a.center.distance(&b.center) - a.diameter / 2 - b.diameter / 2;
+            if gap < rules.min_hole_to_hole {
+                commands.spawn(Violation {
+                    rule: HOLE_TO_HOLE.to_string(),
+                    severity: Severity::Error,
+                    message: format!("{} and {} holes are {} apart; {} required", a.description, b.description, gap.max(Length::ZERO), rules.min_hole_to_hole),
+                    location: between(&bounds[i], &bounds[j]),
+                    entities: vec![a.entity, b.entity],
+                    items: vec![a.item_id.clone(), b.item_id.clone()],
+                });
+            }
+        }
+    }
+}
+
+/// Copper must stay `copper_edge_clearance` away from the board outline, except edge connector
+/// pads. Skipped until a `BoardOutline` has been built.
+pub fn edge_clearance_rule(
+    mut commands: Commands,
+    rules: Res<DesignRules>,
+    outline: Option<Res<BoardOutline>>,
+    tracks: TrackQuery,
+    vias: ViaQuery,
+    footprints: FootprintQuery,
+) {
+    let Some(outline) = outline else { return };
+    let mut reported = HashSet::new();
+    for item in collect_copper(&tracks, &vias, &footprints) {
+        if item.kind == CopperKind::Pad(PadKind::EdgeConnector) {
+            continue;
+        }
+        let Some(clearance) = outline.edge_clearance(&item.shape) else { continue };
+        if clearance < rules.copper_edge_clearance && reported.insert(item.item_id.clone()) {
+            let message = format!("{} is {} from the board edge; {} required", item.description, clearance, rules.copper_edge_clearance);
+            commands.spawn(violation(EDGE_CLEARANCE, Severity::Error, message, item.bounds.center(), &[&item]));
+        }
+    }
+}
+
+/// Silkscreen must stay `silk_clearance` away from pads on the same side, where it would be
+/// clipped by the solder mask opening
+pub fn silk_to_pad_rule(
+    mut commands: Commands,
+    rules: Res<DesignRules>,
+    silk: Query<(Entity, &ComponentId, &Layer, &Shape), With<Silkscreen>>,
+    tracks: TrackQuery,
+    vias: ViaQuery,
+    footprints: FootprintQuery,
+) {
+    let pads: Vec<CopperItem> = collect_copper(&tracks, &vias, &footprints)
+        .into_iter()
+        .filter(|item| matches!(item.kind, CopperKind::Pad(_)))
+        .collect();
+    let tree = bounds_tree(pads.iter().map(|pad| &pad.bounds));
+
+    for (entity, id, layer, shape) in &silk {
+        let (Some(side), Some(bounds)) = (layer.side(), shape.bounding_box()) else { continue };
+        let copper = PcbLayer::outer_copper(side);
+        let search = aabb(&bounds.expanded(rules.silk_clearance));
+        for candidate in tree.locate_in_envelope_intersecting(&search) {
+            let pad = &pads[candidate.data];
+            if !(pad.layers.0 <= copper && copper <= pad.layers.1) {
+                continue;
+            }
+            let distance = shape.distance(&pad.shape);
+            if distance < rules.silk_clearance {
+                commands.spawn(Violation {
+                    rule: SILK_TO_PAD.to_string(),
+                    severity: Severity::Warning,
+                    message: format!("silkscreen on {} is {} from {}; {} required", layer.id, distance, pad.description, rules.silk_clearance),
+                    location: between(&bounds, &pad.bounds),
+                    entities: vec![entity, pad.entity],
+                    items: vec![id.uuid.clone(), pad.item_id.clone()],
+                });
+            }
+        }
+    }
+}
+
+/// A schedule with `clear_violations` and every built-in rule, including the placement and
+/// decoupling rules. Running it needs the `DesignRules`, `PlacementRules`, `DecouplingRules`
+/// and `Tessellation` resources.
+pub fn drc_schedule() -> Schedule {
+    let mut schedule = Schedule::default();
+    schedule.add_systems((
+        clear_violations,
+        copper_clearance_rule,
+        track_width_rule,
+        annular_ring_rule,
+        hole_to_hole_rule,
+        edge_clearance_rule,
+        silk_to_pad_rule,
+        placement::courtyard_overlap_rule,
+        placement::part_edge_clearance_rule,
+        placement::mounting_hole_keepout_rule,
+        placement::connector_mating_area_rule,
+        decoupling::decoupling_rule,
+    ));
+    schedule
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Tessellation;
+    use crate::units::Angle;
+    use crate::components::ViaType;
+
+    fn spawn_track(world: &mut World, net: &str, y_mm: f64, width_mm: f64) -> Entity {
+        let track = Track {
+            start: Point::from_mm(0.0, y_mm),
+            mid: None,
+            end: Point::from_mm(10.0, y_mm),
+            width: Length::from_mm(width_mm),
+        };
+        let shape = track.to_shape(&Tessellation::default());
+        world.spawn((
+            ComponentId { uuid: format!("{}-{}", net, y_mm) },
+            track,
+            Layer { id: PcbLayer::FCu },
+            shape,
+            Net { name: net.to_string() },
+        )).id()
+    }
+
+    #[test]
+    fn finds_clearance_width_and_ring_violations() {
+        let mut world = World::new();
+        world.init_resource::<DesignRules>();
+        world.init_resource::<placement::PlacementRules>();
+        world.init_resource::<decoupling::DecouplingRules>();
+        world.init_resource::<Tessellation>();
+        let gnd = spawn_track(&mut world, "GND", 0.0, 0.25);
+        let vcc = spawn_track(&mut world, "VCC", 0.4, 0.25);
+        spawn_track(&mut world, "VCC", 5.0, 0.1);
+        let via = Via {
+            drill: Length::from_mm(0.4),
+            diameter: Length::from_mm(0.5),
+            via_type: ViaType::Through,
+            start_layer: PcbLayer::FCu,
+            end_layer: PcbLayer::BCu,
+        };
+        let center = Point::from_mm(20.0, 20.0);
+        let shape = via.to_shape(center, &Tessellation::default());
+        world.spawn((ComponentId { uuid: "via".to_string() }, via, Position::new(center, Angle::ZERO), shape));
+
+        let mut schedule = drc_schedule();
+        schedule.run(&mut world);
+        let mut query = world.query::<&Violation>();
+        let mut rules: Vec<(&str, Severity)> = query.iter(&world).map(|v| (v.rule.as_str(), v.severity)).collect();
+        rules.sort();
+        assert_eq!(rules, vec![
+            (ANNULAR_RING, Severity::Error),
+            (COPPER_CLEARANCE, Severity::Error),
+            (TRACK_WIDTH, Severity::Warning),
+        ]);
+        let clearance = query.iter(&world).find(|v| v.rule == COPPER_CLEARANCE).unwrap();
+        assert_eq!(clearance.entities, vec![gnd, vcc]);
+
+        // A second run replaces the previous violations instead of adding to them
+        schedule.run(&mut world);
+        assert_eq!(world.query::<&Violation>().iter(&world).count(), 3);
+    }
+
+    fn pad(number: &str, net: &str, x_mm: f64, clearance: Option<Length>) -> Pad {
+        let center = Point::from_mm(x_mm, 0.0);
+        Pad {
+            id: format!("{}-{}", net, number),
+            number: number.to_string(),
+            kind: PadKind::Smd,
+            offset: center,
+            net: Some(net.to_string()),
+            drill: None,
+            size: Point::from_mm(0.5, 0.5),
+            shapes: vec![(PcbLayer::FCu, Shape::stroke(&[center], false, Length::from_mm(0.5), &Tessellation::default()))],
+            clearance,
+        }
+    }
+
+    fn spawn_footprint(world: &mut World, reference: &str, pads: Vec<Pad>) -> Entity {
+        world.spawn((
+            ComponentInfo { reference: reference.to_string(), value: String::new(), footprint_name: String::new() },
+            Position::new(Point::ORIGIN, Angle::ZERO),
+            Layer { id: PcbLayer::FCu },
+            Pads { pads },
+        )).id()
+    }
+
+    #[test]
+    fn net_tie_pads_may_join_nets() {
+        let mut world = World::new();
+        world.init_resource::<DesignRules>();
+        // Pads 1 and 2 touch, joining GND and AGND through the tie
+        let tie = spawn_footprint(&mut world, "NT1", vec![pad("1", "GND", 0.0, None), pad("2", "AGND", 0.4, None)]);
+        world.entity_mut(tie).insert(NetTies { groups: vec![vec!["1".to_string(), "2".to_string()]] });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(copper_clearance_rule);
+        schedule.run(&mut world);
+        assert_eq!(world.query::<&Violation>().iter(&world).count(), 0);
+
+        // The same pads without the tie short; a pad clearance override raises the requirement
+        // for a pair that meets the board clearance
+        spawn_footprint(&mut world, "R1", vec![pad("1", "A", 10.0, None), pad("2", "B", 10.4, None)]);
+        spawn_footprint(&mut world, "R2", vec![pad("1", "C", 20.0, None), pad("2", "D", 20.8, Some(Length::from_mm(1.0)))]);
+        schedule.run(&mut world);
+        let mut query = world.query::<&Violation>();
+        let mut messages: Vec<&str> = query.iter(&world).map(|v| v.message.as_str()).collect();
+        messages.sort();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].starts_with("pad R1-1 (A) shorts with pad R1-2 (B)"), "{}", messages[0]);
+        assert!(messages[1].starts_with("pad R2-1 (C) and pad R2-2 (D) are"), "{}", messages[1]);
+    }
+
+    #[test]
+    fn through_hole_pads_keep_each_layer_outline() {
+        use crate::geometry::{PolyLine, PolyLineNode, PolygonWithHoles};
+        let square = |x_mm: f64, half_mm: f64| PolygonWithHoles {
+            outline: PolyLine {
+                nodes: [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .map(|(dx, dy)| PolyLineNode::Point(Point::from_mm(x_mm + dx * half_mm, dy * half_mm)))
+                    .to_vec(),
+                closed: true,
+            },
+            holes: Vec::new(),
+        };
+        let mut pcb = crate::world::PcbWorld::new();
+        let mut tht = pad("1", "A", 0.0, None);
+        tht.kind = PadKind::Pth;
+        let front = pad("1", "B", -1.3, None);
+        let back = pad("1", "C", 1.3, None);
+        let pad_shapes = [
+            ((tht.id.clone(), PcbLayer::FCu), square(0.0, 0.5)),
+            ((tht.id.clone(), PcbLayer::BCu), square(0.0, 1.0)),
+            ((front.id.clone(), PcbLayer::FCu), square(-1.3, 0.2)),
+            ((back.id.clone(), PcbLayer::BCu), square(1.3, 0.2)),
+        ].into_iter().collect();
+        spawn_footprint(&mut pcb.world, "J1", vec![tht]);
+        spawn_footprint(&mut pcb.world, "R1", vec![front]);
+        spawn_footprint(&mut pcb.world, "R2", vec![back]);
+        pcb.apply_pad_shapes(&pad_shapes);
+
+        // The large B.Cu outline is only 0.1 mm from R2; the F.Cu one is 0.6 mm from R1
+        let mut schedule = Schedule::default();
+        schedule.add_systems(copper_clearance_rule);
+        schedule.run(&mut pcb.world);
+        let mut query = pcb.world.query::<&Violation>();
+        let violations: Vec<&Violation> = query.iter(&pcb.world).collect();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].items, vec!["A-1".to_string(), "C-1".to_string()]);
+    }
+
+    #[test]
+    fn markers_recognize_only_their_own_items() {
+        let style = MarkerStyle::default();
+        let violation = Violation {
+            rule: COPPER_CLEARANCE.to_string(),
+            severity: Severity::Error,
+            message: String::new(),
+            location: Point::from_mm(12.0, 7.5),
+            entities: Vec::new(),
+            items: Vec::new(),
+        };
+        let label_position = style.label_position(&violation);
+        assert!(style.is_label(&style.label(&violation)));
+        assert!(!style.is_label("Rev B"));
+        assert!(style.is_circle_of(&style.circle(&violation), label_position));
+
+        // A designer's circle elsewhere on the layer, or of another size, is not a marker
+        let mut moved = violation.clone();
+        moved.location = Point::from_mm(30.0, 7.5);
+        assert!(!style.is_circle_of(&style.circle(&moved), label_position));
+        let larger = MarkerStyle { radius: Length::from_mm(2.0), ..style.clone() };
+        assert!(!style.is_circle_of(&larger.circle(&violation), label_position));
+    }
+}
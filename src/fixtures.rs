@@ -0,0 +1,170 @@
+//! Builders for the footprints, pads and copper that unit tests spawn
+//!
+//! Parts get a `ComponentId` (the lowercased reference unless set), `ComponentInfo`, `Position`,
+//! `Layer` and `ComponentFlags`, plus `Fields` and `Pads` when given, so the same part works for
+//! every analysis. Tests add what is specific to them, such as courtyards or marker components,
+//! to the returned entity.
+
+use bevy_ecs::prelude::*;
+use crate::components::*;
+use crate::geometry::{Shape, Tessellation};
+use crate::layers::PcbLayer;
+use crate::units::{Angle, Length, Point};
+
+/// A footprint to spawn, see `part`
+#[derive(Debug, Clone)]
+pub struct PartBuilder {
+    uuid: Option<String>,
+    reference: String,
+    value: String,
+    footprint: String,
+    at: Point,
+    degrees: f64,
+    layer: PcbLayer,
+    flags: ComponentFlags,
+    fields: Option<Fields>,
+    pads: Option<Vec<Pad>>,
+}
+
+/// A front-side part at the origin with no value, footprint, fields or pads
+pub fn part(reference: &str) -> PartBuilder {
+    PartBuilder {
+        uuid: None,
+        reference: reference.to_string(),
+        value: String::new(),
+        footprint: String::new(),
+        at: Point::ORIGIN,
+        degrees: 0.0,
+        layer: PcbLayer::FCu,
+        flags: ComponentFlags::default(),
+        fields: None,
+        pads: None,
+    }
+}
+
+impl PartBuilder {
+    pub fn uuid(mut self, uuid: &str) -> Self {
+        self.uuid = Some(uuid.to_string());
+        self
+    }
+
+    pub fn value(mut self, value: &str) -> Self {
+        self.value = value.to_string();
+        self
+    }
+
+    pub fn footprint(mut self, footprint: &str) -> Self {
+        self.footprint = footprint.to_string();
+        self
+    }
+
+    pub fn at(mut self, at: Point) -> Self {
+        self.at = at;
+        self
+    }
+
+    pub fn rotated(mut self, degrees: f64) -> Self {
+        self.degrees = degrees;
+        self
+    }
+
+    pub fn layer(mut self, layer: PcbLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    pub fn flags(mut self, flags: ComponentFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn fields(mut self, fields: &[(&str, &str)]) -> Self {
+        self.fields = Some(Fields { fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect() });
+        self
+    }
+
+    pub fn pads(mut self, pads: Vec<Pad>) -> Self {
+        self.pads = Some(pads);
+        self
+    }
+
+    /// The part's position
+    pub fn position(&self) -> Position {
+        Position::new(self.at, Angle::from_degrees(self.degrees))
+    }
+
+    pub fn spawn(self, world: &mut World) -> Entity {
+        let position = self.position();
+        let mut entity = world.spawn((
+            ComponentId { uuid: self.uuid.unwrap_or_else(|| self.reference.to_lowercase()) },
+            ComponentInfo { reference: self.reference.clone(), value: self.value, footprint_name: self.footprint },
+            position,
+            Layer { id: self.layer },
+            self.flags,
+        ));
+        if let Some(fields) = self.fields {
+            entity.insert(fields);
+        }
+        if let Some(mut pads) = self.pads {
+            for pad in pads.iter_mut().filter(|pad| pad.id.is_empty()) {
+                pad.id = format!("{}-{}", self.reference, pad.number);
+            }
+            entity.insert(Pads { pads });
+        }
+        entity.id()
+    }
+}
+
+/// A 1 mm square SMD pad at `offset` from its footprint, without copper shapes. Its id is
+/// filled in as "<reference>-<number>" when the part is spawned.
+pub fn pad(number: &str, net: Option<&str>, offset: Point) -> Pad {
+    Pad {
+        id: String::new(),
+        number: number.to_string(),
+        kind: PadKind::Smd,
+        offset,
+        net: net.map(str::to_string),
+        drill: None,
+        size: Point::from_mm(1.0, 1.0),
+        shapes: Vec::new(),
+        clearance: None,
+    }
+}
+
+/// `pad` with round copper of `diameter` on F.Cu, centered at `center` in board coordinates
+pub fn pad_with_copper(number: &str, net: &str, offset: Point, center: Point, diameter: Length) -> Pad {
+    let shape = Shape::stroke(&[center], false, diameter, &Tessellation::default());
+    Pad { shapes: vec![(PcbLayer::FCu, shape)], ..pad(number, Some(net), offset) }
+}
+
+/// A straight 0.2 mm track with its copper shape
+pub fn spawn_track(world: &mut World, uuid: &str, net: &str, start: Point, end: Point, layer: PcbLayer) -> Entity {
+    let track = Track { start, mid: None, end, width: Length::from_mm(0.2) };
+    let shape = track.to_shape(&Tessellation::default());
+    world.spawn((
+        ComponentId { uuid: uuid.to_string() },
+        track,
+        Layer { id: layer },
+        shape,
+        Net { name: net.to_string() },
+    )).id()
+}
+
+/// A 0.6 mm through via with its copper shape
+pub fn spawn_via(world: &mut World, net: &str, at: Point) -> Entity {
+    let via = Via {
+        drill: Length::from_mm(0.3),
+        diameter: Length::from_mm(0.6),
+        via_type: ViaType::Through,
+        start_layer: PcbLayer::FCu,
+        end_layer: PcbLayer::BCu,
+    };
+    let shape = via.to_shape(at, &Tessellation::default());
+    world.spawn((
+        ComponentId { uuid: format!("via-{}", at) },
+        via,
+        Position::new(at, Angle::ZERO),
+        shape,
+        Net { name: net.to_string() },
+    )).id()
+}
@@ -0,0 +1,526 @@
+//! Polygon geometry for board items
+//!
+//! KiCad describes shapes with primitives that may contain true arcs: polylines with arc nodes,
+//! polygons with holes, and graphic segments, rectangles, arcs, circles and Bézier curves. This
+//! module mirrors those primitives and converts them to polygons, approximating curves within a
+//! configurable `Tessellation` error. The resulting `Shape` component supports boolean
+//! operations, offsetting, area and minimum distance.
+//!
+//! Polygon coordinates are nanometers stored as `f64`, matching `Length`.
+
+use bevy_ecs::prelude::*;
+use geo::algorithm::buffer::{BufferStyle, LineCap, LineJoin};
+use geo::{Area, BooleanOps, BoundingRect, Buffer, Contains, Coord, Distance, Euclidean, Intersects, LineString, MultiPolygon, Polygon};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::{PI, TAU};
+use crate::components::{BoundingBox, Position};
+use crate::units::{Length, Point};
+
+/// Maximum deviation allowed when approximating curves with straight segments
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Tessellation {
+    pub max_error: Length,
+}
+
+impl Tessellation {
+    /// KiCad's default arc approximation error (`ARC_HIGH_DEF`, 5 µm)
+    pub const DEFAULT_MAX_ERROR: Length = Length::from_nm(5_000);
+
+    pub fn new(max_error: Length) -> Self {
+        Self { max_error }
+    }
+
+    /// Largest angle one segment may span on a circle of `radius`
+    pub fn max_step(&self, radius: Length) -> f64 {
+        let radius = radius.abs().nm() as f64;
+        let error = self.max_error.nm().max(1) as f64;
+        if error >= radius {
+            PI / 2.0
+        } else {
+            2.0 * (1.0 - error / radius).acos()
+        }
+    }
+
+    /// Number of segments needed for an arc of `sweep` radians on a circle of `radius`
+    pub fn segments(&self, radius: Length, sweep: f64) -> usize {
+        ((sweep.abs() / self.max_step(radius)).ceil() as usize).max(1)
+    }
+}
+
+impl Default for Tessellation {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_ERROR)
+    }
+}
+
+fn coord(point: Point) -> Coord<f64> {
+    Coord { x: point.x.nm() as f64, y: point.y.nm() as f64 }
+}
+
+fn point_at(center: Coord<f64>, radius: f64, angle: f64) -> Point {
+    Point::from_nm(
+        (center.x + radius * angle.cos()).round() as i64,
+        (center.y + radius * angle.sin()).round() as i64,
+    )
+}
+
+/// Points along a three-point arc, including both ends
+pub fn arc_points(start: Point, mid: Point, end: Point, tessellation: &Tessellation) -> Vec<Point> {
+    let (a, b, c) = (coord(start), coord(mid), coord(end));
+    let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+    if d.abs() < f64::EPSILON {
+        // Collinear points: a degenerate arc is a straight segment
+        return vec![start, end];
+    }
+    let sq = |p: Coord<f64>| p.x * p.x + p.y * p.y;
+    let center = Coord {
+        x: (sq(a) * (b.y - c.y) + sq(b) * (c.y - a.y) + sq(c) * (a.y - b.y)) / d,
+        y: (sq(a) * (c.x - b.x) + sq(b) * (a.x - c.x) + sq(c) * (b.x - a.x)) / d,
+    };
+    let radius = (a - center).x.hypot((a - center).y);
+    let angle = |p: Coord<f64>| (p.y - center.y).atan2(p.x - center.x);
+    let (start_angle, mid_angle) = (angle(a), angle(b));
+
+    // Sweep from start to end in whichever direction passes through mid
+    let mut sweep = (angle(c) - start_angle).rem_euclid(TAU);
+    if (mid_angle - start_angle).rem_euclid(TAU) > sweep {
+        sweep -= TAU;
+    }
+    if sweep == 0.0 {
+        sweep = TAU;
+    }
+
+    let segments = tessellation.segments(Length::from_nm(radius.round() as i64), sweep);
+    let mut points = Vec::with_capacity(segments + 1);
+    points.push(start);
+    for i in 1..segments {
+        points.push(point_at(center, radius, start_angle + sweep * i as f64 / segments as f64));
+    }
+    points.push(end);
+    points
+}
+
+/// Points around a full circle, without repeating the first point
+pub fn circle_points(center: Point, radius: Length, tessellation: &Tessellation) -> Vec<Point> {
+    let segments = tessellation.segments(radius, TAU).max(8);
+    let radius = radius.abs().nm() as f64;
+    (0..segments)
+        .map(|i| point_at(coord(center), radius, TAU * i as f64 / segments as f64))
+        .collect()
+}
+
+/// Points along a cubic Bézier curve, including both ends
+pub fn bezier_points(start: Point, control1: Point, control2: Point, end: Point, tessellation: &Tessellation) -> Vec<Point> {
+    let (p0, p1, p2, p3) = (coord(start), coord(control1), coord(control2), coord(end));
+    // Wang's formula bounds the flattening error by the control polygon's second differences
+    let second = |a: Coord<f64>, b: Coord<f64>, c: Coord<f64>| {
+        let d = a - b * 2.0 + c;
+        d.x.hypot(d.y)
+    };
+    let deviation = second(p0, p1, p2).max(second(p1, p2, p3));
+    let error = tessellation.max_error.nm().max(1) as f64;
+    let segments = ((0.75 * deviation / error).sqrt().ceil() as usize).clamp(1, 1000);
+
+    let mut points = Vec::with_capacity(segments + 1);
+    points.push(start);
+    for i in 1..segments {
+        let t = i as f64 / segments as f64;
+        let u = 1.0 - t;
+        let p = p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t);
+        points.push(Point::from_nm(p.x.round() as i64, p.y.round() as i64));
+    }
+    points.push(end);
+    points
+}
+
+/// A node of a polyline: a point, or an arc whose start and end are not repeated as points
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PolyLineNode {
+    Point(Point),
+    Arc { start: Point, mid: Point, end: Point },
+}
+
+/// An open or closed polyline that may contain arcs (KiCad's `SHAPE_LINE_CHAIN`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolyLine {
+    pub nodes: Vec<PolyLineNode>,
+    pub closed: bool,
+}
+
+impl PolyLine {
+    /// The polyline with arcs tessellated, without consecutive duplicate points
+    pub fn points(&self, tessellation: &Tessellation) -> Vec<Point> {
+        let mut points: Vec<Point> = Vec::new();
+        for node in &self.nodes {
+            match *node {
+                PolyLineNode::Point(point) => points.push(point),
+                PolyLineNode::Arc { start, mid, end } => points.extend(arc_points(start, mid, end, tessellation)),
+            }
+        }
+        points.dedup();
+        if self.closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        points
+    }
+
+    /// The polyline with every point mapped through `f`
+    pub fn map_points(&self, f: &impl Fn(Point) -> Point) -> PolyLine {
+        let nodes = self.nodes
+            .iter()
+            .map(|node| match *node {
+                PolyLineNode::Point(point) => PolyLineNode::Point(f(point)),
+                PolyLineNode::Arc { start, mid, end } => PolyLineNode::Arc { start: f(start), mid: f(mid), end: f(end) },
+            })
+            .collect();
+        PolyLine { nodes, closed: self.closed }
+    }
+}
+
+fn line_string(points: &[Point], closed: bool) -> LineString<f64> {
+    let mut line: LineString<f64> = points.iter().map(|point| coord(*point)).collect();
+    if closed {
+        line.close();
+    }
+    line
+}
+
+/// A polygon outline with holes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolygonWithHoles {
+    pub outline: PolyLine,
+    pub holes: Vec<PolyLine>,
+}
+
+impl PolygonWithHoles {
+    /// A polygon with straight edges from a `geo` polygon in nanometer coordinates
+    pub fn from_polygon(polygon: &Polygon<f64>) -> Self {
+        let closed = |line: &LineString<f64>| {
+            let mut points: Vec<Point> = line.coords().map(|c| Point::from_nm(c.x.round() as i64, c.y.round() as i64)).collect();
+            if points.len() > 1 && points.first() == points.last() {
+                points.pop();
+            }
+            PolyLine { nodes: points.into_iter().map(PolyLineNode::Point).collect(), closed: true }
+        };
+        PolygonWithHoles {
+            outline: closed(polygon.exterior()),
+            holes: polygon.interiors().iter().map(closed).collect(),
+        }
+    }
+
+    pub fn to_polygon(&self, tessellation: &Tessellation) -> Polygon<f64> {
+        let closed = |line: &PolyLine| line_string(&line.points(tessellation), true);
+        Polygon::new(closed(&self.outline), self.holes.iter().map(closed).collect())
+    }
+
+    pub fn to_shape(&self, tessellation: &Tessellation) -> Shape {
+        Shape::from_polygon(self.to_polygon(tessellation))
+    }
+}
+
+/// A set of polygons with holes (KiCad's `SHAPE_POLY_SET`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PolySet {
+    pub polygons: Vec<PolygonWithHoles>,
+}
+
+impl PolySet {
+    /// The union of all polygons in the set
+    pub fn to_shape(&self, tessellation: &Tessellation) -> Shape {
+        self.polygons
+            .iter()
+            .map(|polygon| polygon.to_shape(tessellation))
+            .fold(Shape::default(), |acc, shape| acc.union(&shape))
+    }
+}
+
+/// Geometry of a graphic item
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphicShape {
+    Segment { start: Point, end: Point },
+    Rectangle { top_left: Point, bottom_right: Point },
+    Arc { start: Point, mid: Point, end: Point },
+    Circle { center: Point, radius_point: Point },
+    Polygon(PolySet),
+    Bezier { start: Point, control1: Point, control2: Point, end: Point },
+}
+
+impl GraphicShape {
+    /// Whether the shape's outline is a closed curve
+    pub fn is_closed(&self) -> bool {
+        matches!(self, GraphicShape::Rectangle { .. } | GraphicShape::Circle { .. } | GraphicShape::Polygon(_))
+    }
+
+    /// The start and end of an open shape's path
+    pub fn endpoints(&self) -> Option<(Point, Point)> {
+        match *self {
+            GraphicShape::Segment { start, end }
+            | GraphicShape::Arc { start, end, .. }
+            | GraphicShape::Bezier { start, end, .. } => Some((start, end)),
+            _ => None,
+        }
+    }
+
+    /// The centerline of the shape as tessellated paths; closed paths do not repeat their first
+    /// point. Polygons give one path per outline and hole.
+    pub fn paths(&self, tessellation: &Tessellation) -> Vec<Vec<Point>> {
+        match self {
+            GraphicShape::Segment { start, end } => vec![vec![*start, *end]],
+            GraphicShape::Rectangle { top_left, bottom_right } => vec![vec![
+                *top_left,
+                Point::new(bottom_right.x, top_left.y),
+                *bottom_right,
+                Point::new(top_left.x, bottom_right.y),
+            ]],
+            GraphicShape::Arc { start, mid, end } => vec![arc_points(*start, *mid, *end, tessellation)],
+            GraphicShape::Circle { center, radius_point } => {
+                vec![circle_points(*center, center.distance(radius_point), tessellation)]
+            }
+            GraphicShape::Polygon(set) => set.polygons
+                .iter()
+                .flat_map(|polygon| std::iter::once(&polygon.outline).chain(&polygon.holes))
+                .map(|line| line.points(tessellation))
+                .collect(),
+            GraphicShape::Bezier { start, control1, control2, end } => {
+                vec![bezier_points(*start, *control1, *control2, *end, tessellation)]
+            }
+        }
+    }
+
+    /// The shape with every point mapped through `f`. Rectangles become polygons so that they
+    /// stay correct under rotation.
+    pub fn map_points(&self, f: &impl Fn(Point) -> Point) -> GraphicShape {
+        match self {
+            GraphicShape::Segment { start, end } => GraphicShape::Segment { start: f(*start), end: f(*end) },
+            GraphicShape::Rectangle { top_left, bottom_right } => {
+                let corners = [
+                    *top_left,
+                    Point::new(bottom_right.x, top_left.y),
+                    *bottom_right,
+                    Point::new(top_left.x, bottom_right.y),
+                ];
+                let outline = PolyLine {
+                    nodes: corners.into_iter().map(|corner| PolyLineNode::Point(f(corner))).collect(),
+                    closed: true,
+                };
+                GraphicShape::Polygon(PolySet { polygons: vec![PolygonWithHoles { outline, holes: Vec::new() }] })
+            }
+            GraphicShape::Arc { start, mid, end } => GraphicShape::Arc { start: f(*start), mid: f(*mid), end: f(*end) },
+            GraphicShape::Circle { center, radius_point } => {
+                GraphicShape::Circle { center: f(*center), radius_point: f(*radius_point) }
+            }
+            GraphicShape::Polygon(set) => GraphicShape::Polygon(PolySet {
+                polygons: set.polygons
+                    .iter()
+                    .map(|polygon| PolygonWithHoles {
+                        outline: polygon.outline.map_points(f),
+                        holes: polygon.holes.iter().map(|hole| hole.map_points(f)).collect(),
+                    })
+                    .collect(),
+            }),
+            GraphicShape::Bezier { start, control1, control2, end } => GraphicShape::Bezier {
+                start: f(*start),
+                control1: f(*control1),
+                control2: f(*control2),
+                end: f(*end),
+            },
+        }
+    }
+
+    /// The area enclosed by a closed shape
+    pub fn fill(&self, tessellation: &Tessellation) -> Shape {
+        match self {
+            GraphicShape::Polygon(set) => set.to_shape(tessellation),
+            shape if shape.is_closed() => shape
+                .paths(tessellation)
+                .iter()
+                .map(|path| Shape::from_polygon(Polygon::new(line_string(path, true), vec![])))
+                .fold(Shape::default(), |acc, shape| acc.union(&shape)),
+            _ => Shape::default(),
+        }
+    }
+}
+
+/// A graphic item: its geometry, stroke width and whether closed shapes are filled
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graphic {
+    pub shape: GraphicShape,
+    pub stroke_width: Length,
+    pub filled: bool,
+}
+
+impl Graphic {
+    /// A footprint graphic moved from footprint coordinates to the board, given the footprint's
+    /// position
+    pub fn placed(&self, footprint: &Position) -> Graphic {
+        let origin = footprint.point();
+        Graphic {
+            shape: self.shape.map_points(&|point| (origin + point).rotated(footprint.rotation, &origin)),
+            ..self.clone()
+        }
+    }
+
+    /// The copper (or ink) area covered by the stroke and fill
+    pub fn to_shape(&self, tessellation: &Tessellation) -> Shape {
+        let mut shape = if self.filled { self.shape.fill(tessellation) } else { Shape::default() };
+        if self.stroke_width > Length::ZERO {
+            let closed = self.shape.is_closed();
+            for path in self.shape.paths(tessellation) {
+                shape = shape.union(&Shape::stroke(&path, closed, self.stroke_width, tessellation));
+            }
+        }
+        shape
+    }
+}
+
+/// The area covered by an item, as polygons with holes in nanometer coordinates
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shape {
+    pub polygons: MultiPolygon<f64>,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Self::new(MultiPolygon::new(Vec::new()))
+    }
+}
+
+impl Shape {
+    pub fn new(polygons: MultiPolygon<f64>) -> Self {
+        Self { polygons }
+    }
+
+    pub fn from_polygon(polygon: Polygon<f64>) -> Self {
+        Self::new(MultiPolygon::new(vec![polygon]))
+    }
+
+    /// A path drawn with a round pen of the given width
+    pub fn stroke(path: &[Point], closed: bool, width: Length, tessellation: &Tessellation) -> Self {
+        if path.is_empty() || width <= Length::ZERO {
+            return Self::default();
+        }
+        let line = line_string(path, closed);
+        if path.len() == 1 {
+            return Self::new(geo::Point::from(line.0[0]).buffer_with_style(round_style(width / 2, tessellation)));
+        }
+        Self::new(line.buffer_with_style(round_style(width / 2, tessellation)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polygons.0.is_empty()
+    }
+
+    /// Enclosed area in square nanometers
+    pub fn area(&self) -> f64 {
+        self.polygons.unsigned_area()
+    }
+
+    /// Enclosed area in square millimeters
+    pub fn area_mm2(&self) -> f64 {
+        self.area() / 1e12
+    }
+
+    pub fn union(&self, other: &Shape) -> Shape {
+        if self.is_empty() {
+            return other.clone();
+        }
+        if other.is_empty() {
+            return self.clone();
+        }
+        Shape::new(self.polygons.union(&other.polygons))
+    }
+
+    pub fn intersection(&self, other: &Shape) -> Shape {
+        Shape::new(self.polygons.intersection(&other.polygons))
+    }
+
+    pub fn difference(&self, other: &Shape) -> Shape {
+        Shape::new(self.polygons.difference(&other.polygons))
+    }
+
+    pub fn xor(&self, other: &Shape) -> Shape {
+        Shape::new(self.polygons.xor(&other.polygons))
+    }
+
+    /// Grow (positive distance) or shrink (negative distance) the shape with rounded corners
+    pub fn offset(&self, distance: Length, tessellation: &Tessellation) -> Shape {
+        if distance == Length::ZERO || self.is_empty() {
+            return self.clone();
+        }
+        Shape::new(self.polygons.buffer_with_style(round_style(distance, tessellation)))
+    }
+
+    /// Minimum distance between the two shapes; zero when they touch or overlap
+    pub fn distance(&self, other: &Shape) -> Length {
+        Length::from_nm(Euclidean.distance(&self.polygons, &other.polygons).round() as i64)
+    }
+
+    pub fn intersects(&self, other: &Shape) -> bool {
+        self.polygons.intersects(&other.polygons)
+    }
+
+    /// Whether a point lies strictly inside the shape
+    pub fn contains(&self, point: Point) -> bool {
+        self.polygons.contains(&geo::Point::from(coord(point)))
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.polygons.bounding_rect().map(|rect| {
+            let corner = |c: Coord<f64>| Point::from_nm(c.x.round() as i64, c.y.round() as i64);
+            BoundingBox::new(corner(rect.min()), corner(rect.max()))
+        })
+    }
+}
+
+fn round_style(distance: Length, tessellation: &Tessellation) -> BufferStyle<f64> {
+    let step = tessellation.max_step(distance);
+    BufferStyle::new(distance.nm() as f64)
+        .line_join(LineJoin::Round(step))
+        .line_cap(LineCap::Round(step))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arcs_stay_within_tessellation_error() {
+        let tessellation = Tessellation::new(Length::from_mm(0.01));
+        let center = Point::ORIGIN;
+        let radius = Length::from_mm(5.0);
+        let points = arc_points(Point::from_mm(5.0, 0.0), Point::from_mm(0.0, 5.0), Point::from_mm(-5.0, 0.0), &tessellation);
+        assert!(points.len() > 3);
+        assert!(points.iter().all(|p| (center.distance(p) - radius).abs() <= Length::from_nm(1)));
+        assert!(points.iter().all(|p| p.y >= Length::ZERO), "arc should pass through the mid point");
+
+        let circle = GraphicShape::Circle { center, radius_point: Point::from_mm(5.0, 0.0) };
+        let area = circle.fill(&tessellation).area_mm2();
+        // An inscribed polygon loses area, but never more than the error band around the edge
+        assert!(area < PI * 25.0 && area > PI * 4.99 * 4.99);
+    }
+
+    #[test]
+    fn boolean_ops_offset_and_distance() {
+        let tessellation = Tessellation::default();
+        let square = |x: f64, y: f64, size: f64| GraphicShape::Rectangle {
+            top_left: Point::from_mm(x, y),
+            bottom_right: Point::from_mm(x + size, y + size),
+        }.fill(&tessellation);
+
+        let a = square(0.0, 0.0, 2.0);
+        let b = square(1.0, 1.0, 2.0);
+        assert!((a.union(&b).area_mm2() - 7.0).abs() < 1e-6);
+        assert!((a.intersection(&b).area_mm2() - 1.0).abs() < 1e-6);
+        assert!((a.difference(&b).area_mm2() - 3.0).abs() < 1e-6);
+
+        let c = square(5.0, 0.0, 1.0);
+        assert_eq!(a.distance(&c), Length::from_mm(3.0));
+        assert_eq!(a.distance(&b), Length::ZERO);
+        assert!(a.offset(Length::from_mm(3.5), &tessellation).intersects(&c));
+        assert!(!a.offset(Length::from_mm(-0.5), &tessellation).contains(Point::from_mm(0.25, 1.0)));
+
+        let track = Shape::stroke(&[Point::ORIGIN, Point::from_mm(10.0, 0.0)], false, Length::from_mm(0.2), &tessellation);
+        // 10 mm x 0.2 mm body plus two inscribed round caps
+        assert!(track.area_mm2() > 2.02 && track.area_mm2() < 2.0 + PI * 0.01);
+    }
+}
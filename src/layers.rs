@@ -0,0 +1,270 @@
+//! Typed board layers
+//!
+//! Mirrors KiCad's `BoardLayer` set with canonical layer names, plus the user-visible names a
+//! board may assign to them in its stackup.
+
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Side of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    Front,
+    Back,
+}
+
+impl Side {
+    /// The opposite side of the board
+    pub fn flipped(self) -> Self {
+        match self {
+            Side::Front => Side::Back,
+            Side::Back => Side::Front,
+        }
+    }
+}
+
+/// A KiCad board layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PcbLayer {
+    FCu,
+    /// Inner copper layer, numbered 1 to 30 (In1.Cu is closest to F.Cu)
+    InCu(u8),
+    BCu,
+    FAdhes,
+    BAdhes,
+    FPaste,
+    BPaste,
+    FSilkS,
+    BSilkS,
+    FMask,
+    BMask,
+    DwgsUser,
+    CmtsUser,
+    Eco1User,
+    Eco2User,
+    EdgeCuts,
+    Margin,
+    FCrtYd,
+    BCrtYd,
+    FFab,
+    BFab,
+    /// User layer, numbered 1 to 9
+    User(u8),
+    Rescue,
+    /// Unknown, undefined or unselected layer
+    Undefined,
+}
+
+impl PcbLayer {
+    /// Maximum number of inner copper layers KiCad supports
+    pub const MAX_INNER_COPPER: u8 = 30;
+
+    /// Number of user layers KiCad supports
+    pub const MAX_USER: u8 = 9;
+
+    /// KiCad's canonical layer name, e.g. "F.Cu", "In2.Cu", "Edge.Cuts", "User.3"
+    pub fn canonical_name(&self) -> String {
+        match self {
+            PcbLayer::InCu(n) => format!("In{}.Cu", n),
+            PcbLayer::User(n) => format!("User.{}", n),
+            other => other.fixed_name().to_string(),
+        }
+    }
+
+    fn fixed_name(&self) -> &'static str {
+        match self {
+            PcbLayer::FCu => "F.Cu",
+            PcbLayer::BCu => "B.Cu",
+            PcbLayer::FAdhes => "F.Adhes",
+            PcbLayer::BAdhes => "B.Adhes",
+            PcbLayer::FPaste => "F.Paste",
+            PcbLayer::BPaste => "B.Paste",
+            PcbLayer::FSilkS => "F.SilkS",
+            PcbLayer::BSilkS => "B.SilkS",
+            PcbLayer::FMask => "F.Mask",
+            PcbLayer::BMask => "B.Mask",
+            PcbLayer::DwgsUser => "Dwgs.User",
+            PcbLayer::CmtsUser => "Cmts.User",
+            PcbLayer::Eco1User => "Eco1.User",
+            PcbLayer::Eco2User => "Eco2.User",
+            PcbLayer::EdgeCuts => "Edge.Cuts",
+            PcbLayer::Margin => "Margin",
+            PcbLayer::FCrtYd => "F.CrtYd",
+            PcbLayer::BCrtYd => "B.CrtYd",
+            PcbLayer::FFab => "F.Fab",
+            PcbLayer::BFab => "B.Fab",
+            PcbLayer::Rescue => "Rescue",
+            PcbLayer::InCu(_) | PcbLayer::User(_) | PcbLayer::Undefined => "Undefined",
+        }
+    }
+
+    /// Parse a canonical layer name. KiCad's long-form aliases ("F.Silkscreen", "B.Courtyard",
+    /// "User.Drawings", ...) are accepted as well.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(n) = name.strip_prefix("In").and_then(|rest| rest.strip_suffix(".Cu")) {
+            let n: u8 = n.parse().ok()?;
+            return (1..=Self::MAX_INNER_COPPER).contains(&n).then_some(PcbLayer::InCu(n));
+        }
+        if let Some(n) = name.strip_prefix("User.") {
+            if let Ok(n) = n.parse::<u8>() {
+                return (1..=Self::MAX_USER).contains(&n).then_some(PcbLayer::User(n));
+            }
+        }
+
+        let layer = match name {
+            "F.Cu" => PcbLayer::FCu,
+            "B.Cu" => PcbLayer::BCu,
+            "F.Adhes" | "F.Adhesive" => PcbLayer::FAdhes,
+            "B.Adhes" | "B.Adhesive" => PcbLayer::BAdhes,
+            "F.Paste" => PcbLayer::FPaste,
+            "B.Paste" => PcbLayer::BPaste,
+            "F.SilkS" | "F.Silkscreen" => PcbLayer::FSilkS,
+            "B.SilkS" | "B.Silkscreen" => PcbLayer::BSilkS,
+            "F.Mask" => PcbLayer::FMask,
+            "B.Mask" => PcbLayer::BMask,
+            "Dwgs.User" | "User.Drawings" => PcbLayer::DwgsUser,
+            "Cmts.User" | "User.Comments" => PcbLayer::CmtsUser,
+            "Eco1.User" | "User.Eco1" => PcbLayer::Eco1User,
+            "Eco2.User" | "User.Eco2" => PcbLayer::Eco2User,
+            "Edge.Cuts" => PcbLayer::EdgeCuts,
+            "Margin" => PcbLayer::Margin,
+            "F.CrtYd" | "F.Courtyard" => PcbLayer::FCrtYd,
+            "B.CrtYd" | "B.Courtyard" => PcbLayer::BCrtYd,
+            "F.Fab" => PcbLayer::FFab,
+            "B.Fab" => PcbLayer::BFab,
+            "Rescue" => PcbLayer::Rescue,
+            _ => return None,
+        };
+        Some(layer)
+    }
+
+    /// Whether this is a copper layer
+    pub fn is_copper(&self) -> bool {
+        matches!(self, PcbLayer::FCu | PcbLayer::InCu(_) | PcbLayer::BCu)
+    }
+
+    /// Whether this is an inner copper layer
+    pub fn is_inner_copper(&self) -> bool {
+        matches!(self, PcbLayer::InCu(_))
+    }
+
+    /// Which side of the board this layer belongs to, if any
+    pub fn side(&self) -> Option<Side> {
+        match self {
+            PcbLayer::FCu | PcbLayer::FAdhes | PcbLayer::FPaste | PcbLayer::FSilkS
+            | PcbLayer::FMask | PcbLayer::FCrtYd | PcbLayer::FFab => Some(Side::Front),
+            PcbLayer::BCu | PcbLayer::BAdhes | PcbLayer::BPaste | PcbLayer::BSilkS
+            | PcbLayer::BMask | PcbLayer::BCrtYd | PcbLayer::BFab => Some(Side::Back),
+            _ => None,
+        }
+    }
+
+    /// The matching layer on the other side of the board (as when a footprint is flipped).
+    /// Layers without a side are returned unchanged.
+    pub fn flipped(&self) -> Self {
+        match self {
+            PcbLayer::FCu => PcbLayer::BCu,
+            PcbLayer::BCu => PcbLayer::FCu,
+            PcbLayer::FAdhes => PcbLayer::BAdhes,
+            PcbLayer::BAdhes => PcbLayer::FAdhes,
+            PcbLayer::FPaste => PcbLayer::BPaste,
+            PcbLayer::BPaste => PcbLayer::FPaste,
+            PcbLayer::FSilkS => PcbLayer::BSilkS,
+            PcbLayer::BSilkS => PcbLayer::FSilkS,
+            PcbLayer::FMask => PcbLayer::BMask,
+            PcbLayer::BMask => PcbLayer::FMask,
+            PcbLayer::FCrtYd => PcbLayer::BCrtYd,
+            PcbLayer::BCrtYd => PcbLayer::FCrtYd,
+            PcbLayer::FFab => PcbLayer::BFab,
+            PcbLayer::BFab => PcbLayer::FFab,
+            other => *other,
+        }
+    }
+
+    /// The silkscreen layer on the given side
+    pub fn silkscreen(side: Side) -> Self {
+        match side {
+            Side::Front => PcbLayer::FSilkS,
+            Side::Back => PcbLayer::BSilkS,
+        }
+    }
+
+    /// The courtyard layer on the given side
+    pub fn courtyard(side: Side) -> Self {
+        match side {
+            Side::Front => PcbLayer::FCrtYd,
+            Side::Back => PcbLayer::BCrtYd,
+        }
+    }
+
+    /// The outer copper layer on the given side
+    pub fn outer_copper(side: Side) -> Self {
+        match side {
+            Side::Front => PcbLayer::FCu,
+            Side::Back => PcbLayer::BCu,
+        }
+    }
+}
+
+impl fmt::Display for PcbLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.canonical_name())
+    }
+}
+
+/// User-visible layer names, as renamed in the board's stackup
+///
+/// Layers without an entry use their canonical name.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct LayerNames {
+    pub names: HashMap<PcbLayer, String>,
+}
+
+impl LayerNames {
+    /// The user-visible name of a layer
+    pub fn name(&self, layer: PcbLayer) -> String {
+        self.names
+            .get(&layer)
+            .cloned()
+            .unwrap_or_else(|| layer.canonical_name())
+    }
+
+    /// Find a layer by either its user-visible or canonical name
+    pub fn lookup(&self, name: &str) -> Option<PcbLayer> {
+        self.names
+            .iter()
+            .find(|(_, user_name)| user_name.as_str() == name)
+            .map(|(layer, _)| *layer)
+            .or_else(|| PcbLayer::from_name(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_names_round_trip() {
+        let layers = [
+            PcbLayer::FCu, PcbLayer::InCu(1), PcbLayer::InCu(30), PcbLayer::BCu,
+            PcbLayer::FSilkS, PcbLayer::BMask, PcbLayer::EdgeCuts, PcbLayer::FCrtYd,
+            PcbLayer::BFab, PcbLayer::User(9), PcbLayer::Eco2User,
+        ];
+        for layer in layers {
+            assert_eq!(PcbLayer::from_name(&layer.canonical_name()), Some(layer));
+        }
+        assert_eq!(PcbLayer::from_name("In31.Cu"), None);
+        assert_eq!(PcbLayer::from_name("F.Courtyard"), Some(PcbLayer::FCrtYd));
+    }
+
+    #[test]
+    fn sides_and_flipping() {
+        assert_eq!(PcbLayer::FSilkS.side(), Some(Side::Front));
+        assert_eq!(PcbLayer::InCu(2).side(), None);
+        assert_eq!(PcbLayer::FCrtYd.flipped(), PcbLayer::BCrtYd);
+        assert!(PcbLayer::InCu(2).is_copper());
+        assert!(!PcbLayer::EdgeCuts.is_copper());
+    }
+}
@@ -5,15 +5,57 @@
 //! This crate provides an ECS-based approach to working with KiCad board data,
 //! enabling flexible queries, extensible analysis, and high-performance processing.
 
+pub mod actions;
+pub mod board_file;
+pub mod bom;
+pub mod classify;
 pub mod client;
 pub mod components;
+pub mod cpl;
+pub mod decoupling;
+pub mod diff;
+pub mod drc;
+#[cfg(test)]
+mod fixtures;
+pub mod geometry;
+pub mod layers;
+pub mod mounting;
+pub mod netlist;
+pub mod outline;
+pub mod placement;
+pub mod sexpr;
+pub mod snapshot;
+pub mod snippet;
+pub mod spatial;
 pub mod systems;
 pub mod world;
 pub mod tracing;
+pub mod units;
+pub mod values;
+pub mod variant;
+pub mod view;
 
 pub mod prelude {
-    pub use crate::client::KiCadClient;
+    pub use crate::actions::{ActionStatus, PcbAction};
+    pub use crate::board_file::BoardFile;
+    pub use crate::bom::{Bom, BomColumn, BomFormat, BomOptions};
+    pub use crate::classify::{Classification, ClassificationRegistry};
+    pub use crate::client::{KiCadClient, SaveCopyOptions};
     pub use crate::components::*;
+    pub use crate::cpl::{AuxOrigin, Cpl, CplFormat, CplOptions, CplOrigin};
+    pub use crate::decoupling::{Decoupling, DecouplingRules};
+    pub use crate::diff::BoardDiff;
+    pub use crate::drc::{DesignRules, Severity, Violation};
+    pub use crate::geometry::{Shape, Tessellation};
+    pub use crate::layers::{LayerNames, PcbLayer, Side};
+    pub use crate::outline::BoardOutline;
+    pub use crate::placement::PlacementRules;
+    pub use crate::snapshot::{SnapshotFormat, SnapshotRegistry};
+    pub use crate::snippet::{Snippet, SnippetLibrary, SnippetPlacement};
+    pub use crate::spatial::SpatialIndex;
+    pub use crate::units::{Angle, Length, Point};
+    pub use crate::variant::{EffectivePart, VariantOverrides};
+    pub use crate::view::{Appearance, ViewPreset, ViewPresets};
     pub use crate::world::PcbWorld;
     pub use bevy_ecs::prelude::*;
     pub use eyre::Result;
@@ -0,0 +1,206 @@
+//! Mounting hole detection
+//!
+//! Mounting holes are recognized from pad geometry rather than reference designators: a footprint
+//! whose pads are all through-hole, share a single pad number (the main hole plus any stitching
+//! vias around it) and whose largest drill is at least a screw clearance hole. The drill diameter
+//! is then matched against ISO 273 metric and unified (UNC/UNF) clearance hole tables.
+
+use serde::{Deserialize, Serialize};
+use crate::components::{MountingHole, Pad, PadKind, Pads};
+use crate::units::Length;
+use std::fmt;
+
+/// Clearance fit of a hole for a screw, per ISO 273 (fine/medium/coarse) or the unified
+/// close/free fit tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HoleFit {
+    Close,
+    Normal,
+    Loose,
+}
+
+impl fmt::Display for HoleFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HoleFit::Close => "close",
+            HoleFit::Normal => "normal",
+            HoleFit::Loose => "loose",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A screw size with its clearance holes and the washer that bounds its keepout ring
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrewSize {
+    pub name: &'static str,
+    /// Close, normal and loose clearance hole diameters, in millimeters
+    pub clearance_mm: [f64; 3],
+    /// Outer diameter of the matching flat washer, in millimeters
+    pub washer_mm: f64,
+}
+
+impl ScrewSize {
+    /// The clearance hole diameter for a fit
+    pub fn clearance(&self, fit: HoleFit) -> Length {
+        let index = match fit {
+            HoleFit::Close => 0,
+            HoleFit::Normal => 1,
+            HoleFit::Loose => 2,
+        };
+        Length::from_mm(self.clearance_mm[index])
+    }
+
+    /// The washer outer diameter
+    pub fn washer_diameter(&self) -> Length {
+        Length::from_mm(self.washer_mm)
+    }
+
+    /// How a hole of `diameter` fits this screw, if it is a clearance hole for it at all
+    pub fn fit(&self, diameter: Length) -> Option<HoleFit> {
+        let [close, normal, loose] = self.clearance_mm.map(Length::from_mm);
+        if diameter < close - FIT_TOLERANCE || diameter > loose + FIT_TOLERANCE {
+            None
+        } else if diameter < close + (normal - close) / 2 {
+            Some(HoleFit::Close)
+        } else if diameter < normal + (loose - normal) / 2 {
+            Some(HoleFit::Normal)
+        } else {
+            Some(HoleFit::Loose)
+        }
+    }
+}
+
+/// Allowance for holes drawn slightly under or over the tabulated clearance diameters
+const FIT_TOLERANCE: Length = Length::from_nm(50_000);
+
+/// ISO 273 clearance holes and ISO 7089 washers
+pub const METRIC_SCREWS: &[ScrewSize] = &[
+    ScrewSize { name: "M1.6", clearance_mm: [1.7, 1.8, 2.0], washer_mm: 4.0 },
+    ScrewSize { name: "M2", clearance_mm: [2.2, 2.4, 2.6], washer_mm: 5.0 },
+    ScrewSize { name: "M2.5", clearance_mm: [2.7, 2.9, 3.1], washer_mm: 6.0 },
+    ScrewSize { name: "M3", clearance_mm: [3.2, 3.4, 3.6], washer_mm: 7.0 },
+    ScrewSize { name: "M4", clearance_mm: [4.3, 4.5, 4.8], washer_mm: 9.0 },
+    ScrewSize { name: "M5", clearance_mm: [5.3, 5.5, 5.8], washer_mm: 10.0 },
+    ScrewSize { name: "M6", clearance_mm: [6.4, 6.6, 7.0], washer_mm: 12.0 },
+    ScrewSize { name: "M8", clearance_mm: [8.4, 9.0, 10.0], washer_mm: 16.0 },
+    ScrewSize { name: "M10", clearance_mm: [10.5, 11.0, 12.0], washer_mm: 20.0 },
+];
+
+/// Unified screw close/free fit clearance holes and SAE washers; the loose fit is the next
+/// common drill size up
+pub const UNIFIED_SCREWS: &[ScrewSize] = &[
+    ScrewSize { name: "#2-56", clearance_mm: [2.26, 2.44, 2.64], washer_mm: 6.35 },
+    ScrewSize { name: "#4-40", clearance_mm: [2.95, 3.26, 3.45], washer_mm: 7.94 },
+    ScrewSize { name: "#6-32", clearance_mm: [3.66, 3.80, 4.04], washer_mm: 9.53 },
+    ScrewSize { name: "#8-32", clearance_mm: [4.31, 4.50, 4.70], washer_mm: 9.53 },
+    ScrewSize { name: "#10-24", clearance_mm: [4.98, 5.11, 5.41], washer_mm: 11.11 },
+    ScrewSize { name: "1/4-20", clearance_mm: [6.53, 6.76, 7.14], washer_mm: 15.88 },
+];
+
+/// Smallest drill treated as a mounting hole (the close clearance hole for M1.6)
+pub const MIN_MOUNTING_HOLE_DRILL: Length = Length::from_nm(1_700_000);
+
+/// Find the screw a hole is a clearance hole for, preferring metric sizes and the closest fit
+pub fn match_screw(diameter: Length) -> Option<(&'static ScrewSize, HoleFit)> {
+    best_fit(METRIC_SCREWS, diameter).or_else(|| best_fit(UNIFIED_SCREWS, diameter))
+}
+
+/// All screws a hole is a clearance hole for, metric first
+pub fn matching_screws(diameter: Length) -> Vec<(&'static ScrewSize, HoleFit)> {
+    METRIC_SCREWS
+        .iter()
+        .chain(UNIFIED_SCREWS)
+        .filter_map(|screw| screw.fit(diameter).map(|fit| (screw, fit)))
+        .collect()
+}
+
+fn best_fit(table: &'static [ScrewSize], diameter: Length) -> Option<(&'static ScrewSize, HoleFit)> {
+    table
+        .iter()
+        .filter_map(|screw| screw.fit(diameter).map(|fit| (screw, fit)))
+        .min_by_key(|(screw, fit)| (screw.clearance(*fit) - diameter).abs())
+}
+
+/// Derive mounting hole parameters from a footprint's pads
+///
+/// Returns `None` unless the footprint looks like a mounting hole. Footprints from a
+/// "MountingHole" library or with that in their name are accepted with any drill size.
+pub fn detect_mounting_hole(footprint_name: &str, pads: &Pads) -> Option<MountingHole> {
+    if pads.pads.is_empty() || !pads.pads.iter().all(Pad::is_through_hole) {
+        return None;
+    }
+
+    let (main, diameter) = pads.pads
+        .iter()
+        .filter_map(|pad| pad.drill.map(|drill| (pad, drill)))
+        .max_by_key(|(_, drill)| *drill)?;
+    if pads.pads.iter().any(|pad| !pad.number.is_empty() && pad.number != main.number) {
+        return None;
+    }
+    if diameter < MIN_MOUNTING_HOLE_DRILL && !footprint_name.contains("MountingHole") {
+        return None;
+    }
+
+    let plated = main.kind == PadKind::Pth;
+    let pad_diameter = Some(main.size.x.min(main.size.y)).filter(|size| plated && *size > diameter);
+    let annulus = pad_diameter.map(|pad| (pad - diameter) / 2);
+    let screw = match_screw(diameter);
+    let keepout_diameter = screw
+        .map(|(screw, _)| screw.washer_diameter())
+        .unwrap_or(diameter * 2)
+        .max(pad_diameter.unwrap_or(diameter));
+
+    Some(MountingHole {
+        diameter,
+        plated,
+        pad_diameter,
+        annulus,
+        screw_size: screw.map(|(screw, _)| screw.name.to_string()),
+        fit: screw.map(|(_, fit)| fit),
+        keepout_diameter,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Point;
+
+    fn pad(number: &str, kind: PadKind, drill_mm: f64, size_mm: f64) -> Pad {
+        Pad {
+            id: String::new(),
+            number: number.to_string(),
+            kind,
+            offset: Point::ORIGIN,
+            net: None,
+            drill: Some(Length::from_mm(drill_mm)),
+            size: Point::from_mm(size_mm, size_mm),
+            shapes: Vec::new(),
+            clearance: None,
+        }
+    }
+
+    #[test]
+    fn screw_sizes_from_clearance_holes() {
+        assert_eq!(match_screw(Length::from_mm(3.2)).map(|(s, f)| (s.name, f)), Some(("M3", HoleFit::Close)));
+        assert_eq!(match_screw(Length::from_mm(4.5)).map(|(s, f)| (s.name, f)), Some(("M4", HoleFit::Normal)));
+        assert_eq!(match_screw(Length::from_mm(3.8)).map(|(s, _)| s.name), Some("#6-32"));
+        assert!(matching_screws(Length::from_mm(3.26)).iter().any(|(s, _)| s.name == "#4-40"));
+        assert_eq!(match_screw(Length::from_mm(1.0)), None);
+    }
+
+    #[test]
+    fn detects_plated_hole_with_stitching_vias() {
+        let mut pads = vec![pad("1", PadKind::Pth, 3.2, 6.0)];
+        pads.extend((0..8).map(|_| pad("1", PadKind::Pth, 0.7, 1.2)));
+        let hole = detect_mounting_hole("MountingHole:MountingHole_3.2mm_M3_Pad_Via", &Pads { pads }).unwrap();
+        assert!(hole.plated);
+        assert_eq!(hole.screw_size.as_deref(), Some("M3"));
+        assert_eq!(hole.annulus, Some(Length::from_mm(1.4)));
+        assert_eq!(hole.keepout_diameter, Length::from_mm(7.0));
+
+        let connector = Pads { pads: vec![pad("1", PadKind::Pth, 1.0, 1.7), pad("2", PadKind::Pth, 1.0, 1.7)] };
+        assert!(detect_mounting_hole("Connector:Conn_01x02", &connector).is_none());
+    }
+}
@@ -0,0 +1,812 @@
+//! Schematic connectivity and netlist extraction
+//!
+//! Builds nets from schematic wire, bus, junction, label and pin entities, and compares the
+//! result against the nets reported by the board. Coordinates are integer nanometers so that
+//! connection points match exactly.
+
+use bevy_ecs::prelude::*;
+use eyre::{bail, Result};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::RTree;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use tracing::{debug, info, instrument, warn};
+use crate::client::NetData;
+use crate::components::{ComponentInfo, Net, NetPins, Pads, PinRef, SchematicNet};
+use crate::units::Point;
+
+// ===== Schematic components =====
+
+/// Sheet instance an item belongs to, e.g. "/" for the root sheet or "/power/" for a child
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SheetPath {
+    pub path: String,
+}
+
+/// Whether a schematic line carries a single signal or a bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WireKind {
+    Wire,
+    Bus,
+}
+
+/// A wire or bus segment on a schematic sheet
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct SchematicWire {
+    pub start: Point,
+    pub end: Point,
+    pub kind: WireKind,
+}
+
+/// A junction dot, connecting wires that cross at their midpoints
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct SchematicJunction {
+    pub position: Point,
+}
+
+/// Scope of a schematic label
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelKind {
+    /// Connects to labels with the same name on the same sheet
+    Local,
+    /// Connects to labels with the same name on every sheet
+    Global,
+    /// Connects to the matching sheet pin on the parent sheet
+    Hierarchical,
+    /// A power symbol; connects globally like a global label
+    Power,
+}
+
+/// A net label or power symbol anchored at `position`
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct SchematicLabel {
+    pub text: String,
+    pub position: Point,
+    pub kind: LabelKind,
+}
+
+/// A symbol pin connection point
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct SchematicPin {
+    pub reference: String,  // R1, U3, etc.
+    pub number: String,     // 1, 2, A4, etc.
+    pub position: Point,
+}
+
+/// A sheet pin on the parent sheet, connecting to the hierarchical label of the same name in
+/// `child_sheet`
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct SchematicSheetPin {
+    pub child_sheet: String,
+    pub name: String,
+    pub position: Point,
+}
+
+// ===== Bus expansion =====
+
+/// Widest bus `expand_bus` accepts, so a label such as `D[0..99999999]` cannot exhaust memory
+pub const MAX_BUS_WIDTH: usize = 1024;
+
+/// Expand a bus label into its member signal names
+///
+/// Supports vector buses (`D[0..7]`), bus groups (`{SDA SCL}`) and named groups
+/// (`I2C{SDA SCL}` → `I2C.SDA`, `I2C.SCL`). Returns `None` for plain net names, and an error
+/// for buses with more than `MAX_BUS_WIDTH` members.
+pub fn expand_bus(label: &str) -> Result<Option<Vec<String>>> {
+    let label = label.trim();
+
+    if let Some(open) = label.find('{') {
+        let Some(close) = label.rfind('}') else {
+            return Ok(None);
+        };
+        if close < open {
+            return Ok(None);
+        }
+        let prefix = &label[..open];
+        let mut members = Vec::new();
+        for member in label[open + 1..close].split_whitespace() {
+            let expanded = expand_vector(member)?.unwrap_or_else(|| vec![member.to_string()]);
+            for name in expanded {
+                if prefix.is_empty() {
+                    members.push(name);
+                } else {
+                    members.push(format!("{}.{}", prefix, name));
+                }
+            }
+            if members.len() > MAX_BUS_WIDTH {
+                bail!("Bus {} has more than {} members", label, MAX_BUS_WIDTH);
+            }
+        }
+        return Ok(Some(members));
+    }
+
+    expand_vector(label)
+}
+
+/// Expand a vector bus such as `D[0..7]` or `A[15..8]`
+fn expand_vector(label: &str) -> Result<Option<Vec<String>>> {
+    let Some((prefix, first, last)) = vector_range(label) else {
+        return Ok(None);
+    };
+    let width = first.abs_diff(last).saturating_add(1);
+    if width > MAX_BUS_WIDTH as u64 {
+        bail!("Bus {} has {} members, more than {}", label, width, MAX_BUS_WIDTH);
+    }
+
+    let indices: Vec<i64> = if first <= last {
+        (first..=last).collect()
+    } else {
+        (last..=first).rev().collect()
+    };
+    Ok(Some(indices.into_iter().map(|i| format!("{}{}", prefix, i)).collect()))
+}
+
+/// Prefix and index range of a vector bus label
+fn vector_range(label: &str) -> Option<(&str, i64, i64)> {
+    let open = label.find('[')?;
+    let inner = label.strip_suffix(']')?.get(open + 1..)?;
+    let (first, last) = inner.split_once("..")?;
+    let first: i64 = first.trim().parse().ok()?;
+    let last: i64 = last.trim().parse().ok()?;
+    Some((&label[..open], first, last))
+}
+
+/// `expand_bus` for connectivity: oversized buses are reported and treated as plain names
+fn expand_bus_or_warn(label: &str) -> Option<Vec<String>> {
+    expand_bus(label).unwrap_or_else(|err| {
+        warn!("{:#}", err);
+        None
+    })
+}
+
+// ===== Connectivity solver =====
+
+/// Disjoint-set forest used to merge connected items
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new() -> Self {
+        Self { parent: Vec::new() }
+    }
+
+    fn add(&mut self) -> usize {
+        self.parent.push(self.parent.len());
+        self.parent.len() - 1
+    }
+
+    fn find(&mut self, mut node: usize) -> usize {
+        while self.parent[node] != node {
+            self.parent[node] = self.parent[self.parent[node]];
+            node = self.parent[node];
+        }
+        node
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            self.parent[b] = a;
+        }
+    }
+}
+
+/// Scope a net name is resolved in
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum NameScope {
+    Sheet(String, String),
+    Global(String),
+}
+
+/// What a solver node represents, used for naming nets afterwards
+#[derive(Debug, Clone)]
+enum NodeKind {
+    Wire,
+    Pin(PinRef),
+    Label { text: String, kind: LabelKind, sheet: String },
+    SheetPin { name: String, sheet: String },
+    Name,
+}
+
+/// Point-like connection item on a sheet
+struct Anchor {
+    node: usize,
+    position: Point,
+}
+
+#[derive(Default)]
+struct SheetItems {
+    wires: Vec<(usize, SchematicWire)>,
+    buses: Vec<(usize, SchematicWire)>,
+    junctions: Vec<Point>,
+    anchors: Vec<Anchor>,
+    bus_anchors: Vec<Anchor>,
+}
+
+struct Solver {
+    uf: UnionFind,
+    kinds: Vec<NodeKind>,
+    names: HashMap<NameScope, usize>,
+}
+
+impl Solver {
+    fn node(&mut self, kind: NodeKind) -> usize {
+        self.kinds.push(kind);
+        self.uf.add()
+    }
+
+    fn name_node(&mut self, scope: NameScope) -> usize {
+        if let Some(&node) = self.names.get(&scope) {
+            return node;
+        }
+        let node = self.node(NodeKind::Name);
+        self.names.insert(scope, node);
+        node
+    }
+}
+
+fn on_segment(point: Point, start: Point, end: Point) -> bool {
+    let cross = (end.x - start.x).nm() as i128 * (point.y - start.y).nm() as i128
+        - (end.y - start.y).nm() as i128 * (point.x - start.x).nm() as i128;
+    cross == 0
+        && point.x >= start.x.min(end.x) && point.x <= start.x.max(end.x)
+        && point.y >= start.y.min(end.y) && point.y <= start.y.max(end.y)
+}
+
+/// Segments on a sheet with an R-tree over their bounds, for finding those through a point
+struct SegmentIndex<'a> {
+    segments: &'a [(usize, SchematicWire)],
+    tree: RTree<GeomWithData<Rectangle<[i64; 2]>, usize>>,
+}
+
+impl<'a> SegmentIndex<'a> {
+    fn new(segments: &'a [(usize, SchematicWire)]) -> Self {
+        let tree = RTree::bulk_load(
+            segments
+                .iter()
+                .enumerate()
+                .map(|(index, (_, segment))| {
+                    let corner = |point: Point| [point.x.nm(), point.y.nm()];
+                    GeomWithData::new(Rectangle::from_corners(corner(segment.start), corner(segment.end)), index)
+                })
+                .collect(),
+        );
+        Self { segments, tree }
+    }
+
+    /// Nodes of the segments that `point` lies on
+    fn nodes_at(&self, point: Point) -> impl Iterator<Item = usize> + '_ {
+        self.tree
+            .locate_all_at_point(&[point.x.nm(), point.y.nm()])
+            .map(|entry| &self.segments[entry.data])
+            .filter(move |(_, segment)| on_segment(point, segment.start, segment.end))
+            .map(|(node, _)| *node)
+    }
+}
+
+/// Merge wires that touch end-to-line, or cross at a junction
+///
+/// Every such contact happens at an endpoint or a junction, so only the segments through those
+/// points are merged.
+fn connect_segments(uf: &mut UnionFind, segments: &SegmentIndex, junctions: &[Point]) {
+    let mut points: HashSet<Point> = junctions.iter().copied().collect();
+    points.extend(segments.segments.iter().flat_map(|(_, segment)| [segment.start, segment.end]));
+    for point in points {
+        let mut nodes = segments.nodes_at(point);
+        if let Some(first) = nodes.next() {
+            for node in nodes {
+                uf.union(first, node);
+            }
+        }
+    }
+}
+
+/// Merge point-like items with the segments they sit on and with each other
+fn connect_anchors(uf: &mut UnionFind, segments: &SegmentIndex, anchors: &[Anchor]) {
+    let mut at_position: HashMap<Point, usize> = HashMap::new();
+    for anchor in anchors {
+        for node in segments.nodes_at(anchor.position) {
+            uf.union(anchor.node, node);
+        }
+        match at_position.entry(anchor.position) {
+            Entry::Occupied(other) => uf.union(*other.get(), anchor.node),
+            Entry::Vacant(entry) => {
+                entry.insert(anchor.node);
+            }
+        }
+    }
+}
+
+/// Compare references naturally so that R2 sorts before R10
+fn natural_key(reference: &str) -> (String, u64, String) {
+    let split = reference.find(|c: char| c.is_ascii_digit()).unwrap_or(reference.len());
+    let (prefix, rest) = reference.split_at(split);
+    let digits_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let number = rest[..digits_end].parse().unwrap_or(0);
+    (prefix.to_string(), number, rest[digits_end..].to_string())
+}
+
+/// Pick a KiCad-style name for a set of connected nodes
+fn net_name(members: &[&NodeKind], pins: &[PinRef]) -> Option<String> {
+    // Global labels and power symbols take priority, then labels on the shallowest sheet
+    let mut best: Option<(u8, usize, String)> = None;
+    for kind in members {
+        let candidate = match kind {
+            NodeKind::Label { text, kind: LabelKind::Global | LabelKind::Power, .. } => {
+                (0, 0, text.clone())
+            }
+            NodeKind::Label { text, sheet, .. } => {
+                (1, sheet.matches('/').count(), format!("{}{}", sheet, text))
+            }
+            NodeKind::SheetPin { name, sheet } => {
+                (2, sheet.matches('/').count(), format!("{}{}", sheet, name))
+            }
+            _ => continue,
+        };
+        if best.as_ref().is_none_or(|b| candidate < *b) {
+            best = Some(candidate);
+        }
+    }
+    if let Some((_, _, name)) = best {
+        return Some(name);
+    }
+
+    let first = pins.first()?;
+    let prefix = if pins.len() > 1 { "Net" } else { "unconnected" };
+    Some(format!("{}-({}-Pad{})", prefix, first.reference, first.number))
+}
+
+/// Build schematic nets from the schematic entities in `world`
+///
+/// Previously extracted `SchematicNet` entities are replaced. Returns the spawned net entities.
+#[instrument(skip(world))]
+pub fn extract_nets(world: &mut World) -> Vec<Entity> {
+    let mut solver = Solver {
+        uf: UnionFind::new(),
+        kinds: Vec::new(),
+        names: HashMap::new(),
+    };
+    let mut sheets: HashMap<String, SheetItems> = HashMap::new();
+    // (child sheet, pin name, parent sheet, node)
+    let mut sheet_pins: Vec<(String, String, String, usize)> = Vec::new();
+    // Bus labels and bus sheet pins only carry member names, never a net of their own
+    let mut bus_nodes: BTreeSet<usize> = BTreeSet::new();
+
+    let sheet_of = |path: Option<&SheetPath>| path.map_or_else(|| "/".to_string(), |p| p.path.clone());
+
+    let mut wire_query = world.query::<(&SchematicWire, Option<&SheetPath>)>();
+    for (wire, path) in wire_query.iter(world) {
+        let node = solver.node(NodeKind::Wire);
+        let items = sheets.entry(sheet_of(path)).or_default();
+        match wire.kind {
+            WireKind::Wire => items.wires.push((node, wire.clone())),
+            WireKind::Bus => items.buses.push((node, wire.clone())),
+        }
+    }
+
+    let mut junction_query = world.query::<(&SchematicJunction, Option<&SheetPath>)>();
+    for (junction, path) in junction_query.iter(world) {
+        sheets.entry(sheet_of(path)).or_default().junctions.push(junction.position);
+    }
+
+    let mut pin_query = world.query::<(&SchematicPin, Option<&SheetPath>)>();
+    for (pin, path) in pin_query.iter(world) {
+        let node = solver.node(NodeKind::Pin(PinRef {
+            reference: pin.reference.clone(),
+            number: pin.number.clone(),
+        }));
+        sheets.entry(sheet_of(path)).or_default().anchors.push(Anchor { node, position: pin.position });
+    }
+
+    let mut label_query = world.query::<(&SchematicLabel, Option<&SheetPath>)>();
+    let labels: Vec<(SchematicLabel, String)> = label_query
+        .iter(world)
+        .map(|(label, path)| (label.clone(), sheet_of(path)))
+        .collect();
+    // Bus labels and their expanded members, resolved once bus groups are known
+    let mut bus_labels: Vec<(usize, String, LabelKind, Vec<String>)> = Vec::new();
+    for (label, sheet) in labels {
+        let node = solver.node(NodeKind::Label {
+            text: label.text.clone(),
+            kind: label.kind,
+            sheet: sheet.clone(),
+        });
+        let anchor = Anchor { node, position: label.position };
+        if let Some(members) = expand_bus_or_warn(&label.text) {
+            bus_nodes.insert(node);
+            sheets.entry(sheet.clone()).or_default().bus_anchors.push(anchor);
+            bus_labels.push((node, sheet, label.kind, members));
+            continue;
+        }
+
+        let scope = match label.kind {
+            LabelKind::Global | LabelKind::Power => NameScope::Global(label.text.clone()),
+            LabelKind::Local | LabelKind::Hierarchical => NameScope::Sheet(sheet.clone(), label.text.clone()),
+        };
+        let name = solver.name_node(scope);
+        solver.uf.union(node, name);
+        sheets.entry(sheet).or_default().anchors.push(anchor);
+    }
+
+    let mut sheet_pin_query = world.query::<(&SchematicSheetPin, Option<&SheetPath>)>();
+    let pins: Vec<(SchematicSheetPin, String)> = sheet_pin_query
+        .iter(world)
+        .map(|(pin, path)| (pin.clone(), sheet_of(path)))
+        .collect();
+    for (pin, sheet) in pins {
+        let node = solver.node(NodeKind::SheetPin { name: pin.name.clone(), sheet: sheet.clone() });
+        let anchor = Anchor { node, position: pin.position };
+        let items = sheets.entry(sheet.clone()).or_default();
+        if expand_bus_or_warn(&pin.name).is_some() {
+            bus_nodes.insert(node);
+            items.bus_anchors.push(anchor);
+        } else {
+            items.anchors.push(anchor);
+        }
+        sheet_pins.push((pin.child_sheet, pin.name, sheet, node));
+    }
+
+    // Geometric connectivity within each sheet
+    for items in sheets.values() {
+        let wires = SegmentIndex::new(&items.wires);
+        connect_segments(&mut solver.uf, &wires, &items.junctions);
+        connect_anchors(&mut solver.uf, &wires, &items.anchors);
+        let buses = SegmentIndex::new(&items.buses);
+        connect_segments(&mut solver.uf, &buses, &items.junctions);
+        connect_anchors(&mut solver.uf, &buses, &items.bus_anchors);
+    }
+
+    // Each bus group takes its members from the first label attached to it (sorted for
+    // determinism); those members connect by name within the label's scope.
+    let mut bus_members: HashMap<usize, (String, LabelKind, Vec<String>)> = HashMap::new();
+    bus_labels.sort_by(|a, b| (&a.1, &a.3).cmp(&(&b.1, &b.3)));
+    for (node, sheet, kind, members) in &bus_labels {
+        let root = solver.uf.find(*node);
+        bus_members.entry(root).or_insert_with(|| (sheet.clone(), *kind, members.clone()));
+        for member in members {
+            let scope = match kind {
+                LabelKind::Global | LabelKind::Power => NameScope::Global(member.clone()),
+                _ => NameScope::Sheet(sheet.clone(), member.clone()),
+            };
+            solver.name_node(scope);
+        }
+    }
+
+    // Sheet pins connect to the hierarchical label of the same name in the child sheet.
+    // Bus sheet pins connect member-by-member to the bus they sit on in the parent.
+    for (child, name, parent, node) in &sheet_pins {
+        match expand_bus_or_warn(name) {
+            None => {
+                let scope = solver.name_node(NameScope::Sheet(child.clone(), name.clone()));
+                solver.uf.union(*node, scope);
+            }
+            Some(child_members) => {
+                let root = solver.uf.find(*node);
+                let parent_members = bus_members
+                    .get(&root)
+                    .map(|(_, _, members)| members.clone())
+                    .unwrap_or_else(|| child_members.clone());
+                for (child_member, parent_member) in child_members.iter().zip(parent_members.iter()) {
+                    let a = solver.name_node(NameScope::Sheet(child.clone(), child_member.clone()));
+                    let b = solver.name_node(NameScope::Sheet(parent.clone(), parent_member.clone()));
+                    solver.uf.union(a, b);
+                }
+            }
+        }
+    }
+
+    // Group nodes by their root and name each resulting net
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for node in 0..solver.kinds.len() {
+        let root = solver.uf.find(node);
+        groups.entry(root).or_default().push(node);
+    }
+
+    let mut nets: Vec<(String, Vec<PinRef>)> = Vec::new();
+    for nodes in groups.values() {
+        let members: Vec<&NodeKind> = nodes
+            .iter()
+            .filter(|node| !bus_nodes.contains(node))
+            .map(|&node| &solver.kinds[node])
+            .collect();
+        let mut pins: Vec<PinRef> = members
+            .iter()
+            .filter_map(|kind| match kind {
+                NodeKind::Pin(pin) => Some(pin.clone()),
+                _ => None,
+            })
+            .collect();
+        pins.sort_by(|a, b| natural_key(&a.reference).cmp(&natural_key(&b.reference)).then(a.number.cmp(&b.number)));
+
+        let has_items = members.iter().any(|kind| !matches!(kind, NodeKind::Name | NodeKind::Wire));
+        if !has_items {
+            continue;
+        }
+        if let Some(name) = net_name(&members, &pins) {
+            nets.push((name, pins));
+        }
+    }
+    nets.sort_by(|a, b| a.0.cmp(&b.0));
+
+    // Replace nets from any previous extraction
+    let mut old_query = world.query_filtered::<Entity, With<SchematicNet>>();
+    let old: Vec<Entity> = old_query.iter(world).collect();
+    for entity in old {
+        world.despawn(entity);
+    }
+
+    let entities: Vec<Entity> = nets
+        .into_iter()
+        .map(|(name, pins)| {
+            debug!("Schematic net {} with {} pins", name, pins.len());
+            world.spawn((Net { name }, NetPins { pins }, SchematicNet)).id()
+        })
+        .collect();
+
+    info!("Extracted {} schematic nets", entities.len());
+    entities
+}
+
+// ===== Board comparison =====
+
+/// A difference between the schematic-derived netlist and the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetDiscrepancy {
+    /// The schematic has a net the board does not
+    MissingOnBoard { net: String },
+    /// The board has a net the schematic does not
+    MissingInSchematic { net: String },
+    /// A net on both sides connects different pads
+    PadsDiffer {
+        net: String,
+        /// Schematic pins whose footprint pad is not on the net on the board
+        missing_on_board: Vec<PinRef>,
+        /// Board pads on the net whose schematic pin is not
+        missing_in_schematic: Vec<PinRef>,
+    },
+}
+
+/// Compare extracted schematic nets against the nets reported by the board (`GetNets`)
+///
+/// Nets present on both sides are also compared pad by pad against the nets of the footprint
+/// pads in the world; with no footprints loaded only the net names are compared.
+#[instrument(skip(world, board_nets))]
+pub fn compare_with_board(world: &mut World, board_nets: &[NetData]) -> Vec<NetDiscrepancy> {
+    let mut query = world.query_filtered::<(&Net, Option<&NetPins>), With<SchematicNet>>();
+    let schematic: HashMap<String, BTreeSet<PinRef>> = query
+        .iter(world)
+        .map(|(net, pins)| (net.name.clone(), pins.map(|pins| pins.pins.iter().cloned().collect()).unwrap_or_default()))
+        .collect();
+    // Net code 0 is the unnamed "no net" entry
+    let board: BTreeSet<&str> = board_nets
+        .iter()
+        .map(|net| net.name.as_str())
+        .filter(|name| !name.is_empty())
+        .collect();
+    let schematic_names: BTreeSet<&str> = schematic.keys().map(String::as_str).collect();
+
+    let mut discrepancies: Vec<NetDiscrepancy> = schematic_names
+        .difference(&board)
+        .map(|net| NetDiscrepancy::MissingOnBoard { net: net.to_string() })
+        .collect();
+    discrepancies.extend(
+        board
+            .difference(&schematic_names)
+            .map(|net| NetDiscrepancy::MissingInSchematic { net: net.to_string() }),
+    );
+
+    // Pads by net, from the footprints; unnumbered mechanical pads have no schematic pin
+    let mut footprint_query = world.query::<(&ComponentInfo, &Pads)>();
+    let mut footprints = 0;
+    let mut board_pins: HashMap<&str, BTreeSet<PinRef>> = HashMap::new();
+    for (info, pads) in footprint_query.iter(world) {
+        footprints += 1;
+        for pad in pads.pads.iter().filter(|pad| !pad.number.is_empty()) {
+            if let Some(net) = &pad.net {
+                board_pins.entry(net.as_str()).or_default().insert(PinRef {
+                    reference: info.reference.clone(),
+                    number: pad.number.clone(),
+                });
+            }
+        }
+    }
+    if footprints > 0 {
+        let no_pins = BTreeSet::new();
+        for net in schematic_names.intersection(&board) {
+            let schematic_pins = &schematic[*net];
+            let pads = board_pins.get(net).unwrap_or(&no_pins);
+            let missing_on_board: Vec<PinRef> = schematic_pins.difference(pads).cloned().collect();
+            let missing_in_schematic: Vec<PinRef> = pads.difference(schematic_pins).cloned().collect();
+            if !missing_on_board.is_empty() || !missing_in_schematic.is_empty() {
+                discrepancies.push(NetDiscrepancy::PadsDiffer {
+                    net: net.to_string(),
+                    missing_on_board,
+                    missing_in_schematic,
+                });
+            }
+        }
+    }
+
+    for discrepancy in &discrepancies {
+        warn!("Netlist discrepancy: {:?}", discrepancy);
+    }
+    discrepancies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{pad, part};
+
+    fn p(x_mm: i64, y_mm: i64) -> Point {
+        Point::from_nm(x_mm * 1_000_000, y_mm * 1_000_000)
+    }
+
+    #[test]
+    fn expands_vector_and_group_buses() {
+        assert_eq!(expand_bus("D[0..2]").unwrap().unwrap(), vec!["D0", "D1", "D2"]);
+        assert_eq!(expand_bus("A[2..1]").unwrap().unwrap(), vec!["A2", "A1"]);
+        assert_eq!(expand_bus("I2C{SDA SCL}").unwrap().unwrap(), vec!["I2C.SDA", "I2C.SCL"]);
+        assert!(expand_bus("VCC").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_oversized_buses() {
+        assert_eq!(expand_bus("D[1..1024]").unwrap().unwrap().len(), MAX_BUS_WIDTH);
+        assert!(expand_bus("D[0..1024]").is_err());
+        assert!(expand_bus("D[-9223372036854775808..9223372036854775807]").is_err());
+        assert!(expand_bus("{A[0..600] B[0..600]}").is_err());
+
+        // An oversized bus label is treated as a plain name rather than aborting extraction
+        let mut world = World::new();
+        world.spawn(SchematicWire { start: p(0, 0), end: p(10, 0), kind: WireKind::Wire });
+        world.spawn(SchematicLabel { text: "D[0..99999999]".into(), position: p(0, 0), kind: LabelKind::Local });
+        world.spawn(SchematicPin { reference: "U1".into(), number: "1".into(), position: p(10, 0) });
+        extract_nets(&mut world);
+        let mut query = world.query::<&Net>();
+        assert_eq!(query.single(&world).name, "/D[0..99999999]");
+    }
+
+    #[test]
+    fn names_nets_from_labels_and_pins() {
+        let mut world = World::new();
+        world.spawn(SchematicWire { start: p(0, 0), end: p(10, 0), kind: WireKind::Wire });
+        world.spawn(SchematicPin { reference: "R1".into(), number: "1".into(), position: p(0, 0) });
+        world.spawn(SchematicPin { reference: "C1".into(), number: "2".into(), position: p(10, 0) });
+        world.spawn(SchematicLabel { text: "VCC".into(), position: p(5, 0), kind: LabelKind::Power });
+
+        // Power symbol on another sheet joins the same global net
+        world.spawn((
+            SchematicPin { reference: "U1".into(), number: "8".into(), position: p(0, 0) },
+            SheetPath { path: "/mcu/".into() },
+        ));
+        world.spawn((
+            SchematicLabel { text: "VCC".into(), position: p(0, 0), kind: LabelKind::Power },
+            SheetPath { path: "/mcu/".into() },
+        ));
+        world.spawn(SchematicPin { reference: "R2".into(), number: "1".into(), position: p(50, 0) });
+
+        extract_nets(&mut world);
+        let mut query = world.query::<(&Net, &NetPins)>();
+        let mut nets: Vec<(String, usize)> = query.iter(&world).map(|(n, p)| (n.name.clone(), p.pins.len())).collect();
+        nets.sort();
+        assert_eq!(nets, vec![("VCC".to_string(), 3), ("unconnected-(R2-Pad1)".to_string(), 1)]);
+    }
+
+    #[test]
+    fn connects_bus_members_through_sheet_pins() {
+        let mut world = World::new();
+        let child = SheetPath { path: "/io/".into() };
+
+        // Parent: bus labelled DATA[0..1] running into a D[0..1] sheet pin, DATA0 broken out
+        world.spawn(SchematicWire { start: p(0, 0), end: p(20, 0), kind: WireKind::Bus });
+        world.spawn(SchematicLabel { text: "DATA[0..1]".into(), position: p(5, 0), kind: LabelKind::Local });
+        world.spawn(SchematicSheetPin { child_sheet: "/io/".into(), name: "D[0..1]".into(), position: p(20, 0) });
+        world.spawn(SchematicWire { start: p(0, 10), end: p(10, 10), kind: WireKind::Wire });
+        world.spawn(SchematicLabel { text: "DATA0".into(), position: p(0, 10), kind: LabelKind::Local });
+        world.spawn(SchematicPin { reference: "U1".into(), number: "3".into(), position: p(10, 10) });
+
+        // Child: hierarchical bus label, D0 broken out to a pin
+        world.spawn((SchematicLabel { text: "D[0..1]".into(), position: p(0, 0), kind: LabelKind::Hierarchical }, child.clone()));
+        world.spawn((SchematicWire { start: p(0, 5), end: p(10, 5), kind: WireKind::Wire }, child.clone()));
+        world.spawn((SchematicLabel { text: "D0".into(), position: p(0, 5), kind: LabelKind::Local }, child.clone()));
+        world.spawn((SchematicPin { reference: "J1".into(), number: "1".into(), position: p(10, 5) }, child));
+
+        extract_nets(&mut world);
+        let mut query = world.query::<(&Net, &NetPins)>();
+        let nets: Vec<(String, Vec<PinRef>)> = query.iter(&world).map(|(n, p)| (n.name.clone(), p.pins.clone())).collect();
+        assert_eq!(nets.len(), 1);
+        assert_eq!(nets[0].0, "/DATA0");
+        assert_eq!(nets[0].1.len(), 2);
+    }
+
+    #[test]
+    fn connects_tees_and_junctions_but_not_bare_crossings() {
+        let mut world = World::new();
+        // A tee onto the middle of a wire, and a wire crossing it at a junction dot
+        world.spawn(SchematicWire { start: p(0, 0), end: p(20, 0), kind: WireKind::Wire });
+        world.spawn(SchematicWire { start: p(5, 0), end: p(5, 10), kind: WireKind::Wire });
+        world.spawn(SchematicWire { start: p(10, -10), end: p(10, 10), kind: WireKind::Wire });
+        world.spawn(SchematicJunction { position: p(10, 0) });
+        // Crosses the main wire without a junction
+        world.spawn(SchematicWire { start: p(15, -10), end: p(15, 10), kind: WireKind::Wire });
+        world.spawn(SchematicPin { reference: "R1".into(), number: "1".into(), position: p(0, 0) });
+        world.spawn(SchematicPin { reference: "R2".into(), number: "1".into(), position: p(5, 10) });
+        world.spawn(SchematicPin { reference: "R3".into(), number: "1".into(), position: p(10, 10) });
+        world.spawn(SchematicPin { reference: "R4".into(), number: "1".into(), position: p(15, 10) });
+
+        extract_nets(&mut world);
+        let mut query = world.query::<(&Net, &NetPins)>();
+        let mut nets: Vec<(String, usize)> = query.iter(&world).map(|(n, p)| (n.name.clone(), p.pins.len())).collect();
+        nets.sort();
+        assert_eq!(nets, vec![("Net-(R1-Pad1)".to_string(), 3), ("unconnected-(R4-Pad1)".to_string(), 1)]);
+    }
+
+    fn pin(reference: &str, number: &str) -> PinRef {
+        PinRef { reference: reference.into(), number: number.into() }
+    }
+
+    fn spawn_schematic_net(world: &mut World, name: &str, pins: Vec<PinRef>) {
+        world.spawn((Net { name: name.into() }, NetPins { pins }, SchematicNet));
+    }
+
+    fn spawn_footprint(world: &mut World, reference: &str, pads: &[(&str, Option<&str>)]) {
+        let pads = pads.iter().map(|(number, net)| pad(number, *net, Point::ORIGIN)).collect();
+        part(reference).pads(pads).spawn(world);
+    }
+
+    fn board_nets(names: &[&str]) -> Vec<NetData> {
+        names.iter().enumerate().map(|(code, name)| NetData { code: code as i32, name: name.to_string() }).collect()
+    }
+
+    #[test]
+    fn compares_net_names_without_footprints() {
+        let mut world = World::new();
+        spawn_schematic_net(&mut world, "VCC", vec![pin("R1", "1")]);
+        spawn_schematic_net(&mut world, "SDA", vec![pin("R2", "1")]);
+
+        let mut discrepancies = compare_with_board(&mut world, &board_nets(&["", "VCC", "GND"]));
+        discrepancies.sort_by_key(|d| format!("{:?}", d));
+        assert_eq!(discrepancies, vec![
+            NetDiscrepancy::MissingInSchematic { net: "GND".into() },
+            NetDiscrepancy::MissingOnBoard { net: "SDA".into() },
+        ]);
+    }
+
+    #[test]
+    fn reports_pads_on_the_wrong_net() {
+        let mut world = World::new();
+        spawn_schematic_net(&mut world, "VCC", vec![pin("R1", "1"), pin("C1", "1")]);
+        spawn_schematic_net(&mut world, "GND", vec![pin("C1", "2"), pin("R1", "2")]);
+        // C1's pads are swapped on the board; the unnumbered mounting pad is ignored
+        spawn_footprint(&mut world, "R1", &[("1", Some("VCC")), ("2", Some("GND"))]);
+        spawn_footprint(&mut world, "C1", &[("1", Some("GND")), ("2", Some("VCC")), ("", Some("GND"))]);
+
+        let mut discrepancies = compare_with_board(&mut world, &board_nets(&["", "VCC", "GND"]));
+        discrepancies.sort_by_key(|d| format!("{:?}", d));
+        assert_eq!(discrepancies, vec![
+            NetDiscrepancy::PadsDiffer {
+                net: "GND".into(),
+                missing_on_board: vec![pin("C1", "2")],
+                missing_in_schematic: vec![pin("C1", "1")],
+            },
+            NetDiscrepancy::PadsDiffer {
+                net: "VCC".into(),
+                missing_on_board: vec![pin("C1", "1")],
+                missing_in_schematic: vec![pin("C1", "2")],
+            },
+        ]);
+
+        // A pad left unconnected on the board is missing from its net
+        let mut world = World::new();
+        spawn_schematic_net(&mut world, "VCC", vec![pin("R1", "1")]);
+        spawn_footprint(&mut world, "R1", &[("1", None)]);
+        assert_eq!(compare_with_board(&mut world, &board_nets(&["VCC"])), vec![NetDiscrepancy::PadsDiffer {
+            net: "VCC".into(),
+            missing_on_board: vec![pin("R1", "1")],
+            missing_in_schematic: Vec::new(),
+        }]);
+    }
+}
@@ -0,0 +1,316 @@
+//! Board outline assembly from Edge.Cuts
+//!
+//! Edge.Cuts is drawn as loose graphic items. Closed items (rectangles, circles, polygons) are
+//! contours on their own; segments, arcs and Béziers are chained end to end into contours, with
+//! endpoints within a chaining tolerance treated as joined. Contours are then nested: a contour
+//! inside an odd number of others is a cutout of the contour directly around it.
+//!
+//! Problems that would make KiCad's own outline invalid are reported rather than fatal, so the
+//! usable part of an outline is still available.
+
+use bevy_ecs::prelude::*;
+use geo::{Contains, Distance, Euclidean, LineString, MultiPolygon, Polygon};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::{debug, instrument, warn};
+use crate::components::BoundingBox;
+use crate::geometry::{Graphic, Shape, Tessellation};
+use crate::units::{Length, Point};
+
+/// Endpoints closer than this are joined when chaining Edge.Cuts items
+pub const DEFAULT_CHAINING_EPSILON: Length = Length::from_nm(10_000);
+
+/// A problem found while assembling the outline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OutlineIssue {
+    /// A chain of items whose ends do not meet
+    OpenContour { start: Point, end: Point },
+    /// Two edges of the outline cross
+    SelfIntersection { location: Point },
+}
+
+impl fmt::Display for OutlineIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutlineIssue::OpenContour { start, end } => {
+                write!(f, "Open contour from {:.3} to {:.3}", start, end)
+            }
+            OutlineIssue::SelfIntersection { location } => {
+                write!(f, "Outline crosses itself at {:.3}", location)
+            }
+        }
+    }
+}
+
+/// The board boundary assembled from Edge.Cuts
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BoardOutline {
+    /// Closed contours as tessellated rings, without repeating the first point
+    pub contours: Vec<Vec<Point>>,
+    /// Board area: outer contours minus cutouts
+    pub shape: Shape,
+    pub issues: Vec<OutlineIssue>,
+}
+
+impl BoardOutline {
+    /// Assemble the outline from the graphic items on Edge.Cuts
+    #[instrument(skip(graphics, tessellation), fields(items = graphics.len()))]
+    pub fn from_graphics(graphics: &[Graphic], tessellation: &Tessellation, chaining_epsilon: Length) -> Self {
+        let mut contours = Vec::new();
+        let mut open_paths = Vec::new();
+        for graphic in graphics {
+            if graphic.shape.is_closed() {
+                contours.extend(graphic.shape.paths(tessellation).into_iter().filter(|path| path.len() >= 3));
+            } else {
+                open_paths.extend(graphic.shape.paths(tessellation).into_iter().filter(|path| path.len() >= 2));
+            }
+        }
+
+        let mut issues = Vec::new();
+        for chain in chain_paths(open_paths, chaining_epsilon) {
+            let (start, end) = (chain[0], chain[chain.len() - 1]);
+            if chain.len() >= 3 && start.distance(&end) <= chaining_epsilon {
+                let mut ring = chain;
+                ring.pop();
+                contours.push(ring);
+            } else {
+                warn!("Edge.Cuts contour from {:.3} to {:.3} is not closed", start, end);
+                issues.push(OutlineIssue::OpenContour { start, end });
+            }
+        }
+
+        issues.extend(
+            find_crossings(&contours)
+                .into_iter()
+                .map(|location| OutlineIssue::SelfIntersection { location }),
+        );
+        let shape = nest_contours(&contours);
+        debug!("Assembled {} contours with {} issues", contours.len(), issues.len());
+
+        Self { contours, shape, issues }
+    }
+
+    /// Whether the outline is closed and free of crossings
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty() && !self.shape.is_empty()
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.shape.bounding_box()
+    }
+
+    /// Board width and height from the outline's bounding box
+    pub fn size(&self) -> Option<(Length, Length)> {
+        self.bounding_box().map(|bounds| (bounds.width(), bounds.height()))
+    }
+
+    /// Board area in square millimeters, excluding cutouts
+    pub fn area_mm2(&self) -> f64 {
+        self.shape.area_mm2()
+    }
+
+    /// Whether a point is on the board
+    pub fn contains(&self, point: Point) -> bool {
+        self.shape.contains(point)
+    }
+
+    fn edges(&self) -> impl Iterator<Item = LineString<f64>> + '_ {
+        self.contours.iter().map(|ring| ring_line(ring))
+    }
+
+    /// Distance from a point to the nearest board edge, including cutout edges
+    pub fn edge_distance(&self, point: Point) -> Option<Length> {
+        let point = geo::Point::new(point.x.nm() as f64, point.y.nm() as f64);
+        self.edges()
+            .map(|edge| Euclidean.distance(&edge, &point))
+            .min_by(f64::total_cmp)
+            .map(|distance| Length::from_nm(distance.round() as i64))
+    }
+
+    /// Minimum distance from a shape to the nearest board edge; zero if it touches or crosses one
+    pub fn edge_clearance(&self, shape: &Shape) -> Option<Length> {
+        self.edges()
+            .map(|edge| Euclidean.distance(&edge, &shape.polygons))
+            .min_by(f64::total_cmp)
+            .map(|distance| Length::from_nm(distance.round() as i64))
+    }
+}
+
+fn ring_line(ring: &[Point]) -> LineString<f64> {
+    let mut line: LineString<f64> = ring.iter().map(|p| (p.x.nm() as f64, p.y.nm() as f64)).collect();
+    line.close();
+    line
+}
+
+/// Join open paths end to end; each result starts and ends at a loose end or closes on itself
+fn chain_paths(mut paths: Vec<Vec<Point>>, epsilon: Length) -> Vec<Vec<Point>> {
+    let mut chains = Vec::new();
+    while let Some(mut chain) = paths.pop() {
+        loop {
+            let end = chain[chain.len() - 1];
+            if chain.len() > 2 && chain[0].distance(&end) <= epsilon {
+                break;
+            }
+            let next = paths.iter().position(|path| {
+                path[0].distance(&end) <= epsilon || path[path.len() - 1].distance(&end) <= epsilon
+            });
+            let Some(index) = next else {
+                break;
+            };
+            let mut path = paths.swap_remove(index);
+            if path[0].distance(&end) > epsilon {
+                path.reverse();
+            }
+            chain.extend_from_slice(&path[1..]);
+        }
+        // Grow backwards from the start too, in case we began mid-chain
+        loop {
+            let start = chain[0];
+            if chain.len() > 2 && start.distance(&chain[chain.len() - 1]) <= epsilon {
+                break;
+            }
+            let previous = paths.iter().position(|path| {
+                path[0].distance(&start) <= epsilon || path[path.len() - 1].distance(&start) <= epsilon
+            });
+            let Some(index) = previous else {
+                break;
+            };
+            let mut path = paths.swap_remove(index);
+            if path[path.len() - 1].distance(&start) > epsilon {
+                path.reverse();
+            }
+            path.pop();
+            path.extend_from_slice(&chain);
+            chain = path;
+        }
+        chains.push(chain);
+    }
+    chains
+}
+
+/// Points where edges of the contours cross, ignoring edges that share a vertex
+fn find_crossings(contours: &[Vec<Point>]) -> Vec<Point> {
+    let edges: Vec<(usize, usize, Point, Point)> = contours
+        .iter()
+        .enumerate()
+        .flat_map(|(c, ring)| {
+            (0..ring.len()).map(move |i| (c, i, ring[i], ring[(i + 1) % ring.len()]))
+        })
+        .collect();
+
+    let mut crossings = Vec::new();
+    for (n, &(c1, i1, a1, b1)) in edges.iter().enumerate() {
+        for &(c2, i2, a2, b2) in &edges[n + 1..] {
+            if c1 == c2 {
+                let len = contours[c1].len();
+                if (i1 + 1) % len == i2 || (i2 + 1) % len == i1 {
+                    continue;
+                }
+            }
+            if let Some(point) = segment_crossing(a1, b1, a2, b2) {
+                crossings.push(point);
+            }
+        }
+    }
+    crossings.dedup();
+    crossings
+}
+
+/// Intersection point of two segments, if they cross or touch
+fn segment_crossing(a1: Point, b1: Point, a2: Point, b2: Point) -> Option<Point> {
+    let f = |p: Point| (p.x.nm() as f64, p.y.nm() as f64);
+    let ((x1, y1), (x2, y2), (x3, y3), (x4, y4)) = (f(a1), f(b1), f(a2), f(b2));
+    // Reject quickly on bounding boxes
+    if x1.max(x2) < x3.min(x4) || x3.max(x4) < x1.min(x2) || y1.max(y2) < y3.min(y4) || y3.max(y4) < y1.min(y2) {
+        return None;
+    }
+    let denominator = (x1 - x2) * (y3 - y4) - (y1 - y2) * (x3 - x4);
+    if denominator == 0.0 {
+        return None;
+    }
+    let t = ((x1 - x3) * (y3 - y4) - (y1 - y3) * (x3 - x4)) / denominator;
+    let u = ((x1 - x3) * (y1 - y2) - (y1 - y3) * (x1 - x2)) / denominator;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then(|| {
+        Point::from_nm((x1 + t * (x2 - x1)).round() as i64, (y1 + t * (y2 - y1)).round() as i64)
+    })
+}
+
+/// Build the board area: contours nested an even number of levels deep are outer boundaries,
+/// odd levels are cutouts of the contour directly around them
+fn nest_contours(contours: &[Vec<Point>]) -> Shape {
+    let rings: Vec<Polygon<f64>> = contours.iter().map(|ring| Polygon::new(ring_line(ring), vec![])).collect();
+    let parents: Vec<Vec<usize>> = contours
+        .iter()
+        .enumerate()
+        .map(|(i, ring)| {
+            let probe = geo::Point::new(ring[0].x.nm() as f64, ring[0].y.nm() as f64);
+            (0..rings.len()).filter(|&j| j != i && rings[j].contains(&probe)).collect()
+        })
+        .collect();
+
+    let mut polygons = Vec::new();
+    for (i, ring) in rings.iter().enumerate() {
+        if !parents[i].len().is_multiple_of(2) {
+            continue;
+        }
+        let holes = (0..rings.len())
+            .filter(|&j| parents[j].len() == parents[i].len() + 1 && parents[j].contains(&i))
+            .map(|j| rings[j].exterior().clone())
+            .collect();
+        polygons.push(Polygon::new(ring.exterior().clone(), holes));
+    }
+    Shape::new(MultiPolygon::new(polygons))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::GraphicShape;
+
+    fn line(x1: f64, y1: f64, x2: f64, y2: f64) -> Graphic {
+        Graphic {
+            shape: GraphicShape::Segment { start: Point::from_mm(x1, y1), end: Point::from_mm(x2, y2) },
+            stroke_width: Length::from_mm(0.1),
+            filled: false,
+        }
+    }
+
+    #[test]
+    fn chains_segments_and_nests_cutouts() {
+        // Segments out of order and direction, plus a 2 mm square cutout
+        let graphics = vec![
+            line(0.0, 0.0, 50.0, 0.0),
+            line(0.0, 30.0, 50.0, 30.0),
+            line(50.0, 30.0, 50.0, 0.0),
+            line(0.0, 0.0, 0.0, 30.0),
+            Graphic {
+                shape: GraphicShape::Rectangle { top_left: Point::from_mm(10.0, 10.0), bottom_right: Point::from_mm(12.0, 12.0) },
+                stroke_width: Length::from_mm(0.1),
+                filled: false,
+            },
+        ];
+        let outline = BoardOutline::from_graphics(&graphics, &Tessellation::default(), DEFAULT_CHAINING_EPSILON);
+        assert!(outline.is_valid(), "{:?}", outline.issues);
+        assert_eq!(outline.size(), Some((Length::from_mm(50.0), Length::from_mm(30.0))));
+        assert!((outline.area_mm2() - 1496.0).abs() < 1e-6);
+        assert!(!outline.contains(Point::from_mm(11.0, 11.0)));
+        assert_eq!(outline.edge_distance(Point::from_mm(25.0, 5.0)), Some(Length::from_mm(5.0)));
+        assert_eq!(outline.edge_distance(Point::from_mm(13.0, 11.0)), Some(Length::from_mm(1.0)));
+    }
+
+    #[test]
+    fn reports_open_contours_and_crossings() {
+        let open = vec![line(0.0, 0.0, 10.0, 0.0), line(10.0, 0.0, 10.0, 10.0)];
+        let outline = BoardOutline::from_graphics(&open, &Tessellation::default(), DEFAULT_CHAINING_EPSILON);
+        assert!(matches!(outline.issues.as_slice(), [OutlineIssue::OpenContour { .. }]));
+
+        let bowtie = vec![
+            line(0.0, 0.0, 10.0, 10.0),
+            line(10.0, 10.0, 10.0, 0.0),
+            line(10.0, 0.0, 0.0, 10.0),
+            line(0.0, 10.0, 0.0, 0.0),
+        ];
+        let outline = BoardOutline::from_graphics(&bowtie, &Tessellation::default(), DEFAULT_CHAINING_EPSILON);
+        assert_eq!(outline.issues, vec![OutlineIssue::SelfIntersection { location: Point::from_mm(5.0, 5.0) }]);
+    }
+}
@@ -0,0 +1,415 @@
+//! Placement checks for assembly
+//!
+//! Courtyards are assembled from each footprint's courtyard layer graphics the same way the board
+//! outline is assembled from Edge.Cuts. The rules here spawn `Violation` entities like the rules
+//! in `drc` and are part of `drc_schedule`:
+//! - courtyards overlapping on the same side of the board
+//! - parts closer to the board edge than `PlacementRules::edge_clearance`
+//! - parts inside the keepout of a mounting hole (screw head, washer or nut)
+//! - tall parts in the mating area around a connector, where they block the plug
+//!
+//! Parts without a courtyard are checked using their pad shapes instead. Candidates for each
+//! check are found through an R-tree over the part areas' bounding boxes on each side.
+
+use bevy_ecs::prelude::*;
+use regex::Regex;
+use rstar::RTree;
+use std::sync::OnceLock;
+use tracing::debug;
+use crate::components::{
+    BoundingBox, ComponentFlags, ComponentId, ComponentInfo, Connector, Courtyard, Fields, Height, Layer, MountingHole,
+    PadKind, Pads, Position,
+};
+use crate::drc::{aabb, bounds_tree, IndexedBounds, Severity, Violation};
+use crate::geometry::{Graphic, Shape, Tessellation};
+use crate::layers::{PcbLayer, Side};
+use crate::outline::{BoardOutline, DEFAULT_CHAINING_EPSILON};
+use crate::units::Length;
+
+pub const COURTYARD_OVERLAP: &str = "courtyard_overlap";
+pub const PART_EDGE_CLEARANCE: &str = "part_edge_clearance";
+pub const MOUNTING_HOLE_KEEPOUT: &str = "mounting_hole_keepout";
+pub const CONNECTOR_MATING_AREA: &str = "connector_mating_area";
+
+/// Limits used by the placement rules
+#[derive(Resource, Debug, Clone)]
+pub struct PlacementRules {
+    /// Minimum distance from a part's courtyard to the board edge
+    pub edge_clearance: Length,
+    /// Extra clearance around a mounting hole's keepout
+    pub mounting_hole_clearance: Length,
+    /// How far around a connector's courtyard its mating plug reaches
+    pub connector_mating_margin: Length,
+    /// Parts taller than this may not sit in a connector's mating area
+    pub tall_part_height: Length,
+}
+
+impl Default for PlacementRules {
+    fn default() -> Self {
+        Self {
+            edge_clearance: Length::from_mm(0.5),
+            mounting_hole_clearance: Length::ZERO,
+            connector_mating_margin: Length::from_mm(2.0),
+            tall_part_height: Length::from_mm(3.0),
+        }
+    }
+}
+
+/// Assemble a footprint's courtyards from its courtyard layer graphics, given in footprint
+/// coordinates
+pub fn courtyard_from_graphics(graphics: &[(PcbLayer, Graphic)], position: &Position, tessellation: &Tessellation) -> Courtyard {
+    let side_shape = |layer: PcbLayer| {
+        let placed: Vec<Graphic> = graphics
+            .iter()
+            .filter(|(graphic_layer, _)| *graphic_layer == layer)
+            .map(|(_, graphic)| graphic.placed(position))
+            .collect();
+        let outline = BoardOutline::from_graphics(&placed, tessellation, DEFAULT_CHAINING_EPSILON);
+        Some(outline.shape).filter(|shape| !shape.is_empty())
+    };
+    Courtyard { front: side_shape(PcbLayer::FCrtYd), back: side_shape(PcbLayer::BCrtYd) }
+}
+
+fn height_field_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^\s*(?P<number>\d+(?:[.,]\d+)?)\s*(?P<unit>mm|mils?|in)?\s*$").expect("valid height pattern")
+    })
+}
+
+fn height_name_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"_H(?P<number>\d+(?:\.\d+)?)mm").expect("valid footprint height pattern"))
+}
+
+/// A part's height, from a "Height" field (millimeters unless another unit is given) or the
+/// `_H<height>mm` part of KiCad library footprint names
+pub fn part_height(fields: &Fields, footprint_name: &str) -> Option<Length> {
+    if let Some(captures) = fields.get("Height").and_then(|value| height_field_regex().captures(value)) {
+        let number: f64 = captures["number"].replace(',', ".").parse().ok()?;
+        return Some(match captures.name("unit").map(|unit| unit.as_str()) {
+            Some("mil" | "mils") => Length::from_mils(number),
+            Some("in") => Length::from_inches(number),
+            _ => Length::from_mm(number),
+        });
+    }
+    let captures = height_name_regex().captures(footprint_name)?;
+    captures["number"].parse().ok().map(Length::from_mm)
+}
+
+// ===== Rules =====
+
+type PartQuery<'w, 's> = Query<'w, 's, (
+    Entity,
+    &'static ComponentId,
+    &'static ComponentInfo,
+    &'static Layer,
+    Option<&'static Courtyard>,
+    Option<&'static Shape>,
+    Option<&'static Height>,
+    Has<MountingHole>,
+)>;
+
+/// A part's footprint area on one side: its courtyard, or its pads when it has none
+struct PartArea<'a> {
+    entity: Entity,
+    id: &'a str,
+    reference: &'a str,
+    shape: &'a Shape,
+    bounds: BoundingBox,
+    height: Option<Length>,
+    mounting_hole: bool,
+}
+
+/// The part areas on one side and an R-tree over their bounding boxes
+struct SideAreas<'a> {
+    areas: Vec<PartArea<'a>>,
+    tree: RTree<IndexedBounds>,
+}
+
+impl<'a> SideAreas<'a> {
+    fn new(areas: Vec<PartArea<'a>>) -> Self {
+        let tree = bounds_tree(areas.iter().map(|area| &area.bounds));
+        Self { areas, tree }
+    }
+
+    /// Indices of the areas whose bounding box touches `bounds`, in ascending order
+    fn near(&self, bounds: &BoundingBox) -> Vec<usize> {
+        let mut found: Vec<usize> = self.tree.locate_in_envelope_intersecting(&aabb(bounds)).map(|item| item.data).collect();
+        found.sort_unstable();
+        found
+    }
+}
+
+fn part_areas<'a>(parts: &'a PartQuery, side: Side) -> Vec<PartArea<'a>> {
+    parts
+        .iter()
+        .filter_map(|(entity, id, info, layer, courtyard, shape, height, mounting_hole)| {
+            let courtyard = courtyard.and_then(|courtyard| courtyard.on(side));
+            let shape = courtyard.or_else(|| shape.filter(|_| layer.side() == Some(side)))?;
+            Some(PartArea {
+                entity,
+                id: &id.uuid,
+                reference: &info.reference,
+                shape,
+                bounds: shape.bounding_box()?,
+                height: height.map(|height| height.height),
+                mounting_hole,
+            })
+        })
+        .collect()
+}
+
+fn part_violation(rule: &str, severity: Severity, message: String, location: &Shape, parts: &[&PartArea]) -> Violation {
+    Violation {
+        rule: rule.to_string(),
+        severity,
+        message,
+        location: location.bounding_box().map(|bounds| bounds.center()).unwrap_or_default(),
+        entities: parts.iter().map(|part| part.entity).collect(),
+        items: parts.iter().map(|part| part.id.to_string()).collect(),
+    }
+}
+
+/// Courtyards on the same side may touch but not overlap. Footprints marked as exempt from
+/// courtyard requirements are skipped.
+pub fn courtyard_overlap_rule(mut commands: Commands, parts: PartQuery, flags: Query<&ComponentFlags>) {
+    let exempt = |entity: Entity| flags.get(entity).is_ok_and(|flags| flags.exempt_from_courtyard);
+    for side in [Side::Front, Side::Back] {
+        let side_areas = SideAreas::new(part_areas(&parts, side).into_iter().filter(|part| !exempt(part.entity)).collect());
+        let areas = &side_areas.areas;
+        for (i, a) in areas.iter().enumerate() {
+            for j in side_areas.near(&a.bounds).into_iter().filter(|j| *j > i) {
+                let b = &areas[j];
+                let overlap = a.shape.intersection(b.shape);
+                if overlap.area_mm2() > 1e-6 {
+                    let message = format!("{} and {} courtyards overlap by {:.3} mm² on the {:?} side", a.reference, b.reference, overlap.area_mm2(), side);
+                    commands.spawn(part_violation(COURTYARD_OVERLAP, Severity::Error, message, &overlap, &[a, b]));
+                }
+            }
+        }
+    }
+}
+
+/// Parts must stay `edge_clearance` inside the board outline. Mounting holes and card edge
+/// connectors are expected at the edge and skipped.
+pub fn part_edge_clearance_rule(
+    mut commands: Commands,
+    rules: Res<PlacementRules>,
+    outline: Option<Res<BoardOutline>>,
+    parts: PartQuery,
+    pads: Query<&Pads>,
+) {
+    let Some(outline) = outline else { return };
+    let edge_connector = |entity: Entity| {
+        pads.get(entity).is_ok_and(|pads| pads.pads.iter().any(|pad| pad.kind == PadKind::EdgeConnector))
+    };
+    for side in [Side::Front, Side::Back] {
+        for part in part_areas(&parts, side) {
+            if part.mounting_hole || edge_connector(part.entity) {
+                continue;
+            }
+            let Some(clearance) = outline.edge_clearance(part.shape) else { continue };
+            let outside = part.shape.difference(&outline.shape).area_mm2() > 1e-6;
+            if clearance < rules.edge_clearance || outside {
+                let message = if outside {
+                    format!("{} extends past the board edge", part.reference)
+                } else {
+                    format!("{} is {} from the board edge; {} required", part.reference, clearance, rules.edge_clearance)
+                };
+                commands.spawn(part_violation(PART_EDGE_CLEARANCE, Severity::Error, message, part.shape, &[&part]));
+            }
+        }
+    }
+}
+
+/// Parts on either side may not enter a mounting hole's keepout, where the screw head, washer
+/// or nut sits
+pub fn mounting_hole_keepout_rule(
+    mut commands: Commands,
+    rules: Res<PlacementRules>,
+    tessellation: Res<Tessellation>,
+    holes: Query<(Entity, &ComponentId, &ComponentInfo, &Position, &MountingHole)>,
+    parts: PartQuery,
+) {
+    let keepouts: Vec<(Entity, &ComponentId, &ComponentInfo, Length, Shape)> = holes
+        .iter()
+        .map(|(entity, id, info, position, hole)| {
+            let diameter = hole.keepout_diameter + rules.mounting_hole_clearance * 2;
+            (entity, id, info, diameter, Shape::stroke(&[position.point()], false, diameter, &tessellation))
+        })
+        .collect();
+    if keepouts.is_empty() {
+        return;
+    }
+    for side in [Side::Front, Side::Back] {
+        let side_areas = SideAreas::new(part_areas(&parts, side));
+        for (hole_entity, hole_id, hole_info, diameter, keepout) in &keepouts {
+            let Some(keepout_bounds) = keepout.bounding_box() else { continue };
+            let hole_area = PartArea {
+                entity: *hole_entity,
+                id: &hole_id.uuid,
+                reference: &hole_info.reference,
+                shape: keepout,
+                bounds: keepout_bounds,
+                height: None,
+                mounting_hole: true,
+            };
+            for part in side_areas.near(&keepout_bounds).into_iter().map(|i| &side_areas.areas[i]) {
+                if part.mounting_hole || !part.shape.intersects(keepout) {
+                    continue;
+                }
+                let overlap = part.shape.intersection(keepout);
+                if overlap.area_mm2() > 1e-6 {
+                    let message = format!("{} is inside the {} keepout of {} on the {:?} side", part.reference, diameter, hole_info.reference, side);
+                    commands.spawn(part_violation(MOUNTING_HOLE_KEEPOUT, Severity::Error, message, &overlap, &[&hole_area, part]));
+                }
+            }
+        }
+    }
+}
+
+/// Parts taller than `tall_part_height` may not sit within `connector_mating_margin` of a
+/// connector on the same side, where they would block the mating plug
+pub fn connector_mating_area_rule(
+    mut commands: Commands,
+    rules: Res<PlacementRules>,
+    tessellation: Res<Tessellation>,
+    parts: PartQuery,
+    connectors: Query<(), With<Connector>>,
+) {
+    for side in [Side::Front, Side::Back] {
+        let side_areas = SideAreas::new(part_areas(&parts, side));
+        for connector in side_areas.areas.iter().filter(|part| connectors.contains(part.entity)) {
+            let mating_area = connector.shape.offset(rules.connector_mating_margin, &tessellation);
+            for part in side_areas.near(&connector.bounds.expanded(rules.connector_mating_margin)).into_iter().map(|i| &side_areas.areas[i]) {
+                let Some(height) = part.height.filter(|height| *height > rules.tall_part_height) else { continue };
+                if connectors.contains(part.entity) || !part.shape.intersects(&mating_area) {
+                    continue;
+                }
+                let overlap = part.shape.intersection(&mating_area);
+                let message = format!("{} is {} tall and within {} of connector {}", part.reference, height, rules.connector_mating_margin, connector.reference);
+                commands.spawn(part_violation(CONNECTOR_MATING_AREA, Severity::Warning, message, &overlap, &[connector, part]));
+            }
+        }
+    }
+    debug!("Checked connector mating areas");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::part;
+    use crate::geometry::GraphicShape;
+    use crate::units::Point;
+    use std::collections::BTreeMap;
+
+    fn rectangle(width_mm: f64, height_mm: f64) -> (PcbLayer, Graphic) {
+        let shape = GraphicShape::Rectangle {
+            top_left: Point::from_mm(-width_mm / 2.0, -height_mm / 2.0),
+            bottom_right: Point::from_mm(width_mm / 2.0, height_mm / 2.0),
+        };
+        (PcbLayer::FCrtYd, Graphic { shape, stroke_width: Length::from_mm(0.05), filled: false })
+    }
+
+    /// A part rotated 90° with a rectangular courtyard, so a 2 x 6 mm rectangle spans 6 mm along X
+    fn spawn_part(world: &mut World, reference: &str, x_mm: f64, size_mm: (f64, f64), height_mm: Option<f64>) -> Entity {
+        let builder = part(reference).at(Point::from_mm(x_mm, 0.0)).rotated(90.0);
+        let courtyard = courtyard_from_graphics(&[rectangle(size_mm.0, size_mm.1)], &builder.position(), &Tessellation::default());
+        let entity = builder.spawn(world);
+        world.entity_mut(entity).insert(courtyard);
+        if let Some(height) = height_mm {
+            world.entity_mut(entity).insert(Height { height: Length::from_mm(height) });
+        }
+        entity
+    }
+
+    fn violations<M>(world: &mut World, rule: impl IntoSystemConfigs<M>) -> Vec<Violation> {
+        world.init_resource::<PlacementRules>();
+        world.init_resource::<Tessellation>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(rule);
+        schedule.run(world);
+        let mut query = world.query::<&Violation>();
+        query.iter(world).cloned().collect()
+    }
+
+    #[test]
+    fn heights_from_fields_and_footprint_names() {
+        let fields = |value: &str| Fields { fields: BTreeMap::from([("Height".to_string(), value.to_string())]) };
+        assert_eq!(part_height(&fields("4.5"), ""), Some(Length::from_mm(4.5)));
+        assert_eq!(part_height(&fields("100 mil"), ""), Some(Length::from_mils(100.0)));
+        let name = "Capacitor_THT:CP_Radial_D8.0mm_H11.5mm_P3.50mm";
+        assert_eq!(part_height(&Fields::default(), name), Some(Length::from_mm(11.5)));
+        assert_eq!(part_height(&Fields::default(), "Resistor_SMD:R_0603_1608Metric"), None);
+    }
+
+    #[test]
+    fn reports_overlapping_courtyards() {
+        let mut world = World::new();
+        let u1 = spawn_part(&mut world, "U1", 0.0, (2.0, 6.0), None);
+        let u2 = spawn_part(&mut world, "U2", 5.0, (2.0, 6.0), None);
+        spawn_part(&mut world, "U3", 12.0, (2.0, 6.0), None);
+
+        let found = violations(&mut world, courtyard_overlap_rule);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].rule.as_str(), found[0].items.clone()), (COURTYARD_OVERLAP, vec!["u1".to_string(), "u2".to_string()]));
+        assert_eq!(found[0].entities, vec![u1, u2]);
+    }
+
+    #[test]
+    fn reports_parts_within_the_edge_clearance() {
+        let mut world = World::new();
+        let edge = Graphic {
+            shape: GraphicShape::Rectangle { top_left: Point::from_mm(0.0, -20.0), bottom_right: Point::from_mm(50.0, 20.0) },
+            stroke_width: Length::from_mm(0.1),
+            filled: false,
+        };
+        world.insert_resource(BoardOutline::from_graphics(&[edge], &Tessellation::default(), DEFAULT_CHAINING_EPSILON));
+        // 0.2 mm from the right edge, against the default 0.5 mm
+        let near = spawn_part(&mut world, "U1", 48.8, (2.0, 2.0), None);
+        spawn_part(&mut world, "U2", 25.0, (2.0, 2.0), None);
+
+        let found = violations(&mut world, part_edge_clearance_rule);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].rule.as_str(), found[0].entities.clone()), (PART_EDGE_CLEARANCE, vec![near]));
+    }
+
+    #[test]
+    fn reports_parts_in_mounting_hole_keepouts() {
+        let mut world = World::new();
+        let hole = part("H1").at(Point::from_mm(30.0, 0.0)).spawn(&mut world);
+        world.entity_mut(hole).insert(MountingHole {
+            diameter: Length::from_mm(3.2),
+            plated: false,
+            pad_diameter: None,
+            annulus: None,
+            screw_size: Some("M3".to_string()),
+            fit: None,
+            keepout_diameter: Length::from_mm(6.0),
+        });
+        // Reaches to 1 mm from the hole center, inside the 3 mm keepout radius
+        let inside = spawn_part(&mut world, "U1", 32.0, (2.0, 2.0), None);
+        spawn_part(&mut world, "U2", 40.0, (2.0, 2.0), None);
+
+        let found = violations(&mut world, mounting_hole_keepout_rule);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].rule, MOUNTING_HOLE_KEEPOUT);
+        assert_eq!(found[0].entities, vec![hole, inside]);
+        assert_eq!(found[0].items, vec!["h1".to_string(), "u1".to_string()]);
+    }
+
+    #[test]
+    fn reports_tall_parts_by_connectors() {
+        let mut world = World::new();
+        let j1 = spawn_part(&mut world, "J1", 20.0, (4.0, 4.0), None);
+        world.entity_mut(j1).insert(Connector);
+        spawn_part(&mut world, "C1", 24.0, (2.0, 2.0), Some(10.0));
+        // Low enough to sit next to the connector
+        spawn_part(&mut world, "C2", 16.0, (2.0, 2.0), Some(1.0));
+
+        let found = violations(&mut world, connector_mating_area_rule);
+        let found: Vec<(&str, Vec<String>)> = found.iter().map(|v| (v.rule.as_str(), v.items.clone())).collect();
+        assert_eq!(found, vec![(CONNECTOR_MATING_AREA, vec!["j1".to_string(), "c1".to_string()])]);
+    }
+}
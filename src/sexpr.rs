@@ -0,0 +1,230 @@
+//! S-expression parsing for KiCad files
+//!
+//! KiCad's board, footprint and schematic files are S-expressions: nested lists of symbols,
+//! numbers and double-quoted strings, where every list starts with a symbol naming it, e.g.
+//! `(via (at 10 20) (size 0.6) (net 3))`. Atoms are kept as text; numbers are converted as they
+//! are read.
+
+use eyre::{eyre, Result};
+use std::fmt;
+
+/// A parsed S-expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum SExpr {
+    /// A bare symbol or number, e.g. `smd` or `1.27`
+    Symbol(String),
+    /// A double-quoted string, with escapes resolved
+    String(String),
+    List(Vec<SExpr>),
+}
+
+impl SExpr {
+    /// Parse text holding a single expression
+    pub fn parse(text: &str) -> Result<SExpr> {
+        let mut parser = Parser { text, position: 0 };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        if parser.position < text.len() {
+            return Err(parser.error("trailing text after expression"));
+        }
+        Ok(expr)
+    }
+
+    /// The symbol or string text of an atom
+    pub fn as_atom(&self) -> Option<&str> {
+        match self {
+            SExpr::Symbol(text) | SExpr::String(text) => Some(text),
+            SExpr::List(_) => None,
+        }
+    }
+
+    /// The symbol a list starts with
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            SExpr::List(items) => match items.first() {
+                Some(SExpr::Symbol(name)) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether this is a list named `name`
+    pub fn is(&self, name: &str) -> bool {
+        self.name() == Some(name)
+    }
+
+    /// The elements of a list after its name; empty for atoms
+    pub fn args(&self) -> &[SExpr] {
+        match self {
+            SExpr::List(items) if self.name().is_some() => &items[1..],
+            SExpr::List(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// The first child list named `name`
+    pub fn child(&self, name: &str) -> Option<&SExpr> {
+        self.args().iter().find(|arg| arg.is(name))
+    }
+
+    /// Every child list named `name`
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a SExpr> {
+        self.args().iter().filter(move |arg| arg.is(name))
+    }
+
+    /// The argument at `index` as text, if it is an atom
+    pub fn atom(&self, index: usize) -> Option<&str> {
+        self.args().get(index).and_then(SExpr::as_atom)
+    }
+
+    /// The argument at `index` as a number
+    pub fn number(&self, index: usize) -> Option<f64> {
+        self.atom(index).and_then(|text| text.parse().ok())
+    }
+
+    /// The first argument of the child named `name`, e.g. `"F.Cu"` for `(layer "F.Cu")`
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.child(name).and_then(|child| child.atom(0))
+    }
+
+    /// Whether a bare symbol is among the arguments, e.g. `locked` in `(footprint "x" locked ...)`
+    pub fn has_flag(&self, flag: &str) -> bool {
+        self.args().iter().any(|arg| matches!(arg, SExpr::Symbol(symbol) if symbol == flag))
+    }
+}
+
+/// Writes the expression on one line, quoting strings, in a form KiCad reads back
+impl fmt::Display for SExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SExpr::Symbol(symbol) => f.write_str(symbol),
+            SExpr::String(text) => {
+                f.write_str("\"")?;
+                for c in text.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            SExpr::List(items) => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> eyre::Report {
+        let line = self.text[..self.position].matches('\n').count() + 1;
+        eyre!("S-expression syntax error on line {}: {}", line, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn expr(&mut self) -> Result<SExpr> {
+        // Lists are parsed with an explicit stack, as board files nest deeply
+        let mut stack: Vec<Vec<SExpr>> = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let atom = match self.peek() {
+                None => return Err(self.error("unexpected end of input")),
+                Some('(') => {
+                    self.position += 1;
+                    stack.push(Vec::new());
+                    continue;
+                }
+                Some(')') => {
+                    let Some(items) = stack.pop() else {
+                        return Err(self.error("unbalanced ')'"));
+                    };
+                    self.position += 1;
+                    SExpr::List(items)
+                }
+                Some('"') => self.string()?,
+                Some(_) => self.symbol(),
+            };
+            match stack.last_mut() {
+                Some(list) => list.push(atom),
+                None => return Ok(atom),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<SExpr> {
+        let mut text = String::new();
+        let mut chars = self.text[self.position + 1..].char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position += offset + 2;
+                    return Ok(SExpr::String(text));
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => text.push('\n'),
+                    Some((_, 't')) => text.push('\t'),
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                c => text.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn symbol(&mut self) -> SExpr {
+        let rest = &self.text[self.position..];
+        let end = rest.find(|c: char| c.is_whitespace() || c == '(' || c == ')' || c == '"').unwrap_or(rest.len());
+        self.position += end;
+        SExpr::Symbol(rest[..end].to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_lists_and_strings() {
+        let expr = SExpr::parse(r#"
+            (footprint "R:R_0603" locked
+              (at 10.5 -3 90)
+              (property "Value" "10 \"k\"\n"))
+        "#).unwrap();
+        assert!(expr.is("footprint"));
+        assert_eq!(expr.atom(0), Some("R:R_0603"));
+        assert!(expr.has_flag("locked"));
+        assert_eq!(expr.child("at").and_then(|at| at.number(2)), Some(90.0));
+        assert_eq!(expr.child("property").and_then(|p| p.atom(1)), Some("10 \"k\"\n"));
+        assert_eq!(expr.children("at").count(), 1);
+
+        assert_eq!(SExpr::parse(&expr.to_string()).unwrap(), expr);
+
+        assert!(SExpr::parse("(a (b)").is_err());
+        assert!(SExpr::parse("(a)) ").is_err());
+        assert!(SExpr::parse("(a \"b)").is_err());
+    }
+}
@@ -0,0 +1,446 @@
+//! Saving and loading worlds
+//!
+//! A snapshot holds every entity that has at least one registered component, together with the
+//! `BoardOutline`, `LayerNames` and `AuxOrigin` resources, so a board can be loaded from KiCad once and
+//! analyzed many times offline, and analysis results such as `Violation`s can be cached between
+//! runs. Snapshots are written as compact binary (bincode behind a magic header) or as JSON for
+//! debugging.
+//!
+//! Components are saved by name through the `SnapshotRegistry` resource, which knows every
+//! built-in component. Applications add their own with `SnapshotRegistry::register`; components
+//! that refer to other entities also implement `MapEntities` and use `register_mapped`, since
+//! entities get new ids when a snapshot is loaded. Components with names the loading registry
+//! does not know are skipped with a warning.
+
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use bevy_ecs::world::{EntityRef, EntityWorldMut};
+use eyre::{eyre, Result, WrapErr};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::{info, instrument, warn};
+use crate::classify::Classification;
+use crate::components::*;
+use crate::cpl::AuxOrigin;
+use crate::decoupling::Decoupling;
+use crate::drc::Violation;
+use crate::geometry::Shape;
+use crate::layers::{LayerNames, PcbLayer};
+use crate::netlist::{SchematicJunction, SchematicLabel, SchematicPin, SchematicSheetPin, SchematicWire, SheetPath};
+use crate::outline::BoardOutline;
+use crate::variant::EffectivePart;
+
+/// Version written into snapshots; loading rejects other versions
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+const MAGIC: &[u8; 8] = b"KECSSNAP";
+
+/// How a snapshot is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Binary,
+    Json,
+}
+
+impl SnapshotFormat {
+    /// JSON for paths ending in `.json`, binary otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => SnapshotFormat::Json,
+            _ => SnapshotFormat::Binary,
+        }
+    }
+}
+
+// ===== Encoding =====
+
+trait Codec {
+    type Payload: Serialize + DeserializeOwned;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Self::Payload>;
+    fn decode<T: DeserializeOwned>(payload: &Self::Payload) -> Result<T>;
+    fn saver(registration: &Registration) -> SaveFn<Self::Payload>;
+    fn loader(registration: &Registration) -> LoadFn<Self::Payload>;
+}
+
+struct Binary;
+struct Json;
+
+impl Codec for Binary {
+    type Payload = Vec<u8>;
+
+    fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(payload: &Vec<u8>) -> Result<T> {
+        Ok(bincode::deserialize(payload)?)
+    }
+
+    fn saver(registration: &Registration) -> SaveFn<Vec<u8>> {
+        registration.save_binary
+    }
+
+    fn loader(registration: &Registration) -> LoadFn<Vec<u8>> {
+        registration.load_binary
+    }
+}
+
+impl Codec for Json {
+    type Payload = serde_json::Value;
+
+    fn encode<T: Serialize>(value: &T) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(value)?)
+    }
+
+    fn decode<T: DeserializeOwned>(payload: &serde_json::Value) -> Result<T> {
+        Ok(T::deserialize(payload)?)
+    }
+
+    fn saver(registration: &Registration) -> SaveFn<serde_json::Value> {
+        registration.save_json
+    }
+
+    fn loader(registration: &Registration) -> LoadFn<serde_json::Value> {
+        registration.load_json
+    }
+}
+
+type SaveFn<P> = fn(&EntityRef) -> Option<Result<P>>;
+type LoadFn<P> = fn(&mut EntityWorldMut, &P) -> Result<()>;
+type MapFn = fn(&mut EntityWorldMut, &mut EntityRemap);
+
+fn save<T: Component + Serialize, C: Codec>(entity: &EntityRef) -> Option<Result<C::Payload>> {
+    entity.get::<T>().map(C::encode)
+}
+
+fn load<T: Component + DeserializeOwned, C: Codec>(entity: &mut EntityWorldMut, payload: &C::Payload) -> Result<()> {
+    entity.insert(C::decode::<T>(payload)?);
+    Ok(())
+}
+
+fn map<T: Component + MapEntities>(entity: &mut EntityWorldMut, remap: &mut EntityRemap) {
+    if let Some(mut component) = entity.get_mut::<T>() {
+        component.map_entities(remap);
+    }
+}
+
+// ===== Registry =====
+
+struct Registration {
+    name: String,
+    save_binary: SaveFn<Vec<u8>>,
+    save_json: SaveFn<serde_json::Value>,
+    load_binary: LoadFn<Vec<u8>>,
+    load_json: LoadFn<serde_json::Value>,
+    map_entities: Option<MapFn>,
+}
+
+/// The components saved in snapshots, by name
+#[derive(Resource)]
+pub struct SnapshotRegistry {
+    registrations: Vec<Registration>,
+    by_name: HashMap<String, usize>,
+}
+
+impl SnapshotRegistry {
+    /// A registry without any components
+    pub fn empty() -> Self {
+        Self { registrations: Vec::new(), by_name: HashMap::new() }
+    }
+
+    /// A registry with every built-in component
+    pub fn new() -> Self {
+        macro_rules! register {
+            ($registry:ident; $($component:ident),* $(,)?) => {
+                $( $registry.register::<$component>(stringify!($component)); )*
+            };
+        }
+        let mut registry = Self::empty();
+        register!(registry;
+            ComponentId, ComponentInfo, ComponentDescription, Datasheet, Fields, ComponentFlags,
+            Position, BoundingBox, Layer, MountingStyle, NetTies, DesignRuleOverrides, Models3D,
+            Pads, MountingHole, Courtyard, Height, Shape, Classification,
+            Resistance, Capacitance, Inductance, VoltageRating, Tolerance, Dielectric,
+            Resistor, Capacitor, Inductor, Diode, Transistor, IntegratedCircuit, Connector,
+            Crystal, Switch, TestPoint,
+            Track, Via, Zone, Silkscreen, Net, NetPins, SchematicNet,
+            SheetPath, SchematicWire, SchematicJunction, SchematicLabel, SchematicPin,
+            SchematicSheetPin, EffectivePart,
+        );
+        registry.register_mapped::<Violation>("Violation");
+        registry.register_mapped::<Decoupling>("Decoupling");
+        registry
+    }
+
+    /// Save and load component `T` under `name`, replacing any component registered under it
+    pub fn register<T: Component + Serialize + DeserializeOwned>(&mut self, name: &str) -> &mut Self {
+        self.add(Registration {
+            name: name.to_string(),
+            save_binary: save::<T, Binary>,
+            save_json: save::<T, Json>,
+            load_binary: load::<T, Binary>,
+            load_json: load::<T, Json>,
+            map_entities: None,
+        })
+    }
+
+    /// Like `register`, for a component holding entity references, which are remapped to the
+    /// loaded entities
+    pub fn register_mapped<T: Component + Serialize + DeserializeOwned + MapEntities>(&mut self, name: &str) -> &mut Self {
+        self.register::<T>(name);
+        self.registrations[self.by_name[name]].map_entities = Some(map::<T>);
+        self
+    }
+
+    fn add(&mut self, registration: Registration) -> &mut Self {
+        match self.by_name.get(&registration.name) {
+            Some(&index) => self.registrations[index] = registration,
+            None => {
+                self.by_name.insert(registration.name.clone(), self.registrations.len());
+                self.registrations.push(registration);
+            }
+        }
+        self
+    }
+
+    /// Whether a component is registered under `name`
+    pub fn contains(&self, name: &str) -> bool {
+        self.by_name.contains_key(name)
+    }
+
+    /// Number of registered components
+    pub fn len(&self) -> usize {
+        self.registrations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registrations.is_empty()
+    }
+}
+
+impl Default for SnapshotRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ===== Entity references =====
+
+struct EntityRemap(HashMap<Entity, Entity>);
+
+impl EntityMapper for EntityRemap {
+    /// Entities that were not in the snapshot become `Entity::PLACEHOLDER`
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        self.0.get(&entity).copied().unwrap_or(Entity::PLACEHOLDER)
+    }
+}
+
+impl MapEntities for Violation {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        for entity in &mut self.entities {
+            *entity = mapper.map_entity(*entity);
+        }
+    }
+}
+
+impl MapEntities for Decoupling {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        for capacitor in self.pins.iter_mut().flat_map(|pin| pin.capacitors.iter_mut()) {
+            capacitor.entity = mapper.map_entity(capacitor.entity);
+        }
+    }
+}
+
+// ===== Snapshots =====
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotFile<P> {
+    version: u32,
+    layer_names: Vec<(PcbLayer, String)>,
+    outline: Option<BoardOutline>,
+    aux_origin: Option<AuxOrigin>,
+    entities: Vec<SavedEntity<P>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntity<P> {
+    id: Entity,
+    components: BTreeMap<String, P>,
+}
+
+fn collect<C: Codec>(world: &World, registry: &SnapshotRegistry) -> Result<SnapshotFile<C::Payload>> {
+    let mut entities = Vec::new();
+    for entity in world.iter_entities() {
+        let mut components = BTreeMap::new();
+        for registration in &registry.registrations {
+            if let Some(payload) = C::saver(registration)(&entity) {
+                let payload = payload.wrap_err_with(|| format!("Failed to save {}", registration.name))?;
+                components.insert(registration.name.clone(), payload);
+            }
+        }
+        if !components.is_empty() {
+            entities.push(SavedEntity { id: entity.id(), components });
+        }
+    }
+
+    let mut layer_names: Vec<(PcbLayer, String)> = world
+        .get_resource::<LayerNames>()
+        .map(|names| names.names.iter().map(|(layer, name)| (*layer, name.clone())).collect())
+        .unwrap_or_default();
+    layer_names.sort();
+
+    Ok(SnapshotFile {
+        version: SNAPSHOT_VERSION,
+        layer_names,
+        outline: world.get_resource::<BoardOutline>().cloned(),
+        aux_origin: world.get_resource::<AuxOrigin>().copied(),
+        entities,
+    })
+}
+
+fn restore<C: Codec>(world: &mut World, registry: &SnapshotRegistry, file: SnapshotFile<C::Payload>) -> Result<Vec<Entity>> {
+    if file.version != SNAPSHOT_VERSION {
+        return Err(eyre!("Unsupported snapshot version {} (expected {})", file.version, SNAPSHOT_VERSION));
+    }
+
+    // Spawn everything first so that entity references can be remapped afterwards
+    let spawned: Vec<Entity> = file.entities.iter().map(|_| world.spawn_empty().id()).collect();
+    let mut remap = EntityRemap(file.entities.iter().map(|saved| saved.id).zip(spawned.iter().copied()).collect());
+
+    let mut unknown = BTreeMap::new();
+    for (saved, &entity) in file.entities.iter().zip(&spawned) {
+        let mut entity = world.entity_mut(entity);
+        for (name, payload) in &saved.components {
+            match registry.by_name.get(name) {
+                Some(&index) => C::loader(&registry.registrations[index])(&mut entity, payload)
+                    .wrap_err_with(|| format!("Failed to load {}", name))?,
+                None => *unknown.entry(name.as_str()).or_insert(0) += 1,
+            }
+        }
+    }
+    for (name, count) in unknown {
+        warn!("Skipped {} unregistered {} components", count, name);
+    }
+
+    let mappers: Vec<MapFn> = registry.registrations.iter().filter_map(|registration| registration.map_entities).collect();
+    for &entity in &spawned {
+        let mut entity = world.entity_mut(entity);
+        for map_entities in &mappers {
+            map_entities(&mut entity, &mut remap);
+        }
+    }
+
+    if !file.layer_names.is_empty() {
+        world.insert_resource(LayerNames { names: file.layer_names.into_iter().collect() });
+    }
+    if let Some(outline) = file.outline {
+        world.insert_resource(outline);
+    }
+    if let Some(aux_origin) = file.aux_origin {
+        world.insert_resource(aux_origin);
+    }
+    Ok(spawned)
+}
+
+/// Encode every entity with registered components, plus the outline, layer names and aux origin
+#[instrument(skip(world))]
+pub fn save_world(world: &mut World, format: SnapshotFormat) -> Result<Vec<u8>> {
+    world.get_resource_or_insert_with(SnapshotRegistry::new);
+    let registry = world.resource::<SnapshotRegistry>();
+    let data = match format {
+        SnapshotFormat::Binary => {
+            let mut data = MAGIC.to_vec();
+            bincode::serialize_into(&mut data, &collect::<Binary>(world, registry)?)?;
+            data
+        }
+        SnapshotFormat::Json => serde_json::to_vec_pretty(&collect::<Json>(world, registry)?)?,
+    };
+    info!("Saved {} byte snapshot", data.len());
+    Ok(data)
+}
+
+/// Spawn the entities of a snapshot into `world`, returning them in saved order
+#[instrument(skip(world, data), fields(bytes = data.len()))]
+pub fn load_world(world: &mut World, data: &[u8], format: SnapshotFormat) -> Result<Vec<Entity>> {
+    world.get_resource_or_insert_with(SnapshotRegistry::new);
+    let spawned = world.resource_scope(|world, registry: Mut<SnapshotRegistry>| match format {
+        SnapshotFormat::Binary => {
+            let data = data.strip_prefix(MAGIC.as_slice()).ok_or_else(|| eyre!("Not a kicad-ecs snapshot"))?;
+            let file: SnapshotFile<Vec<u8>> = bincode::deserialize(data).wrap_err("Corrupt snapshot")?;
+            restore::<Binary>(world, &registry, file)
+        }
+        SnapshotFormat::Json => {
+            let file: SnapshotFile<serde_json::Value> = serde_json::from_slice(data).wrap_err("Corrupt snapshot")?;
+            restore::<Json>(world, &registry, file)
+        }
+    })?;
+    info!("Loaded {} entities from snapshot", spawned.len());
+    Ok(spawned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drc::Severity;
+    use crate::units::{Angle, Length, Point};
+
+    #[derive(Component, Debug, PartialEq, Serialize, Deserialize)]
+    struct Reviewed(String);
+
+    #[test]
+    fn round_trips_components_and_entity_references() {
+        let mut world = World::new();
+        let mut registry = SnapshotRegistry::new();
+        registry.register::<Reviewed>("Reviewed");
+        world.insert_resource(registry);
+
+        let original = world.spawn((
+            ComponentId { uuid: "r1".to_string() },
+            ComponentInfo { reference: "R1".to_string(), value: "10k".to_string(), footprint_name: "R_0603".to_string() },
+            Position::new(Point::from_mm(10.0, 5.0), Angle::from_degrees(90.0)),
+            Layer { id: PcbLayer::InCu(2) },
+            Shape::stroke(&[Point::ORIGIN], false, Length::from_mm(1.0), &Default::default()),
+            Resistor,
+            Reviewed("ok".to_string()),
+        )).id();
+        world.spawn(Violation {
+            rule: "test".to_string(),
+            severity: Severity::Error,
+            message: "R1 is wrong".to_string(),
+            location: Point::ORIGIN,
+            entities: vec![original],
+            items: vec!["r1".to_string()],
+        });
+
+        for format in [SnapshotFormat::Binary, SnapshotFormat::Json] {
+            let data = save_world(&mut world, format).unwrap();
+
+            // Entities already in the target world shift the ids of the loaded ones
+            let mut loaded = World::new();
+            loaded.spawn_batch((0..5).map(|_| Net { name: "GND".to_string() }));
+            let mut registry = SnapshotRegistry::new();
+            registry.register::<Reviewed>("Reviewed");
+            loaded.insert_resource(registry);
+            let spawned = load_world(&mut loaded, &data, format).unwrap();
+            assert_eq!(spawned.len(), 2);
+
+            let r1 = loaded.entity(spawned[0]);
+            assert_eq!(r1.get::<ComponentInfo>().unwrap().reference, "R1");
+            assert_eq!(r1.get::<Layer>(), Some(&Layer { id: PcbLayer::InCu(2) }));
+            assert_eq!(r1.get::<Position>().unwrap().rotation, Angle::from_degrees(90.0));
+            assert_eq!(r1.get::<Shape>(), world.get::<Shape>(original));
+            assert!(r1.contains::<Resistor>());
+            assert_eq!(r1.get::<Reviewed>(), Some(&Reviewed("ok".to_string())));
+            assert_eq!(loaded.get::<Violation>(spawned[1]).unwrap().entities, vec![spawned[0]]);
+
+            // Without the registration the user component is skipped
+            let mut plain = World::new();
+            let spawned = load_world(&mut plain, &data, format).unwrap();
+            assert!(!plain.entity(spawned[0]).contains::<Reviewed>());
+        }
+        assert!(load_world(&mut World::new(), b"garbage", SnapshotFormat::Binary).is_err());
+    }
+}
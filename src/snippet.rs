@@ -0,0 +1,427 @@
+//! Reusable board snippets
+//!
+//! A snippet is a group of placed and routed board items, such as a power supply block, kept as
+//! the S-expression text KiCad uses for copy and paste. `KiCadClient::save_selection_to_string`
+//! captures the selection in pcbnew, `Snippet::instantiate` makes a copy moved and rotated around
+//! the snippet's anchor, with nets and references renamed and fresh UUIDs, and
+//! `KiCadClient::place_snippet` pastes that copy into the open board.
+//!
+//! Top-level items are in board coordinates and are transformed point by point. Footprint
+//! contents are relative to the footprint, so only the footprint's own position moves, while
+//! the absolute angles of its pads and texts turn with it. Rectangles become polygons when the
+//! rotation is not a multiple of 90°, since KiCad rectangles are axis-aligned.
+
+use eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use tracing::{debug, info, instrument};
+use crate::sexpr::SExpr;
+use crate::units::{Angle, Length, Point};
+
+/// File name suffix of snippets in a `SnippetLibrary` directory
+pub const SNIPPET_EXTENSION: &str = ".snippet.json";
+
+/// Lists holding a point as their first two arguments
+const POINT_LISTS: &[&str] = &["at", "start", "mid", "end", "center", "xy"];
+
+/// Items whose `(at x y angle)` carries an absolute orientation
+const ORIENTED_ITEMS: &[&str] = &["footprint", "module", "pad", "property", "fp_text", "gr_text", "gr_text_box", "fp_text_box"];
+
+/// Lists of a `kicad_pcb` container that describe the board rather than items on it
+const BOARD_METADATA: &[&str] = &["version", "generator", "generator_version", "general", "paper", "title_block", "layers", "setup", "net", "property"];
+
+/// A group of board items that can be placed again elsewhere
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The point that lands on the placement position, in the coordinates of `contents`
+    pub anchor: Point,
+    /// Names of the nets the items connect to
+    pub nets: Vec<String>,
+    /// The items, as KiCad clipboard text
+    pub contents: String,
+}
+
+/// Where and how to place a copy of a snippet
+#[derive(Debug, Clone, Default)]
+pub struct SnippetPlacement {
+    /// Board position of the snippet's anchor
+    pub position: Point,
+    /// Counter-clockwise rotation around the anchor
+    pub rotation: Angle,
+    /// Net renames, old name to new; other nets keep their names
+    pub nets: HashMap<String, String>,
+    /// Reference renames, e.g. "U1" to "U7"; other references are kept
+    pub references: HashMap<String, String>,
+}
+
+impl Snippet {
+    /// A snippet of clipboard text (from `KiCadClient::save_selection_to_string`), anchored at
+    /// the center of its items' bounding box
+    pub fn new(name: &str, contents: &str) -> Result<Self> {
+        let root = SExpr::parse(contents).wrap_err("Invalid snippet contents")?;
+        let mut points = Vec::new();
+        for item in items(&root) {
+            item_points(item, &mut points);
+        }
+        let Some(&first) = points.first() else {
+            return Err(eyre!("Snippet {} has no placed items", name));
+        };
+        let (min, max) = points.iter().fold((first, first), |(min, max), point| {
+            (Point::new(min.x.min(point.x), min.y.min(point.y)), Point::new(max.x.max(point.x), max.y.max(point.y)))
+        });
+
+        let mut nets = BTreeSet::new();
+        net_names(&root, &mut nets);
+        Ok(Self {
+            name: name.to_string(),
+            description: String::new(),
+            anchor: Point::new((min.x + max.x) / 2, (min.y + max.y) / 2),
+            nets: nets.into_iter().collect(),
+            contents: contents.to_string(),
+        })
+    }
+
+    /// The snippet with another anchor, e.g. the position of its main footprint
+    pub fn with_anchor(mut self, anchor: Point) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Clipboard text for a copy of the snippet placed per `placement`, ready for
+    /// `KiCadClient::parse_and_create_items`
+    #[instrument(skip(self, placement), fields(name = %self.name))]
+    pub fn instantiate(&self, placement: &SnippetPlacement) -> Result<String> {
+        let mut root = SExpr::parse(&self.contents).wrap_err_with(|| format!("Invalid snippet {}", self.name))?;
+        let (anchor, target, rotation) = (self.anchor, placement.position, placement.rotation);
+        let transform = |point: Point| (target + (point - anchor)).rotated(rotation, &target);
+        let square = (rotation.degrees() / 90.0).fract().abs() < 1e-9;
+
+        for item in items_mut(&mut root) {
+            if !square && item.is("gr_rect") {
+                rectangle_to_polygon(item);
+            }
+            if item.is("footprint") || item.is("module") {
+                place_footprint(item, &transform, rotation);
+            } else {
+                transform_points(item, None, &transform, rotation);
+            }
+        }
+
+        rename(&mut root, &placement.nets, &placement.references);
+        let mut uuids = HashMap::new();
+        refresh_uuids(&mut root, &mut uuids);
+        debug!("Instantiated {} with {} new UUIDs", self.name, uuids.len());
+        Ok(root.to_string())
+    }
+}
+
+/// Snippets by name, stored as one JSON file each
+#[derive(Debug, Clone, Default)]
+pub struct SnippetLibrary {
+    pub snippets: BTreeMap<String, Snippet>,
+}
+
+impl SnippetLibrary {
+    /// Load every snippet file in a directory
+    #[instrument(skip(dir), fields(dir = %dir.as_ref().display()))]
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut library = Self::default();
+        let entries = std::fs::read_dir(dir)
+            .wrap_err_with(|| format!("Failed to read snippet library {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if !path.to_string_lossy().ends_with(SNIPPET_EXTENSION) {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("Failed to read snippet {}", path.display()))?;
+            let snippet: Snippet = serde_json::from_str(&text)
+                .wrap_err_with(|| format!("Invalid snippet {}", path.display()))?;
+            library.insert(snippet);
+        }
+        info!("Loaded {} snippets", library.snippets.len());
+        Ok(library)
+    }
+
+    /// Write every snippet into a directory, creating it if needed
+    pub fn save_to_dir(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("Failed to create snippet library {}", dir.display()))?;
+        for snippet in self.snippets.values() {
+            let path = dir.join(format!("{}{}", file_stem(&snippet.name), SNIPPET_EXTENSION));
+            std::fs::write(&path, serde_json::to_string_pretty(snippet)?)
+                .wrap_err_with(|| format!("Failed to write snippet {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Add a snippet, returning the one it replaces
+    pub fn insert(&mut self, snippet: Snippet) -> Option<Snippet> {
+        self.snippets.insert(snippet.name.clone(), snippet)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Snippet> {
+        self.snippets.get(name)
+    }
+}
+
+/// A snippet name made safe for use as a file name
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+// ===== Items =====
+
+fn is_item(expr: &SExpr) -> bool {
+    expr.name().is_some_and(|name| !BOARD_METADATA.contains(&name))
+}
+
+/// The board items of clipboard text: the children of a `kicad_pcb` container, or a single item
+fn items(root: &SExpr) -> Vec<&SExpr> {
+    if root.is("kicad_pcb") {
+        root.args().iter().filter(|item| is_item(item)).collect()
+    } else {
+        vec![root]
+    }
+}
+
+fn items_mut(root: &mut SExpr) -> Vec<&mut SExpr> {
+    if !root.is("kicad_pcb") {
+        return vec![root];
+    }
+    match root {
+        SExpr::List(children) => children.iter_mut().skip(1).filter(|item| is_item(item)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Board-coordinate points of an item; only the position of a footprint
+fn item_points(item: &SExpr, points: &mut Vec<Point>) {
+    if item.is("footprint") || item.is("module") {
+        points.extend(item.child("at").and_then(xy));
+        return;
+    }
+    if item.name().is_some_and(|name| POINT_LISTS.contains(&name)) {
+        points.extend(xy(item));
+    }
+    for child in item.args() {
+        item_points(child, points);
+    }
+}
+
+fn xy(expr: &SExpr) -> Option<Point> {
+    Some(Point::from_mm(expr.number(0)?, expr.number(1)?))
+}
+
+fn net_names(expr: &SExpr, names: &mut BTreeSet<String>) {
+    match expr.name() {
+        // (net 3 "GND"), (net "GND") or (net 3), which is resolved by the net table
+        Some("net") => names.extend(net_name_index(expr).and_then(|index| expr.atom(index)).filter(|name| !name.is_empty()).map(str::to_string)),
+        Some("net_name") => names.extend(expr.atom(0).filter(|name| !name.is_empty()).map(str::to_string)),
+        _ => expr.args().iter().for_each(|child| net_names(child, names)),
+    }
+}
+
+/// Which argument of a `net` list is the name
+fn net_name_index(net: &SExpr) -> Option<usize> {
+    match (net.args().first(), net.args().get(1)) {
+        (_, Some(SExpr::String(_))) => Some(1),
+        (Some(SExpr::String(_)), None) => Some(0),
+        _ => None,
+    }
+}
+
+// ===== Transforms =====
+
+fn format_mm(length: Length) -> SExpr {
+    SExpr::Symbol(length.mm().to_string())
+}
+
+fn format_degrees(angle: Angle) -> SExpr {
+    let degrees = (angle.normalized().degrees() * 1e6).round() / 1e6;
+    SExpr::Symbol(degrees.to_string())
+}
+
+fn set_point(list: &mut SExpr, point: Point) {
+    if let SExpr::List(items) = list {
+        if items.len() >= 3 {
+            items[1] = format_mm(point.x);
+            items[2] = format_mm(point.y);
+        }
+    }
+}
+
+/// Turn the angle of an `(at x y [angle])` list by `rotation`
+fn rotate_at(at: &mut SExpr, rotation: Angle) {
+    if rotation == Angle::ZERO {
+        return;
+    }
+    let angle = Angle::from_degrees(at.number(2).unwrap_or(0.0)) + rotation;
+    if let SExpr::List(items) = at {
+        items.truncate(3);
+        items.push(format_degrees(angle));
+    }
+}
+
+/// Map every point of a top-level item, turning the angle of oriented items
+fn transform_points(expr: &mut SExpr, parent: Option<&str>, transform: &impl Fn(Point) -> Point, rotation: Angle) {
+    let name = expr.name().map(str::to_string);
+    if let Some(name) = name.as_deref().filter(|name| POINT_LISTS.contains(name)) {
+        if let Some(point) = xy(expr) {
+            set_point(expr, transform(point));
+        }
+        if name == "at" && parent.is_some_and(|parent| ORIENTED_ITEMS.contains(&parent)) {
+            rotate_at(expr, rotation);
+        }
+        return;
+    }
+    if let SExpr::List(children) = expr {
+        for child in children.iter_mut().skip(1) {
+            transform_points(child, name.as_deref(), transform, rotation);
+        }
+    }
+}
+
+/// Move a footprint and turn it with its pads and texts; their offsets are relative to it
+fn place_footprint(footprint: &mut SExpr, transform: &impl Fn(Point) -> Point, rotation: Angle) {
+    let SExpr::List(children) = footprint else {
+        return;
+    };
+    for child in children.iter_mut().skip(1) {
+        if child.is("at") {
+            if let Some(point) = xy(child) {
+                set_point(child, transform(point));
+            }
+            rotate_at(child, rotation);
+        } else if child.name().is_some_and(|name| ORIENTED_ITEMS.contains(&name)) {
+            if let SExpr::List(parts) = child {
+                parts.iter_mut().filter(|part| part.is("at")).for_each(|at| rotate_at(at, rotation));
+            }
+        }
+    }
+}
+
+/// Replace `(gr_rect (start ..) (end ..) ..)` with the equivalent `(gr_poly (pts ..) ..)`
+fn rectangle_to_polygon(rect: &mut SExpr) {
+    let (Some(start), Some(end)) = (rect.child("start").and_then(xy), rect.child("end").and_then(xy)) else {
+        return;
+    };
+    let corners = [start, Point::new(end.x, start.y), end, Point::new(start.x, end.y)];
+    let mut pts = vec![SExpr::Symbol("pts".to_string())];
+    pts.extend(corners.iter().map(|corner| SExpr::List(vec![SExpr::Symbol("xy".to_string()), format_mm(corner.x), format_mm(corner.y)])));
+    if let SExpr::List(children) = rect {
+        children.retain(|child| !child.is("start") && !child.is("end"));
+        children[0] = SExpr::Symbol("gr_poly".to_string());
+        children.insert(1, SExpr::List(pts));
+    }
+}
+
+/// Rename nets and references throughout
+fn rename(expr: &mut SExpr, nets: &HashMap<String, String>, references: &HashMap<String, String>) {
+    let index = match expr.name() {
+        Some("net") => net_name_index(expr).map(|index| (index, nets)),
+        Some("net_name") => Some((0, nets)),
+        Some("property") if expr.atom(0) == Some("Reference") => Some((1, references)),
+        Some("fp_text") if expr.atom(0) == Some("reference") => Some((1, references)),
+        _ => None,
+    };
+    let SExpr::List(children) = expr else {
+        return;
+    };
+    if let Some((index, renames)) = index {
+        if let Some(SExpr::String(name)) = children.get_mut(index + 1) {
+            if let Some(renamed) = renames.get(name.as_str()) {
+                *name = renamed.clone();
+            }
+        }
+    }
+    for child in children.iter_mut().skip(1) {
+        rename(child, nets, references);
+    }
+}
+
+/// Give every item a new UUID, keeping group membership consistent
+fn refresh_uuids(expr: &mut SExpr, uuids: &mut HashMap<String, String>) {
+    let renamed = matches!(expr.name(), Some("uuid" | "tstamp" | "members"));
+    let SExpr::List(children) = expr else {
+        return;
+    };
+    for child in children.iter_mut().skip(1) {
+        match child {
+            SExpr::Symbol(id) | SExpr::String(id) if renamed => {
+                *id = uuids.entry(id.clone()).or_insert_with(new_uuid).clone();
+            }
+            child => refresh_uuids(child, uuids),
+        }
+    }
+}
+
+/// A random (version 4) UUID
+fn new_uuid() -> String {
+    let bits: u128 = rand::random();
+    let bits = (bits & !(0xf << 76) | (0x4 << 76)) & !(0x3 << 62) | (0x2 << 62);
+    let hex = format!("{:032x}", bits);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SELECTION: &str = r#"
+        (kicad_pcb (version 20240108) (generator "pcbnew")
+          (footprint "Package_TO_SOT_SMD:SOT-23" (layer "F.Cu") (uuid "fp-u1") (at 10 10)
+            (property "Reference" "U1" (at 0 -2.4) (layer "F.SilkS"))
+            (pad "1" smd rect (at -0.95 1) (size 0.6 0.7) (layers "F.Cu") (net 1 "VIN") (uuid "pad-1")))
+          (segment (start 10 10) (end 14 10) (width 0.3) (layer "F.Cu") (net 1 "VIN") (uuid "trk-1"))
+          (via (at 14 10) (size 0.6) (drill 0.3) (layers "F.Cu" "B.Cu") (net 2 "GND") (uuid "via-1"))
+          (gr_rect (start 8 8) (end 16 12) (stroke (width 0.1)) (layer "F.SilkS") (uuid "box-1"))
+          (group "" (uuid "grp-1") (members "fp-u1" "trk-1" "via-1")))
+    "#;
+
+    #[test]
+    fn instantiates_moved_rotated_and_renamed() {
+        let snippet = Snippet::new("ldo", SELECTION).unwrap();
+        assert_eq!(snippet.anchor, Point::from_mm(12.0, 10.0));
+        assert_eq!(snippet.nets, vec!["GND".to_string(), "VIN".to_string()]);
+
+        let placement = SnippetPlacement {
+            position: Point::from_mm(100.0, 50.0),
+            rotation: Angle::from_degrees(90.0),
+            nets: [("VIN".to_string(), "VIN_2".to_string())].into(),
+            references: [("U1".to_string(), "U7".to_string())].into(),
+        };
+        let copy = SExpr::parse(&snippet.instantiate(&placement).unwrap()).unwrap();
+        let placed = items(&copy);
+
+        // The footprint 2 mm left of the anchor ends up 2 mm below it, turned by 90°
+        let footprint = placed[0];
+        assert_eq!(footprint.child("at").unwrap().args().iter().filter_map(SExpr::as_atom).collect::<Vec<_>>(), ["100", "52", "90"]);
+        assert_eq!(footprint.child("pad").and_then(|pad| pad.child("at")).and_then(|at| at.number(2)), Some(90.0));
+        assert_eq!(footprint.child("property").and_then(|reference| reference.atom(1)), Some("U7"));
+        assert_eq!(footprint.child("pad").and_then(|pad| pad.child("net")).and_then(|net| net.atom(1)), Some("VIN_2"));
+
+        let segment = placed[1];
+        assert_eq!(segment.child("end").and_then(xy), Some(Point::from_mm(100.0, 48.0)));
+        assert_eq!(placed[2].child("at").map(|at| at.args().len()), Some(2));
+        assert!(placed[3].is("gr_rect"));
+
+        // UUIDs are new, and the group still lists its members
+        let uuid = footprint.value("uuid").unwrap();
+        assert_ne!(uuid, "fp-u1");
+        assert_eq!(uuid.len(), 36);
+        assert_eq!(placed[4].child("members").and_then(|members| members.atom(0)), Some(uuid));
+
+        let tilted = SnippetPlacement { rotation: Angle::from_degrees(30.0), ..Default::default() };
+        let copy = SExpr::parse(&snippet.instantiate(&tilted).unwrap()).unwrap();
+        assert!(items(&copy)[3].is("gr_poly"));
+    }
+}
@@ -0,0 +1,211 @@
+//! Spatial index for proximity queries
+//!
+//! `SpatialIndex` keeps one R-tree per layer of every entity with a `Position` and `Layer`. Items
+//! are indexed by their `BoundingBox` when they have one and by their position otherwise.
+//! `update_spatial_index` keeps the trees in sync using change detection, so only entities whose
+//! position, layer or bounding box changed since the last update are re-indexed.
+//!
+//! Coordinates are integer nanometers like `Length`; squared distances stay within `i64` for
+//! boards up to about two meters across.
+
+use bevy_ecs::prelude::*;
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+use std::collections::HashMap;
+use tracing::debug;
+use crate::components::{BoundingBox, Layer, Position};
+use crate::layers::PcbLayer;
+use crate::units::{Length, Point};
+
+/// An entity's extent as stored in the R-tree
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IndexedItem {
+    entity: Entity,
+    envelope: AABB<[i64; 2]>,
+}
+
+impl RTreeObject for IndexedItem {
+    type Envelope = AABB<[i64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl PointDistance for IndexedItem {
+    /// Squared distance from a point to the nearest edge of the item (zero inside it)
+    fn distance_2(&self, point: &[i64; 2]) -> i64 {
+        self.envelope.distance_2(point)
+    }
+}
+
+fn to_rstar(point: Point) -> [i64; 2] {
+    [point.x.nm(), point.y.nm()]
+}
+
+fn envelope(position: &Position, bounds: Option<&BoundingBox>) -> AABB<[i64; 2]> {
+    match bounds {
+        Some(bounds) => AABB::from_corners(to_rstar(bounds.min), to_rstar(bounds.max)),
+        None => AABB::from_point(to_rstar(position.point())),
+    }
+}
+
+/// Per-layer R-trees over positioned entities
+#[derive(Resource, Default)]
+pub struct SpatialIndex {
+    layers: HashMap<PcbLayer, RTree<IndexedItem>>,
+    items: HashMap<Entity, (PcbLayer, IndexedItem)>,
+}
+
+impl SpatialIndex {
+    /// Number of indexed entities
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Add or move an entity
+    pub fn insert(&mut self, entity: Entity, layer: PcbLayer, position: &Position, bounds: Option<&BoundingBox>) {
+        self.remove(entity);
+        let item = IndexedItem { entity, envelope: envelope(position, bounds) };
+        self.layers.entry(layer).or_default().insert(item);
+        self.items.insert(entity, (layer, item));
+    }
+
+    /// Remove an entity; returns whether it was indexed
+    pub fn remove(&mut self, entity: Entity) -> bool {
+        let Some((layer, item)) = self.items.remove(&entity) else {
+            return false;
+        };
+        if let Some(tree) = self.layers.get_mut(&layer) {
+            tree.remove(&item);
+        }
+        true
+    }
+
+    /// The layer and extent an entity is indexed with
+    pub fn bounds(&self, entity: Entity) -> Option<(PcbLayer, BoundingBox)> {
+        self.items.get(&entity).map(|(layer, item)| {
+            let (min, max) = (item.envelope.lower(), item.envelope.upper());
+            (*layer, BoundingBox::new(Point::from_nm(min[0], min[1]), Point::from_nm(max[0], max[1])))
+        })
+    }
+
+    fn trees(&self, layer: Option<PcbLayer>) -> impl Iterator<Item = &RTree<IndexedItem>> {
+        self.layers
+            .iter()
+            .filter(move |(tree_layer, _)| layer.is_none_or(|layer| **tree_layer == layer))
+            .map(|(_, tree)| tree)
+    }
+
+    /// The `n` entities closest to `point`, nearest first, with their distance. Pass `None` as
+    /// the layer to search all layers.
+    pub fn nearest(&self, layer: Option<PcbLayer>, point: Point, n: usize) -> Vec<(Entity, Length)> {
+        let query = to_rstar(point);
+        let mut found: Vec<(Entity, i64)> = self
+            .trees(layer)
+            .flat_map(|tree| {
+                tree.nearest_neighbor_iter_with_distance_2(&query)
+                    .take(n)
+                    .map(|(item, distance_2)| (item.entity, distance_2))
+            })
+            .collect();
+        found.sort_by_key(|(_, distance_2)| *distance_2);
+        found.truncate(n);
+        found
+            .into_iter()
+            .map(|(entity, distance_2)| (entity, Length::from_nm((distance_2 as f64).sqrt().round() as i64)))
+            .collect()
+    }
+
+    /// Entities whose extent comes within `radius` of `point`
+    pub fn within_radius(&self, layer: Option<PcbLayer>, point: Point, radius: Length) -> Vec<Entity> {
+        let query = to_rstar(point);
+        let radius_2 = radius.nm() * radius.nm();
+        self.trees(layer)
+            .flat_map(|tree| tree.locate_within_distance(query, radius_2).map(|item| item.entity))
+            .collect()
+    }
+
+    /// Entities whose extent overlaps or touches `area`
+    pub fn intersecting(&self, layer: Option<PcbLayer>, area: &BoundingBox) -> Vec<Entity> {
+        let envelope = AABB::from_corners(to_rstar(area.min), to_rstar(area.max));
+        self.trees(layer)
+            .flat_map(|tree| tree.locate_in_envelope_intersecting(&envelope).map(|item| item.entity))
+            .collect()
+    }
+}
+
+/// Re-index entities whose position, layer or bounding box changed, and drop entities that lost
+/// their position or layer (including despawned ones)
+#[allow(clippy::type_complexity)]
+pub fn update_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    changed: Query<Entity, Or<(Changed<Position>, Changed<Layer>, Changed<BoundingBox>)>>,
+    items: Query<(&Position, &Layer, Option<&BoundingBox>)>,
+    mut removed_positions: RemovedComponents<Position>,
+    mut removed_layers: RemovedComponents<Layer>,
+    mut removed_bounds: RemovedComponents<BoundingBox>,
+) {
+    let mut dirty: Vec<Entity> = changed.iter().collect();
+    dirty.extend(removed_positions.read());
+    dirty.extend(removed_layers.read());
+    dirty.extend(removed_bounds.read());
+    if dirty.is_empty() {
+        return;
+    }
+
+    for entity in &dirty {
+        match items.get(*entity) {
+            Ok((position, layer, bounds)) => index.insert(*entity, layer.id, position, bounds),
+            Err(_) => {
+                index.remove(*entity);
+            }
+        }
+    }
+    debug!("Re-indexed {} entities, {} in spatial index", dirty.len(), index.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units::Angle;
+
+    fn spawn(world: &mut World, x_mm: f64, y_mm: f64, layer: PcbLayer) -> Entity {
+        world.spawn((Position::new(Point::from_mm(x_mm, y_mm), Angle::ZERO), Layer { id: layer })).id()
+    }
+
+    #[test]
+    fn tracks_changes_and_answers_queries() {
+        let mut world = World::new();
+        world.init_resource::<SpatialIndex>();
+        let system = world.register_system(update_spatial_index);
+
+        let a = spawn(&mut world, 0.0, 0.0, PcbLayer::FCu);
+        let b = spawn(&mut world, 5.0, 0.0, PcbLayer::FCu);
+        let c = spawn(&mut world, 1.0, 0.0, PcbLayer::BCu);
+        world.run_system(system).unwrap();
+
+        let index = world.resource::<SpatialIndex>();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.nearest(Some(PcbLayer::FCu), Point::from_mm(4.0, 0.0), 1), vec![(b, Length::from_mm(1.0))]);
+        assert_eq!(index.nearest(None, Point::from_mm(0.9, 0.0), 1)[0].0, c);
+        assert_eq!(index.within_radius(Some(PcbLayer::FCu), Point::ORIGIN, Length::from_mm(2.0)), vec![a]);
+
+        // Move b next to the origin, give a a 2 mm box and despawn c
+        world.entity_mut(b).insert(Position::new(Point::from_mm(3.0, 0.0), Angle::ZERO));
+        world.entity_mut(a).insert(BoundingBox::around(Point::ORIGIN, Length::from_mm(2.0)));
+        world.despawn(c);
+        world.run_system(system).unwrap();
+
+        let index = world.resource::<SpatialIndex>();
+        assert_eq!(index.len(), 2);
+        let area = BoundingBox::new(Point::from_mm(0.5, -0.5), Point::from_mm(3.5, 0.5));
+        let mut hits = index.intersecting(None, &area);
+        hits.sort();
+        assert_eq!(hits, vec![a, b]);
+        assert_eq!(index.nearest(None, Point::from_mm(0.5, 0.0), 1), vec![(a, Length::ZERO)]);
+    }
+}
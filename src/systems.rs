@@ -2,15 +2,21 @@
 
 use bevy_ecs::prelude::*;
 use crate::components::*;
+use crate::layers::LayerNames;
 
 /// System to analyze component distribution by layer
 pub fn layer_analysis_system(
-    query: Query<(&ComponentInfo, &Layer)>
+    query: Query<(&ComponentInfo, &Layer)>,
+    layer_names: Option<Res<LayerNames>>,
 ) {
     let mut layer_counts = std::collections::HashMap::new();
     
     for (_info, layer) in query.iter() {
-        *layer_counts.entry(layer.layer_name.clone()).or_insert(0) += 1;
+        let name = match &layer_names {
+            Some(names) => names.name(layer.id),
+            None => layer.id.canonical_name(),
+        };
+        *layer_counts.entry(name).or_insert(0) += 1;
     }
     
     println!("Component distribution by layer:");
@@ -26,8 +32,13 @@ pub fn mounting_hole_analysis_system(
     println!("Mounting hole analysis:");
     
     for (info, pos, hole) in query.iter() {
-        println!("  {} at ({:.1}, {:.1}): {} hole, {} screw", 
-                 info.reference, pos.x, pos.y, 
-                 hole.diameter_mm, hole.screw_size);
+        let plating = if hole.plated { "plated" } else { "unplated" };
+        match (&hole.screw_size, hole.fit) {
+            (Some(screw), Some(fit)) => println!("  {} at {:.1}: {:.2} {} hole, {} screw ({} fit), keepout {:.1}",
+                                                 info.reference, pos.point(), hole.diameter, plating,
+                                                 screw, fit, hole.keepout_diameter),
+            _ => println!("  {} at {:.1}: {:.2} {} hole, no matching screw size",
+                          info.reference, pos.point(), hole.diameter, plating),
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,297 @@
+//! Strongly typed units
+//!
+//! KiCad stores all geometry as integer nanometers and angles as degrees. `Length` keeps the
+//! nanometer integer so values read from KiCad can be written back without loss, and only
+//! converts to millimeters, mils or inches at the edges.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+const NM_PER_MM: f64 = 1_000_000.0;
+const NM_PER_MIL: f64 = 25_400.0;
+const NM_PER_INCH: f64 = 25_400_000.0;
+
+/// A length or coordinate, stored as integer nanometers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Length(i64);
+
+impl Length {
+    pub const ZERO: Length = Length(0);
+
+    /// Create a length from nanometers
+    pub const fn from_nm(nanometers: i64) -> Self {
+        Length(nanometers)
+    }
+
+    /// Create a length from millimeters, rounded to the nearest nanometer
+    pub fn from_mm(millimeters: f64) -> Self {
+        Length((millimeters * NM_PER_MM).round() as i64)
+    }
+
+    /// Create a length from mils (thousandths of an inch), rounded to the nearest nanometer
+    pub fn from_mils(mils: f64) -> Self {
+        Length((mils * NM_PER_MIL).round() as i64)
+    }
+
+    /// Create a length from inches, rounded to the nearest nanometer
+    pub fn from_inches(inches: f64) -> Self {
+        Length((inches * NM_PER_INCH).round() as i64)
+    }
+
+    /// The length in nanometers
+    pub const fn nm(self) -> i64 {
+        self.0
+    }
+
+    /// The length in millimeters
+    pub fn mm(self) -> f64 {
+        self.0 as f64 / NM_PER_MM
+    }
+
+    /// The length in mils
+    pub fn mils(self) -> f64 {
+        self.0 as f64 / NM_PER_MIL
+    }
+
+    /// The length in inches
+    pub fn inches(self) -> f64 {
+        self.0 as f64 / NM_PER_INCH
+    }
+
+    /// The absolute value of this length
+    pub fn abs(self) -> Self {
+        Length(self.0.abs())
+    }
+}
+
+impl fmt::Display for Length {
+    /// Formats as millimeters, honoring any requested precision (`{:.2}` → "1.27 mm")
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.mm(), f)?;
+        f.write_str(" mm")
+    }
+}
+
+impl Add for Length {
+    type Output = Length;
+    fn add(self, rhs: Length) -> Length {
+        Length(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for Length {
+    fn add_assign(&mut self, rhs: Length) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for Length {
+    type Output = Length;
+    fn sub(self, rhs: Length) -> Length {
+        Length(self.0 - rhs.0)
+    }
+}
+
+impl SubAssign for Length {
+    fn sub_assign(&mut self, rhs: Length) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Neg for Length {
+    type Output = Length;
+    fn neg(self) -> Length {
+        Length(-self.0)
+    }
+}
+
+impl Mul<i64> for Length {
+    type Output = Length;
+    fn mul(self, rhs: i64) -> Length {
+        Length(self.0 * rhs)
+    }
+}
+
+impl Mul<f64> for Length {
+    type Output = Length;
+    /// Scale a length, rounding to the nearest nanometer
+    fn mul(self, rhs: f64) -> Length {
+        Length((self.0 as f64 * rhs).round() as i64)
+    }
+}
+
+impl Div<i64> for Length {
+    type Output = Length;
+    fn div(self, rhs: i64) -> Length {
+        Length(self.0 / rhs)
+    }
+}
+
+impl Div for Length {
+    type Output = f64;
+    /// Ratio between two lengths
+    fn div(self, rhs: Length) -> f64 {
+        self.0 as f64 / rhs.0 as f64
+    }
+}
+
+impl Sum for Length {
+    fn sum<I: Iterator<Item = Length>>(iter: I) -> Self {
+        Length(iter.map(|l| l.0).sum())
+    }
+}
+
+/// An angle, stored in degrees like KiCad's `EDA_ANGLE`
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct Angle(f64);
+
+impl Angle {
+    pub const ZERO: Angle = Angle(0.0);
+
+    /// Create an angle from degrees
+    pub const fn from_degrees(degrees: f64) -> Self {
+        Angle(degrees)
+    }
+
+    /// Create an angle from radians
+    pub fn from_radians(radians: f64) -> Self {
+        Angle(radians.to_degrees())
+    }
+
+    /// The angle in degrees
+    pub const fn degrees(self) -> f64 {
+        self.0
+    }
+
+    /// The angle in radians
+    pub fn radians(self) -> f64 {
+        self.0.to_radians()
+    }
+
+    /// The equivalent angle in the range [0, 360)
+    pub fn normalized(self) -> Self {
+        Angle(self.0.rem_euclid(360.0))
+    }
+}
+
+impl fmt::Display for Angle {
+    /// Formats as degrees, honoring any requested precision (`{:.0}` → "90°")
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        f.write_str("°")
+    }
+}
+
+impl Add for Angle {
+    type Output = Angle;
+    fn add(self, rhs: Angle) -> Angle {
+        Angle(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Angle {
+    type Output = Angle;
+    fn sub(self, rhs: Angle) -> Angle {
+        Angle(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Angle {
+    type Output = Angle;
+    fn neg(self) -> Angle {
+        Angle(-self.0)
+    }
+}
+
+/// A point or offset in board coordinates (KiCad's Y axis points down)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Point {
+    pub x: Length,
+    pub y: Length,
+}
+
+impl Point {
+    pub const ORIGIN: Point = Point { x: Length::ZERO, y: Length::ZERO };
+
+    pub const fn new(x: Length, y: Length) -> Self {
+        Self { x, y }
+    }
+
+    /// Create a point from nanometer coordinates
+    pub const fn from_nm(x: i64, y: i64) -> Self {
+        Self { x: Length::from_nm(x), y: Length::from_nm(y) }
+    }
+
+    /// Create a point from millimeter coordinates
+    pub fn from_mm(x: f64, y: f64) -> Self {
+        Self { x: Length::from_mm(x), y: Length::from_mm(y) }
+    }
+
+    /// Straight-line distance to another point, rounded to the nearest nanometer
+    pub fn distance(&self, other: &Point) -> Length {
+        let dx = (self.x - other.x).nm() as f64;
+        let dy = (self.y - other.y).nm() as f64;
+        Length::from_nm(dx.hypot(dy).round() as i64)
+    }
+
+    /// Rotate this point around `center` by `angle`, using KiCad's convention that positive
+    /// angles are counter-clockwise as seen on screen
+    pub fn rotated(&self, angle: Angle, center: &Point) -> Point {
+        let (sin, cos) = angle.radians().sin_cos();
+        let dx = (self.x - center.x).nm() as f64;
+        let dy = (self.y - center.y).nm() as f64;
+        // Y points down, so a visually counter-clockwise rotation negates the usual sin terms
+        let x = dx * cos + dy * sin;
+        let y = -dx * sin + dy * cos;
+        Point {
+            x: center.x + Length::from_nm(x.round() as i64),
+            y: center.y + Length::from_nm(y.round() as i64),
+        }
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, rhs: Point) -> Point {
+        Point { x: self.x + rhs.x, y: self.y + rhs.y }
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, rhs: Point) -> Point {
+        Point { x: self.x - rhs.x, y: self.y - rhs.y }
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(p) => write!(f, "({:.*}, {:.*}) mm", p, self.x.mm(), p, self.y.mm()),
+            None => write!(f, "({}, {}) mm", self.x.mm(), self.y.mm()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_round_instead_of_truncating() {
+        // 0.1 mm * 1e6 is 99999.99999999999 in f64; truncation would lose a nanometer
+        assert_eq!(Length::from_mm(0.1).nm(), 100_000);
+        assert_eq!(Length::from_mils(10.0).nm(), 254_000);
+        assert_eq!(Length::from_inches(0.1), Length::from_mils(100.0));
+        assert_eq!(Length::from_nm(1_270_000).mm(), 1.27);
+    }
+
+    #[test]
+    fn rotation_follows_kicad_convention() {
+        let p = Point::from_mm(1.0, 0.0).rotated(Angle::from_degrees(90.0), &Point::ORIGIN);
+        assert_eq!(p, Point::from_mm(0.0, -1.0));
+        assert_eq!(Angle::from_degrees(-90.0).normalized().degrees(), 270.0);
+    }
+}
@@ -0,0 +1,393 @@
+//! Component value parsing
+//!
+//! Turns free-form value strings such as "10k", "4k7", "0R", "100nF 50V X7R" or "2u2 ±10%" into
+//! engineering quantities. Both SI suffixes ("4.7k", "100nF") and IEC 60062 RKM notation, where
+//! the multiplier letter replaces the decimal point ("4k7", "4R7", "2n2"), are understood.
+//!
+//! Values without a unit ("10k", "100n") are interpreted using the component's classification
+//! marker: ohms for `Resistor`, farads for `Capacitor` and henries for `Inductor`.
+
+use bevy_ecs::prelude::*;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::OnceLock;
+use tracing::{debug, info, instrument};
+use crate::components::*;
+
+/// The kind of quantity a value describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuantityKind {
+    Resistance,
+    Capacitance,
+    Inductance,
+}
+
+/// A parsed primary quantity, in base SI units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quantity {
+    Resistance(f64),
+    Capacitance(f64),
+    Inductance(f64),
+}
+
+/// Everything recognized in a value string
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedValue {
+    pub quantity: Option<Quantity>,
+    pub voltage_rating: Option<f64>,
+    pub tolerance: Option<f64>,
+    pub dielectric: Option<Dielectric>,
+}
+
+fn quantity_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(?P<int>\d*)(?:[.,](?P<frac>\d+))?(?P<mult>[pnuµμmkKMGRr])?(?P<rkm>\d*)(?P<unit>Ω|Ω|[Oo]hms?|OHMS?|[FfHh])?$")
+            .expect("valid quantity pattern")
+    })
+}
+
+fn voltage_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^(?P<int>\d+)(?:[.,](?P<frac>\d+))?(?P<mult>[mk])?[Vv](?P<rkm>\d*)(?:DC|dc)?$")
+            .expect("valid voltage pattern")
+    })
+}
+
+fn tolerance_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"^[±+\-/]*(?P<int>\d+)(?:[.,](?P<frac>\d+))?%$").expect("valid tolerance pattern")
+    })
+}
+
+/// Build a number from its integer part and a fractional part written either after a decimal
+/// point or after an RKM multiplier letter
+fn number(int: &str, frac: &str) -> Option<f64> {
+    let int = if int.is_empty() { "0" } else { int };
+    if frac.is_empty() {
+        int.parse().ok()
+    } else {
+        format!("{}.{}", int, frac).parse().ok()
+    }
+}
+
+fn multiplier(prefix: &str) -> f64 {
+    match prefix {
+        "p" => 1e-12,
+        "n" => 1e-9,
+        "u" | "µ" | "μ" => 1e-6,
+        "m" => 1e-3,
+        "k" | "K" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        _ => 1.0,
+    }
+}
+
+/// Parse a primary quantity such as "4k7", "100nF", "0R" or "10uH"
+pub fn parse_quantity(token: &str, hint: Option<QuantityKind>) -> Option<Quantity> {
+    let captures = quantity_regex().captures(token)?;
+    let get = |name| captures.name(name).map_or("", |m| m.as_str());
+    let (int, frac, mult, rkm, unit) = (get("int"), get("frac"), get("mult"), get("rkm"), get("unit"));
+
+    // Need at least one digit, and RKM digits only make sense after a multiplier letter
+    if int.is_empty() && frac.is_empty() && rkm.is_empty() {
+        return None;
+    }
+    if !rkm.is_empty() && (mult.is_empty() || !frac.is_empty()) {
+        return None;
+    }
+
+    let kind = match unit.chars().next() {
+        Some('F' | 'f') => QuantityKind::Capacitance,
+        Some('H' | 'h') => QuantityKind::Inductance,
+        Some(_) => QuantityKind::Resistance,
+        None if matches!(mult, "R" | "r") => QuantityKind::Resistance,
+        None => hint?,
+    };
+    // A bare number is only trusted as ohms; "10" on a capacitor is as likely pF as F
+    if mult.is_empty() && unit.is_empty() && kind != QuantityKind::Resistance {
+        return None;
+    }
+
+    let value = number(int, if rkm.is_empty() { frac } else { rkm })? * multiplier(mult);
+    Some(match kind {
+        QuantityKind::Resistance => Quantity::Resistance(value),
+        QuantityKind::Capacitance => Quantity::Capacitance(value),
+        QuantityKind::Inductance => Quantity::Inductance(value),
+    })
+}
+
+fn parse_voltage(token: &str) -> Option<f64> {
+    let captures = voltage_regex().captures(token)?;
+    let get = |name| captures.name(name).map_or("", |m| m.as_str());
+    let (frac, rkm) = (get("frac"), get("rkm"));
+    if !frac.is_empty() && !rkm.is_empty() {
+        return None;
+    }
+    Some(number(get("int"), if rkm.is_empty() { frac } else { rkm })? * multiplier(get("mult")))
+}
+
+fn parse_tolerance(token: &str) -> Option<f64> {
+    let captures = tolerance_regex().captures(token)?;
+    number(&captures["int"], captures.name("frac").map_or("", |m| m.as_str()))
+}
+
+fn parse_dielectric(token: &str) -> Option<Dielectric> {
+    let dielectric = match token.to_ascii_uppercase().as_str() {
+        "C0G" | "COG" | "NP0" | "NPO" => Dielectric::C0G,
+        "X5R" => Dielectric::X5R,
+        "X6S" => Dielectric::X6S,
+        "X7R" => Dielectric::X7R,
+        "X7S" => Dielectric::X7S,
+        "X7T" => Dielectric::X7T,
+        "X8R" => Dielectric::X8R,
+        "Y5V" => Dielectric::Y5V,
+        "Z5U" => Dielectric::Z5U,
+        _ => return None,
+    };
+    Some(dielectric)
+}
+
+/// Parse a value string; `hint` gives the quantity for values written without a unit
+pub fn parse_value(value: &str, hint: Option<QuantityKind>) -> ParsedValue {
+    let mut parsed = ParsedValue::default();
+    let tokens = value
+        .split(|c: char| c.is_whitespace() || matches!(c, '/' | ';' | '_'))
+        .filter(|token| !token.is_empty());
+
+    for token in tokens {
+        if parsed.quantity.is_none() {
+            if let Some(quantity) = parse_quantity(token, hint) {
+                parsed.quantity = Some(quantity);
+                continue;
+            }
+        }
+        if parsed.voltage_rating.is_none() {
+            if let Some(volts) = parse_voltage(token) {
+                parsed.voltage_rating = Some(volts);
+                continue;
+            }
+        }
+        if parsed.tolerance.is_none() {
+            if let Some(percent) = parse_tolerance(token) {
+                parsed.tolerance = Some(percent);
+                continue;
+            }
+        }
+        if parsed.dielectric.is_none() {
+            parsed.dielectric = parse_dielectric(token);
+        }
+    }
+    parsed
+}
+
+/// Parse every component value in the world, replacing any previously parsed `Resistance`,
+/// `Capacitance`, `Inductance`, `VoltageRating`, `Tolerance` and `Dielectric` components.
+/// Returns the number of components with a recognized primary quantity.
+#[instrument(skip(world))]
+pub fn apply_parsed_values(world: &mut World) -> usize {
+    let mut query = world.query::<(Entity, &ComponentInfo, Has<Resistor>, Has<Capacitor>, Has<Inductor>)>();
+    let parsed: Vec<(Entity, ParsedValue)> = query
+        .iter(world)
+        .map(|(entity, info, resistor, capacitor, inductor)| {
+            let hint = if resistor {
+                Some(QuantityKind::Resistance)
+            } else if capacitor {
+                Some(QuantityKind::Capacitance)
+            } else if inductor {
+                Some(QuantityKind::Inductance)
+            } else {
+                None
+            };
+            let value = parse_value(&info.value, hint);
+            debug!("Parsed value {:?} of {} as {:?}", info.value, info.reference, value);
+            (entity, value)
+        })
+        .collect();
+
+    let mut quantities = 0;
+    for (entity, value) in parsed {
+        let mut entity = world.entity_mut(entity);
+        entity.remove::<(Resistance, Capacitance, Inductance, VoltageRating, Tolerance, Dielectric)>();
+        match value.quantity {
+            Some(Quantity::Resistance(ohms)) => { entity.insert(Resistance { ohms }); }
+            Some(Quantity::Capacitance(farads)) => { entity.insert(Capacitance { farads }); }
+            Some(Quantity::Inductance(henries)) => { entity.insert(Inductance { henries }); }
+            None => {}
+        }
+        if value.quantity.is_some() {
+            quantities += 1;
+        }
+        if let Some(volts) = value.voltage_rating {
+            entity.insert(VoltageRating { volts });
+        }
+        if let Some(percent) = value.tolerance {
+            entity.insert(Tolerance { percent });
+        }
+        if let Some(dielectric) = value.dielectric {
+            entity.insert(dielectric);
+        }
+    }
+
+    info!("Parsed {} component values", quantities);
+    quantities
+}
+
+/// Whether a net name looks like a ground or negative supply return
+pub fn is_ground_net(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    name.contains("GND") || name.contains("VSS")
+}
+
+/// Rail name prefixes that mark a net as a power supply
+const POWER_NET_PREFIXES: &[&str] = &[
+    "VCC", "VDD", "AVCC", "AVDD", "DVDD", "VBAT", "VBUS", "VIN", "VSYS", "VIO", "VCORE", "VREF", "PWR",
+];
+
+/// Name parts that make a rail-named net a control or monitor signal, as in "PWR_EN",
+/// "PWRGOOD" or "VBUS_DET"
+const SIGNAL_NET_SUFFIXES: &[&str] = &[
+    "EN", "ENABLE", "GOOD", "PG", "OK", "FAULT", "DET", "DETECT", "SENSE", "FB", "CTRL",
+];
+
+/// Whether a net name looks like a power rail: "+3V3", "VCC", "VDDA", "/power/VBUS", "5V".
+/// Hierarchical sheet paths are ignored and ground nets never count, nor do signals named
+/// after a rail such as "PWR_EN" or "VBUS_DET".
+pub fn is_power_net(name: &str) -> bool {
+    let name = name.rsplit('/').next().unwrap_or(name).to_ascii_uppercase();
+    if name.is_empty() || is_ground_net(&name) {
+        return false;
+    }
+    let mut tokens = name.split(|c: char| !c.is_ascii_alphanumeric()).filter(|token| !token.is_empty());
+    if tokens.any(|token| SIGNAL_NET_SUFFIXES.contains(&token)) {
+        return false;
+    }
+    let rail_prefix = POWER_NET_PREFIXES.iter().find(|prefix| name.starts_with(*prefix));
+    // A suffix written without a separator, as in "PWRGOOD", only when it is all that follows
+    // the rail name; "VCCENET" and "VDDPGM" are rails
+    if rail_prefix.is_some_and(|prefix| SIGNAL_NET_SUFFIXES.contains(&&name[prefix.len()..])) {
+        return false;
+    }
+    name.starts_with('+') || rail_prefix.is_some() || parse_voltage(&name).is_some()
+}
+
+/// Total capacitance connected to each non-ground net, in farads
+///
+/// Each capacitor counts toward every net its pads connect to, except ground nets, so a
+/// decoupling capacitor between +3V3 and GND adds to "+3V3" only.
+pub fn capacitance_per_net(world: &mut World) -> BTreeMap<String, f64> {
+    let mut totals = BTreeMap::new();
+    let mut query = world.query::<(&Capacitance, &Pads)>();
+    for (capacitance, pads) in query.iter(world) {
+        let mut nets: Vec<&str> = pads.pads
+            .iter()
+            .filter_map(|pad| pad.net.as_deref())
+            .filter(|net| !is_ground_net(net))
+            .collect();
+        nets.sort_unstable();
+        nets.dedup();
+        for net in nets {
+            *totals.entry(net.to_string()).or_insert(0.0) += capacitance.farads;
+        }
+    }
+    totals
+}
+
+/// Format a value with an SI prefix, e.g. `format_si(4700.0, "Ω")` → "4.7 kΩ"
+pub fn format_si(value: f64, unit: &str) -> String {
+    const PREFIXES: [(f64, &str); 8] = [
+        (1e9, "G"), (1e6, "M"), (1e3, "k"), (1.0, ""),
+        (1e-3, "m"), (1e-6, "µ"), (1e-9, "n"), (1e-12, "p"),
+    ];
+    if value == 0.0 {
+        return format!("0 {}", unit);
+    }
+    let (scale, prefix) = PREFIXES
+        .iter()
+        .find(|(scale, _)| value.abs() >= scale * 0.999_999)
+        .unwrap_or(&PREFIXES[PREFIXES.len() - 1]);
+    // Round to 4 significant digits to hide float noise such as 4.699999999
+    let scaled = format!("{:.3}", value / scale);
+    let scaled = scaled.trim_end_matches('0').trim_end_matches('.');
+    format!("{} {}{}", scaled, prefix, unit)
+}
+
+impl fmt::Display for Resistance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_si(self.ohms, "Ω"))
+    }
+}
+
+impl fmt::Display for Capacitance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_si(self.farads, "F"))
+    }
+}
+
+impl fmt::Display for Inductance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_si(self.henries, "H"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Option<Quantity>, b: Quantity) -> bool {
+        match (a, b) {
+            (Some(Quantity::Resistance(a)), Quantity::Resistance(b))
+            | (Some(Quantity::Capacitance(a)), Quantity::Capacitance(b))
+            | (Some(Quantity::Inductance(a)), Quantity::Inductance(b)) => (a - b).abs() <= b.abs() * 1e-9,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn rkm_and_si_notation() {
+        let r = Some(QuantityKind::Resistance);
+        assert!(close(parse_quantity("4k7", r), Quantity::Resistance(4700.0)));
+        assert!(close(parse_quantity("10k", r), Quantity::Resistance(10_000.0)));
+        assert!(close(parse_quantity("0R", None), Quantity::Resistance(0.0)));
+        assert!(close(parse_quantity("R47", None), Quantity::Resistance(0.47)));
+        assert!(close(parse_quantity("1M5", r), Quantity::Resistance(1.5e6)));
+        assert!(close(parse_quantity("2n2", Some(QuantityKind::Capacitance)), Quantity::Capacitance(2.2e-9)));
+        assert!(close(parse_quantity("4.7uH", None), Quantity::Inductance(4.7e-6)));
+        assert_eq!(parse_quantity("10k", None), None);
+        assert_eq!(parse_quantity("10", Some(QuantityKind::Capacitance)), None);
+        assert_eq!(parse_quantity("STM32F103", r), None);
+    }
+
+    #[test]
+    fn ratings_tolerance_and_dielectric() {
+        let parsed = parse_value("100nF 50V X7R ±10%", None);
+        assert!(close(parsed.quantity, Quantity::Capacitance(100e-9)));
+        assert_eq!(parsed.voltage_rating, Some(50.0));
+        assert_eq!(parsed.tolerance, Some(10.0));
+        assert_eq!(parsed.dielectric, Some(Dielectric::X7R));
+
+        let parsed = parse_value("10u/6V3/NP0", Some(QuantityKind::Capacitance));
+        assert!(close(parsed.quantity, Quantity::Capacitance(10e-6)));
+        assert_eq!(parsed.voltage_rating, Some(6.3));
+        assert_eq!(parsed.dielectric, Some(Dielectric::C0G));
+
+        assert!(is_power_net("+3V3") && is_power_net("/power/VDDA") && is_power_net("5V"));
+        assert!(!is_power_net("GND") && !is_power_net("SDA") && !is_power_net("/mcu/PA3"));
+        for signal in ["PWR_EN", "PWR_GOOD", "PWRGOOD", "VBUS_DET", "/usb/VBUS_DET", "VDD_SENSE", "+3V3_EN", "VIN_PG"] {
+            assert!(!is_power_net(signal), "{} is not a rail", signal);
+        }
+        assert!(is_power_net("VCC_3V3") && is_power_net("VCCIO") && is_power_net("PWR_5V"));
+        // Suffix letters that merely start the rest of a rail name
+        for rail in ["VCCENET", "VDDPGM", "VINENABLE_OUT", "/usb/VBUSDETACHED"] {
+            assert!(is_power_net(rail), "{} is a rail", rail);
+        }
+        assert!(!is_power_net("PWREN") && !is_power_net("VBUS-DET"));
+
+        assert_eq!(format_si(4700.0, "Ω"), "4.7 kΩ");
+        assert_eq!(format_si(100e-9, "F"), "100 nF");
+    }
+}
@@ -0,0 +1,210 @@
+//! Assembly variants
+//!
+//! A variant overrides the DNP flag, value, MPN and manufacturer of parts by reference designator. Variants
+//! live in the `VariantOverrides` resource and are applied as a view: `apply_variant_system`
+//! writes an `EffectivePart` component on every footprint from its own fields and flags plus the
+//! active variant's overrides, and the BOM, placement list and statistics read `EffectivePart`
+//! when it is present. The board data itself is never changed, so switching variants is just a
+//! matter of selecting another one and running the system again.
+//!
+//! Variants can be loaded from TOML, one table per variant keyed by reference:
+//!
+//! ```toml
+//! [lite]
+//! U3 = { dnp = true }
+//! R12 = { dnp = true }
+//!
+//! [pro]
+//! C4 = { value = "22u", mpn = "GRM21BR61A226ME51L", manufacturer = "Murata" }
+//! ```
+//!
+//! Overriding the MPN without a manufacturer clears the manufacturer, as the board's one
+//! belongs to the replaced part.
+
+use bevy_ecs::prelude::*;
+use eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use tracing::{debug, warn};
+use crate::bom::{MANUFACTURER_FIELDS, MPN_FIELDS};
+use crate::components::*;
+
+/// What a variant changes about one part; unset entries keep the board's value
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartOverride {
+    pub dnp: Option<bool>,
+    pub value: Option<String>,
+    pub mpn: Option<String>,
+    pub manufacturer: Option<String>,
+}
+
+/// The overrides of one variant, by reference designator
+pub type Variant = BTreeMap<String, PartOverride>;
+
+/// All known variants and the one currently applied
+#[derive(Resource, Debug, Clone, Default)]
+pub struct VariantOverrides {
+    pub variants: BTreeMap<String, Variant>,
+    /// `None` applies the board as drawn
+    pub active: Option<String>,
+}
+
+impl VariantOverrides {
+    /// Parse variants from TOML; no variant is active
+    pub fn from_toml_str(config: &str) -> Result<Self> {
+        let variants = toml::from_str(config).wrap_err("Invalid variant config")?;
+        Ok(Self { variants, active: None })
+    }
+
+    /// Load variants from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read variant config {}", path.display()))?;
+        Self::from_toml_str(&config)
+            .wrap_err_with(|| format!("Failed to load variant config {}", path.display()))
+    }
+
+    /// Make `name` the active variant, or the board as drawn for `None`
+    pub fn select(&mut self, name: Option<&str>) -> Result<()> {
+        if let Some(name) = name {
+            if !self.variants.contains_key(name) {
+                return Err(eyre!("Unknown variant {}", name));
+            }
+        }
+        self.active = name.map(str::to_string);
+        Ok(())
+    }
+
+    /// The active variant's override for a reference, if any
+    pub fn part(&self, reference: &str) -> Option<&PartOverride> {
+        self.active
+            .as_ref()
+            .and_then(|name| self.variants.get(name))
+            .and_then(|variant| variant.get(reference))
+    }
+}
+
+/// A footprint as built in the active variant
+#[derive(Component, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectivePart {
+    pub value: String,
+    pub mpn: Option<String>,
+    pub manufacturer: Option<String>,
+    pub dnp: bool,
+    /// The variant this was produced for; `None` for the board as drawn
+    pub variant: Option<String>,
+}
+
+impl EffectivePart {
+    /// The part as drawn on the board, without any variant
+    pub fn base(info: &ComponentInfo, fields: Option<&Fields>, flags: Option<&ComponentFlags>) -> Self {
+        Self {
+            value: info.value.clone(),
+            mpn: fields.and_then(|fields| fields.get_any(MPN_FIELDS)).map(str::to_string),
+            manufacturer: fields.and_then(|fields| fields.get_any(MANUFACTURER_FIELDS)).map(str::to_string),
+            dnp: flags.is_some_and(|flags| flags.do_not_populate),
+            variant: None,
+        }
+    }
+
+    /// `part` if present, otherwise the part as drawn
+    pub fn resolve(part: Option<&EffectivePart>, info: &ComponentInfo, fields: Option<&Fields>, flags: Option<&ComponentFlags>) -> Self {
+        part.cloned().unwrap_or_else(|| Self::base(info, fields, flags))
+    }
+}
+
+/// Write the `EffectivePart` of every footprint for the active variant
+pub fn apply_variant_system(
+    mut commands: Commands,
+    overrides: Res<VariantOverrides>,
+    parts: Query<(Entity, &ComponentInfo, Option<&Fields>, Option<&ComponentFlags>)>,
+) {
+    let mut overridden = HashSet::new();
+    for (entity, info, fields, flags) in &parts {
+        let mut part = EffectivePart::base(info, fields, flags);
+        part.variant = overrides.active.clone();
+        if let Some(change) = overrides.part(&info.reference) {
+            overridden.insert(info.reference.as_str());
+            if let Some(dnp) = change.dnp {
+                part.dnp = dnp;
+            }
+            if let Some(value) = &change.value {
+                part.value = value.clone();
+            }
+            if let Some(mpn) = &change.mpn {
+                part.mpn = Some(mpn.clone());
+                part.manufacturer = None;
+            }
+            if let Some(manufacturer) = &change.manufacturer {
+                part.manufacturer = Some(manufacturer.clone());
+            }
+        }
+        commands.entity(entity).insert(part);
+    }
+
+    if let Some(name) = &overrides.active {
+        match overrides.variants.get(name) {
+            Some(variant) => {
+                for reference in variant.keys().filter(|reference| !overridden.contains(reference.as_str())) {
+                    warn!("Variant {} overrides {}, which is not on the board", name, reference);
+                }
+            }
+            None => warn!("Unknown variant {}; applying the board as drawn", name),
+        }
+    }
+    debug!("Applied variant {:?} to {} parts", overrides.active, overridden.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+
+    #[test]
+    fn variants_override_dnp_value_and_mpn() {
+        let mut world = World::new();
+        let mut overrides = VariantOverrides::from_toml_str(r#"
+            [lite]
+            U3 = { dnp = true }
+
+            [pro]
+            C4 = { value = "22u", mpn = "GRM21BR61A226ME51L" }
+        "#).unwrap();
+        assert!(overrides.select(Some("deluxe")).is_err());
+        overrides.select(Some("lite")).unwrap();
+        world.insert_resource(overrides);
+
+        let info = |reference: &str, value: &str| ComponentInfo {
+            reference: reference.to_string(),
+            value: value.to_string(),
+            footprint_name: String::new(),
+        };
+        let u3 = world.spawn(info("U3", "LDO")).id();
+        let fields = [("MPN", "X"), ("Manufacturer", "Yageo")].map(|(name, value)| (name.to_string(), value.to_string()));
+        let c4 = world.spawn((info("C4", "10u"), Fields { fields: fields.into() })).id();
+
+        world.run_system_once(apply_variant_system);
+        assert!(world.get::<EffectivePart>(u3).unwrap().dnp);
+        assert_eq!(world.get::<EffectivePart>(c4).unwrap().mpn.as_deref(), Some("X"));
+
+        world.resource_mut::<VariantOverrides>().select(Some("pro")).unwrap();
+        world.run_system_once(apply_variant_system);
+        assert!(!world.get::<EffectivePart>(u3).unwrap().dnp);
+        let part = world.get::<EffectivePart>(c4).unwrap();
+        assert_eq!((part.value.as_str(), part.mpn.as_deref(), part.variant.as_deref()), ("22u", Some("GRM21BR61A226ME51L"), Some("pro")));
+        assert_eq!(part.manufacturer, None);
+
+        world.resource_mut::<VariantOverrides>().variants.get_mut("pro").unwrap()
+            .get_mut("C4").unwrap().manufacturer = Some("Murata".to_string());
+        world.run_system_once(apply_variant_system);
+        assert_eq!(world.get::<EffectivePart>(c4).unwrap().manufacturer.as_deref(), Some("Murata"));
+
+        // An active variant set directly on the resource need not exist
+        world.resource_mut::<VariantOverrides>().active = Some("deluxe".to_string());
+        world.run_system_once(apply_variant_system);
+        assert_eq!(world.get::<EffectivePart>(c4).unwrap().mpn.as_deref(), Some("X"));
+    }
+}
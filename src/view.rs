@@ -0,0 +1,242 @@
+//! PCB editor view settings
+//!
+//! pcbnew's view is set by the visible layers, the active layer and the appearance settings of
+//! the board editor (inactive layer display, net colors, flipped board view, ratsnest). A
+//! `ViewPreset` sets any of these at once, leaving unset ones as they are, so a review tool can
+//! switch the editor to e.g. "bottom side, only B.Cu and B.Silkscreen" for each finding with
+//! `KiCadClient::apply_view_preset` and put the previous view back afterwards.
+//!
+//! Presets can be loaded from TOML, one table per preset, with layers by canonical name:
+//!
+//! ```toml
+//! [back]
+//! layers = ["B.Cu", "B.Silkscreen", "Edge.Cuts"]
+//! active_layer = "B.Cu"
+//! flip = "flipped"
+//!
+//! [nets]
+//! inactive_layers = "dimmed"
+//! net_colors = "all"
+//! ```
+
+use eyre::{eyre, Result, WrapErr};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::layers::{PcbLayer, Side};
+
+/// How layers other than the active one are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InactiveLayerDisplay {
+    #[default]
+    Normal,
+    Dimmed,
+    Hidden,
+}
+
+/// Where net and netclass colors are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetColorDisplay {
+    /// On the ratsnest and on all copper items
+    #[default]
+    All,
+    /// On the ratsnest only
+    Ratsnest,
+    Off,
+}
+
+/// Whether the board is viewed from the front or, mirrored, from the back
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardFlip {
+    #[default]
+    Normal,
+    /// Viewed from the back, mirrored around the X axis
+    Flipped,
+}
+
+/// Which ratsnest lines are drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RatsnestDisplay {
+    /// Also to items on hidden layers
+    #[default]
+    AllLayers,
+    /// Only between items on visible layers
+    VisibleLayers,
+}
+
+/// Appearance settings of the board editor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Appearance {
+    pub inactive_layers: InactiveLayerDisplay,
+    pub net_colors: NetColorDisplay,
+    pub flip: BoardFlip,
+    pub ratsnest: RatsnestDisplay,
+}
+
+/// A set of view settings to apply together; unset entries keep the editor's current setting
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewPreset {
+    /// The layers to show; every other layer is hidden
+    #[serde(default, with = "layer_names", skip_serializing_if = "Option::is_none")]
+    pub layers: Option<Vec<PcbLayer>>,
+    #[serde(default, with = "layer_name", skip_serializing_if = "Option::is_none")]
+    pub active_layer: Option<PcbLayer>,
+    pub inactive_layers: Option<InactiveLayerDisplay>,
+    pub net_colors: Option<NetColorDisplay>,
+    pub flip: Option<BoardFlip>,
+    pub ratsnest: Option<RatsnestDisplay>,
+}
+
+impl ViewPreset {
+    /// One side of the board as seen from that side: its outer copper (active) and silkscreen
+    /// plus the board outline
+    pub fn side(side: Side) -> Self {
+        Self {
+            layers: Some(vec![PcbLayer::outer_copper(side), PcbLayer::silkscreen(side), PcbLayer::EdgeCuts]),
+            active_layer: Some(PcbLayer::outer_copper(side)),
+            flip: Some(match side {
+                Side::Front => BoardFlip::Normal,
+                Side::Back => BoardFlip::Flipped,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// `appearance` with the entries this preset sets replaced
+    pub fn apply_to(&self, appearance: Appearance) -> Appearance {
+        Appearance {
+            inactive_layers: self.inactive_layers.unwrap_or(appearance.inactive_layers),
+            net_colors: self.net_colors.unwrap_or(appearance.net_colors),
+            flip: self.flip.unwrap_or(appearance.flip),
+            ratsnest: self.ratsnest.unwrap_or(appearance.ratsnest),
+        }
+    }
+
+    /// Whether the preset changes any appearance setting
+    pub fn sets_appearance(&self) -> bool {
+        self.inactive_layers.is_some() || self.net_colors.is_some() || self.flip.is_some() || self.ratsnest.is_some()
+    }
+}
+
+/// Named view presets
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewPresets {
+    pub presets: BTreeMap<String, ViewPreset>,
+}
+
+impl ViewPresets {
+    /// The built-in presets `front` and `back` (see `ViewPreset::side`)
+    pub fn new() -> Self {
+        let presets = [("front", Side::Front), ("back", Side::Back)]
+            .into_iter()
+            .map(|(name, side)| (name.to_string(), ViewPreset::side(side)))
+            .collect();
+        Self { presets }
+    }
+
+    /// Parse presets from TOML, added to (or replacing) the built-in ones
+    pub fn from_toml_str(config: &str) -> Result<Self> {
+        let presets: BTreeMap<String, ViewPreset> = toml::from_str(config).wrap_err("Invalid view presets")?;
+        let mut result = Self::new();
+        result.presets.extend(presets);
+        Ok(result)
+    }
+
+    /// Load presets from a TOML file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let config = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read view presets {}", path.display()))?;
+        Self::from_toml_str(&config)
+            .wrap_err_with(|| format!("Failed to load view presets {}", path.display()))
+    }
+
+    /// The preset called `name`
+    pub fn get(&self, name: &str) -> Result<&ViewPreset> {
+        self.presets.get(name).ok_or_else(|| eyre!("Unknown view preset {}", name))
+    }
+}
+
+impl Default for ViewPresets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Layers as canonical names in config files
+mod layer_names {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(layers: &Option<Vec<PcbLayer>>, serializer: S) -> Result<S::Ok, S::Error> {
+        let names = layers.as_ref().map(|layers| layers.iter().map(PcbLayer::canonical_name).collect::<Vec<_>>());
+        names.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<PcbLayer>>, D::Error> {
+        let names: Option<Vec<String>> = Option::deserialize(deserializer)?;
+        names.map(|names| {
+            names.iter()
+                .map(|name| PcbLayer::from_name(name).ok_or_else(|| D::Error::custom(format!("unknown layer {}", name))))
+                .collect()
+        }).transpose()
+    }
+}
+
+/// A layer as its canonical name in config files
+mod layer_name {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(layer: &Option<PcbLayer>, serializer: S) -> Result<S::Ok, S::Error> {
+        layer.map(|layer| layer.canonical_name()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PcbLayer>, D::Error> {
+        let name: Option<String> = Option::deserialize(deserializer)?;
+        name.map(|name| PcbLayer::from_name(&name).ok_or_else(|| D::Error::custom(format!("unknown layer {}", name))))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_from_toml() {
+        let presets = ViewPresets::from_toml_str(r#"
+            [inner]
+            layers = ["In1.Cu", "In2.Cu", "Edge.Cuts"]
+            active_layer = "In1.Cu"
+            inactive_layers = "dimmed"
+
+            [back]
+            layers = ["B.Cu", "B.Silkscreen"]
+        "#).unwrap();
+
+        let inner = presets.get("inner").unwrap();
+        assert_eq!(inner.layers, Some(vec![PcbLayer::InCu(1), PcbLayer::InCu(2), PcbLayer::EdgeCuts]));
+        assert_eq!(inner.active_layer, Some(PcbLayer::InCu(1)));
+        let appearance = inner.apply_to(Appearance { flip: BoardFlip::Flipped, ..Default::default() });
+        assert_eq!(appearance.inactive_layers, InactiveLayerDisplay::Dimmed);
+        assert_eq!(appearance.flip, BoardFlip::Flipped);
+
+        // Presets from the file replace built-in ones of the same name
+        assert_eq!(presets.get("back").unwrap().layers, Some(vec![PcbLayer::BCu, PcbLayer::BSilkS]));
+        assert_eq!(presets.get("front").unwrap(), &ViewPreset::side(Side::Front));
+        assert!(presets.get("top").is_err());
+
+        let back = ViewPreset::side(Side::Back);
+        assert_eq!(back.flip, Some(BoardFlip::Flipped));
+        let text = toml::to_string(&back).unwrap();
+        assert_eq!(toml::from_str::<ViewPreset>(&text).unwrap(), back);
+
+        assert!(ViewPresets::from_toml_str("[bad]\nlayers = [\"Q.Cu\"]").is_err());
+    }
+}
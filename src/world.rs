@@ -1,29 +1,101 @@
 //! PCB World - the main ECS container
 
 use bevy_ecs::prelude::*;
-use eyre::Result;
-use tracing::{debug, instrument};
+use bevy_ecs::system::SystemId;
+use eyre::{Result, WrapErr};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{debug, info, instrument, warn};
+use crate::board_file::BoardFile;
+use crate::classify::ClassificationRegistry;
+use crate::client::KiCadClient;
 use crate::components::*;
+use crate::bom::{generate_bom, Bom, BomOptions};
+use crate::cpl::{generate_cpl, AuxOrigin, Cpl, CplOptions};
+use crate::decoupling::DecouplingRules;
+use crate::diff::BoardDiff;
+use crate::drc::{drc_schedule, DesignRules, NetClassRules, Violation};
+use crate::layers::{LayerNames, PcbLayer, Side};
+use crate::geometry::{PolygonWithHoles, Shape, Tessellation};
+use crate::mounting::detect_mounting_hole;
+use crate::outline::{BoardOutline, DEFAULT_CHAINING_EPSILON};
+use crate::placement::{courtyard_from_graphics, part_height, PlacementRules};
+use crate::snapshot::{load_world, save_world, SnapshotFormat, SnapshotRegistry};
+use crate::spatial::{update_spatial_index, SpatialIndex};
+use crate::units::Angle;
+use crate::variant::{apply_variant_system, EffectivePart, VariantOverrides};
 
 /// Main ECS world for PCB data
 pub struct PcbWorld {
     pub world: World,
     component_count: usize,
+    spatial_index_system: SystemId,
+    variant_system: SystemId,
+    drc: Schedule,
 }
 
 impl PcbWorld {
     /// Create a new empty PCB world
     pub fn new() -> Self {
+        let mut world = World::new();
+        world.init_resource::<LayerNames>();
+        world.init_resource::<SpatialIndex>();
+        world.init_resource::<Tessellation>();
+        world.init_resource::<DesignRules>();
+        world.init_resource::<PlacementRules>();
+        world.init_resource::<DecouplingRules>();
+        world.init_resource::<VariantOverrides>();
+        world.init_resource::<SnapshotRegistry>();
+        let spatial_index_system = world.register_system(update_spatial_index);
+        let variant_system = world.register_system(apply_variant_system);
+        
         Self {
-            world: World::new(),
+            world,
             component_count: 0,
+            spatial_index_system,
+            variant_system,
+            drc: drc_schedule(),
+        }
+    }
+    
+    /// Populate a new world from a parsed board: layer names, footprints with their pad shapes,
+    /// tracks, vias, zones, silkscreen and the board outline
+    #[instrument(skip(board), fields(footprints = board.footprints.len()))]
+    pub fn from_board(board: BoardFile) -> Self {
+        let mut pcb = Self::new();
+        pcb.set_layer_names(board.layer_names);
+        pcb.world.insert_resource(AuxOrigin { point: board.aux_origin });
+        for footprint in board.footprints {
+            pcb.spawn_footprint(footprint);
+        }
+        pcb.apply_pad_shapes(&board.pad_shapes);
+        pcb.spawn_tracks(board.tracks);
+        pcb.spawn_vias(board.vias);
+        pcb.spawn_zones(board.zones);
+        pcb.spawn_silkscreen(&board.shapes);
+        for issue in &pcb.build_board_outline(&board.shapes).issues {
+            warn!("Board outline: {}", issue);
         }
+        info!("Loaded board with {} footprints", pcb.component_count);
+        pcb
     }
     
-    /// Load board data from KiCad (placeholder)
-    pub fn from_board(_board_data: ()) -> Result<Self> {
-        // TODO: Implement board loading
-        Ok(Self::new())
+    /// Load a `.kicad_pcb` file
+    pub fn from_board_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::from_board(BoardFile::from_file(path)?))
+    }
+    
+    /// Load the board open in KiCad with a single `SaveDocumentToString` request, parsed like a
+    /// `.kicad_pcb` file. On large boards this is much faster than fetching each item type, and
+    /// gives the same world as loading the saved file. Netclasses are project settings, so set
+    /// them separately with `set_net_classes`.
+    #[instrument(skip(client))]
+    pub async fn from_kicad_document(client: &mut KiCadClient) -> Result<Self> {
+        let text = client.save_document_to_string().await?;
+        let board = BoardFile::parse(&text).wrap_err("Failed to parse the board document from KiCad")?;
+        Ok(Self::from_board(board))
     }
     
     /// Add a component to the world
@@ -33,8 +105,8 @@ impl PcbWorld {
                         reference: String, 
                         value: String,
                         footprint_name: String,
-                        position: (f64, f64, f64), // x, y, rotation
-                        layer: String,
+                        position: Position,
+                        layer: PcbLayer,
                         description: String,
                         flags: (bool, bool, bool)) -> Entity { // exclude_bom, dnp, locked
         
@@ -43,13 +115,14 @@ impl PcbWorld {
         let entity = self.world.spawn((
             ComponentId { uuid: id },
             ComponentInfo { reference, value, footprint_name },
-            Position { x: position.0, y: position.1, rotation: position.2 },
-            Layer { layer_name: layer },
+            position,
+            Layer { id: layer },
             ComponentDescription { description },
             ComponentFlags {
                 exclude_from_bom: flags.0,
                 do_not_populate: flags.1,
                 locked: flags.2,
+                ..Default::default()
             },
         ));
         
@@ -62,18 +135,17 @@ impl PcbWorld {
     pub fn add_mounting_hole(&mut self,
                             id: String,
                             reference: String,
-                            position: (f64, f64, f64), // x, y, rotation
-                            layer: String,
-                            diameter: f64,
-                            screw_size: String) -> Entity {
+                            position: Position,
+                            layer: PcbLayer,
+                            hole: MountingHole) -> Entity {
         
         debug!("Adding mounting hole {} to ECS world", reference);
         
         self.world.spawn((
             ComponentId { uuid: id },
-            Position { x: position.0, y: position.1, rotation: position.2 },
-            Layer { layer_name: layer },
-            MountingHole { diameter_mm: diameter, screw_size },
+            position,
+            Layer { id: layer },
+            hole,
         )).id()
     }
     
@@ -84,124 +156,62 @@ impl PcbWorld {
     pub fn spawn_footprint(&mut self, footprint_data: crate::client::FootprintData) -> Entity {
         debug!("Spawning footprint {} to ECS world", footprint_data.reference);
         
-        let entity = self.world.spawn((
+        let position = Position::new(footprint_data.position, footprint_data.rotation);
+        let tessellation = *self.world.resource::<Tessellation>();
+        let courtyard = (!footprint_data.courtyards.is_empty())
+            .then(|| courtyard_from_graphics(&footprint_data.courtyards, &position, &tessellation));
+        let fields = Fields { fields: footprint_data.fields };
+        let height = part_height(&fields, &footprint_data.footprint_name);
+        
+        let mut entity = self.world.spawn((
             ComponentId { uuid: footprint_data.id },
             ComponentInfo { 
                 reference: footprint_data.reference,
                 value: footprint_data.value,
-                footprint_name: footprint_data.footprint_name,
+                footprint_name: footprint_data.footprint_name.clone(),
             },
-            Position { 
-                x: footprint_data.position.0, 
-                y: footprint_data.position.1, 
-                rotation: footprint_data.rotation,
-            },
-            Layer { layer_name: footprint_data.layer },
+            position,
+            Layer { id: footprint_data.layer },
             ComponentDescription { 
-                description: "Component".to_string(),
+                description: footprint_data.description.unwrap_or_default(),
             },
+            fields,
             ComponentFlags {
                 exclude_from_bom: footprint_data.exclude_from_bom,
                 do_not_populate: footprint_data.do_not_populate,
                 locked: footprint_data.locked,
+                not_in_schematic: footprint_data.not_in_schematic,
+                exclude_from_position_files: footprint_data.exclude_from_position_files,
+                exempt_from_courtyard: footprint_data.exempt_from_courtyard,
             },
+            footprint_data.mounting_style,
         ));
         
-        self.component_count += 1;
-        entity.id()
-    }
-    
-    /// Spawn a resistor entity
-    #[instrument(skip(self), fields(reference = %reference))]
-    pub fn spawn_resistor(&mut self, 
-                         id: String,
-                         reference: String,
-                         value: String, 
-                         footprint: String,
-                         position: (f64, f64, f64),
-                         layer: String) -> Entity {
-        debug!("Spawning resistor {} to ECS world", reference);
-        
-        let entity = self.world.spawn((
-            ComponentId { uuid: id },
-            ComponentInfo { reference, value, footprint_name: footprint },
-            Position { x: position.0, y: position.1, rotation: position.2 },
-            Layer { layer_name: layer },
-            ComponentDescription { description: "Resistor".to_string() },
-            Resistor, // Marker component for resistors
-        ));
-        
-        self.component_count += 1;
-        entity.id()
-    }
-    
-    /// Spawn a capacitor entity
-    #[instrument(skip(self), fields(reference = %reference))]
-    pub fn spawn_capacitor(&mut self,
-                          id: String, 
-                          reference: String,
-                          value: String,
-                          footprint: String,
-                          position: (f64, f64, f64),
-                          layer: String) -> Entity {
-        debug!("Spawning capacitor {} to ECS world", reference);
-        
-        let entity = self.world.spawn((
-            ComponentId { uuid: id },
-            ComponentInfo { reference, value, footprint_name: footprint },
-            Position { x: position.0, y: position.1, rotation: position.2 },
-            Layer { layer_name: layer },
-            ComponentDescription { description: "Capacitor".to_string() },
-            Capacitor, // Marker component for capacitors
-        ));
-        
-        self.component_count += 1;
-        entity.id()
-    }
-    
-    /// Spawn an IC entity
-    #[instrument(skip(self), fields(reference = %reference))]
-    pub fn spawn_ic(&mut self,
-                   id: String,
-                   reference: String, 
-                   value: String,
-                   footprint: String,
-                   position: (f64, f64, f64),
-                   layer: String) -> Entity {
-        debug!("Spawning IC {} to ECS world", reference);
-        
-        let entity = self.world.spawn((
-            ComponentId { uuid: id },
-            ComponentInfo { reference, value, footprint_name: footprint },
-            Position { x: position.0, y: position.1, rotation: position.2 },
-            Layer { layer_name: layer },
-            ComponentDescription { description: "Integrated Circuit".to_string() },
-            IntegratedCircuit, // Marker component for ICs
-        ));
-        
-        self.component_count += 1;
-        entity.id()
-    }
-    
-    /// Spawn a connector entity
-    #[instrument(skip(self), fields(reference = %reference))]
-    pub fn spawn_connector(&mut self,
-                          id: String,
-                          reference: String,
-                          value: String, 
-                          footprint: String,
-                          position: (f64, f64, f64),
-                          layer: String) -> Entity {
-        debug!("Spawning connector {} to ECS world", reference);
+        let pads = Pads { pads: footprint_data.pads };
+        if let Some(hole) = detect_mounting_hole(&footprint_data.footprint_name, &pads) {
+            debug!("Detected {:?} mounting hole", hole.screw_size);
+            entity.insert(hole);
+        }
+        entity.insert(pads);
         
-        let entity = self.world.spawn((
-            ComponentId { uuid: id },
-            ComponentInfo { reference, value, footprint_name: footprint },
-            Position { x: position.0, y: position.1, rotation: position.2 },
-            Layer { layer_name: layer },
-            ComponentDescription { description: "Connector".to_string() },
-            Connector, // Marker component for connectors
-        ));
+        if let Some(courtyard) = courtyard {
+            entity.insert(courtyard);
+        }
+        if let Some(height) = height {
+            entity.insert(Height { height });
+        }
+        if let Some(url) = footprint_data.datasheet {
+            entity.insert(Datasheet { url });
+        }
+        if !footprint_data.net_ties.is_empty() {
+            entity.insert(NetTies { groups: footprint_data.net_ties });
+        }
+        if !footprint_data.overrides.is_empty() {
+            entity.insert(footprint_data.overrides);
+        }
+        if !footprint_data.models.is_empty() {
+            entity.insert(Models3D { models: footprint_data.models });
+        }
         
         self.component_count += 1;
         entity.id()
@@ -212,20 +222,355 @@ impl PcbWorld {
     pub fn spawn_mounting_hole(&mut self,
                               id: String,
                               reference: String,
-                              position: (f64, f64, f64),
-                              layer: String,
-                              diameter: f64,
-                              screw_size: String) -> Entity {
+                              position: Position,
+                              layer: PcbLayer,
+                              hole: MountingHole) -> Entity {
         debug!("Spawning mounting hole {} to ECS world", reference);
         
         self.world.spawn((
             ComponentId { uuid: id },
-            Position { x: position.0, y: position.1, rotation: position.2 },
-            Layer { layer_name: layer },
-            MountingHole { diameter_mm: diameter, screw_size },
+            position,
+            Layer { id: layer },
+            hole,
         )).id()
     }
     
+    /// Classify all footprints with the given registry, inserting `Classification` and marker
+    /// components. Returns the number of footprints classified.
+    #[instrument(skip(self, registry))]
+    pub fn classify(&mut self, registry: &ClassificationRegistry) -> usize {
+        registry.apply(&mut self.world)
+    }
+    
+    /// Parse component values into `Resistance`, `Capacitance`, `Inductance`, `VoltageRating`,
+    /// `Tolerance` and `Dielectric` components. Run after `classify` so values without units can
+    /// be interpreted. Returns the number of values with a recognized quantity.
+    #[instrument(skip(self))]
+    pub fn parse_values(&mut self) -> usize {
+        crate::values::apply_parsed_values(&mut self.world)
+    }
+    
+    /// Set each pad's per-layer shapes from `KiCadClient::get_pad_shapes`, keyed by pad UUID and
+    /// layer, and give footprints a `Shape` (and matching `BoundingBox`) made of the union of
+    /// their pad shapes on all layers. Curves are approximated using the `Tessellation` resource.
+    /// Returns the number of footprints updated.
+    #[instrument(skip(self, pad_shapes), fields(pads = pad_shapes.len()))]
+    pub fn apply_pad_shapes(&mut self, pad_shapes: &HashMap<(String, PcbLayer), PolygonWithHoles>) -> usize {
+        let tessellation = *self.world.resource::<Tessellation>();
+        let mut by_pad: HashMap<&str, Vec<(PcbLayer, &PolygonWithHoles)>> = HashMap::new();
+        for ((id, layer), polygon) in pad_shapes {
+            by_pad.entry(id.as_str()).or_default().push((*layer, polygon));
+        }
+        
+        let mut query = self.world.query::<(Entity, &mut Pads)>();
+        let shapes: Vec<(Entity, Shape)> = query
+            .iter_mut(&mut self.world)
+            .filter_map(|(entity, mut pads)| {
+                let mut shape = Shape::default();
+                for pad in &mut pads.pads {
+                    let mut layers = by_pad.get(pad.id.as_str()).cloned().unwrap_or_default();
+                    layers.sort_by_key(|(layer, _)| *layer);
+                    pad.shapes = layers.into_iter()
+                        .map(|(layer, polygon)| (layer, polygon.to_shape(&tessellation)))
+                        .collect();
+                    for (_, pad_shape) in &pad.shapes {
+                        shape = shape.union(pad_shape);
+                    }
+                }
+                (!shape.is_empty()).then_some((entity, shape))
+            })
+            .collect();
+        
+        let count = shapes.len();
+        for (entity, shape) in shapes {
+            let mut entity = self.world.entity_mut(entity);
+            if let Some(bounds) = shape.bounding_box() {
+                entity.insert(bounds);
+            }
+            entity.insert(shape);
+        }
+        debug!("Applied pad shapes to {} footprints", count);
+        count
+    }
+    
+    /// Assemble the board outline from the Edge.Cuts shapes among `shapes` (as returned by
+    /// `KiCadClient::get_board_shapes`) and store it as the `BoardOutline` resource
+    #[instrument(skip(self, shapes))]
+    pub fn build_board_outline(&mut self, shapes: &[crate::client::BoardShapeData]) -> &BoardOutline {
+        let tessellation = *self.world.resource::<Tessellation>();
+        let edges: Vec<_> = shapes.iter()
+            .filter(|shape| shape.layer == PcbLayer::EdgeCuts)
+            .map(|shape| shape.graphic.clone())
+            .collect();
+        let outline = BoardOutline::from_graphics(&edges, &tessellation, DEFAULT_CHAINING_EPSILON);
+        self.world.insert_resource(outline);
+        self.world.resource::<BoardOutline>()
+    }
+    
+    /// Spawn track entities from `KiCadClient::get_tracks`, with their copper `Shape`. Tracks
+    /// are positioned at their start point. Returns the number spawned.
+    #[instrument(skip(self, tracks), fields(count = tracks.len()))]
+    pub fn spawn_tracks(&mut self, tracks: Vec<crate::client::TrackData>) -> usize {
+        let tessellation = *self.world.resource::<Tessellation>();
+        let count = tracks.len();
+        for data in tracks {
+            let track = Track { start: data.start, mid: data.mid, end: data.end, width: data.width };
+            let shape = track.to_shape(&tessellation);
+            let mut entity = self.world.spawn((
+                ComponentId { uuid: data.id },
+                Position::new(data.start, Angle::ZERO),
+                Layer { id: data.layer },
+                track,
+            ));
+            if let Some(bounds) = shape.bounding_box() {
+                entity.insert(bounds);
+            }
+            entity.insert(shape);
+            if let Some(name) = data.net {
+                entity.insert(Net { name });
+            }
+        }
+        debug!("Spawned {} tracks", count);
+        count
+    }
+    
+    /// Spawn via entities from `KiCadClient::get_vias`, with their copper `Shape`. Vias span
+    /// several layers, so they are not in the spatial index. Returns the number spawned.
+    #[instrument(skip(self, vias), fields(count = vias.len()))]
+    pub fn spawn_vias(&mut self, vias: Vec<crate::client::ViaData>) -> usize {
+        let tessellation = *self.world.resource::<Tessellation>();
+        let count = vias.len();
+        for data in vias {
+            let via = Via {
+                drill: data.drill,
+                diameter: data.diameter,
+                via_type: data.via_type,
+                start_layer: data.start_layer,
+                end_layer: data.end_layer,
+            };
+            let shape = via.to_shape(data.position, &tessellation);
+            let mut entity = self.world.spawn((
+                ComponentId { uuid: data.id },
+                Position::new(data.position, Angle::ZERO),
+                via,
+                shape,
+            ));
+            if let Some(name) = data.net {
+                entity.insert(Net { name });
+            }
+        }
+        debug!("Spawned {} vias", count);
+        count
+    }
+    
+    /// Spawn zone entities from `KiCadClient::get_zones`, with the outline as their `Shape`.
+    /// Like vias, zones can span several layers and are not in the spatial index. Returns the
+    /// number spawned.
+    #[instrument(skip(self, zones), fields(count = zones.len()))]
+    pub fn spawn_zones(&mut self, zones: Vec<crate::client::ZoneData>) -> usize {
+        let tessellation = *self.world.resource::<Tessellation>();
+        let count = zones.len();
+        for data in zones {
+            let shape = data.outline.to_shape(&tessellation);
+            let zone = Zone {
+                name: data.name,
+                kind: data.kind,
+                layers: data.layers,
+                outline: data.outline,
+                priority: data.priority,
+                filled: data.filled,
+            };
+            let mut entity = self.world.spawn((ComponentId { uuid: data.id }, zone));
+            if let Some(bounds) = shape.bounding_box() {
+                entity.insert(bounds);
+            }
+            entity.insert(shape);
+            if let Some(name) = data.net {
+                entity.insert(Net { name });
+            }
+        }
+        debug!("Spawned {} zones", count);
+        count
+    }
+    
+    /// Spawn entities for the silkscreen shapes among `shapes` (as returned by
+    /// `KiCadClient::get_board_shapes`), marked `Silkscreen`. Returns the number spawned.
+    #[instrument(skip(self, shapes))]
+    pub fn spawn_silkscreen(&mut self, shapes: &[crate::client::BoardShapeData]) -> usize {
+        let tessellation = *self.world.resource::<Tessellation>();
+        let mut count = 0;
+        for data in shapes.iter().filter(|shape| matches!(shape.layer, PcbLayer::FSilkS | PcbLayer::BSilkS)) {
+            let shape = data.graphic.to_shape(&tessellation);
+            let Some(bounds) = shape.bounding_box() else {
+                continue;
+            };
+            self.world.spawn((
+                ComponentId { uuid: data.id.clone() },
+                Position::new(bounds.center(), Angle::ZERO),
+                Layer { id: data.layer },
+                bounds,
+                shape,
+                Silkscreen,
+            ));
+            count += 1;
+        }
+        debug!("Spawned {} silkscreen shapes", count);
+        count
+    }
+    
+    /// Set the rules used by `run_drc`, keeping the board-wide minimums and taking netclass
+    /// values from the project (typically `KiCadClient::get_net_classes` and
+    /// `KiCadClient::get_net_class_for_nets`)
+    pub fn set_net_classes(&mut self, classes: Vec<NetClassRules>, by_net: HashMap<String, NetClassRules>) {
+        let mut rules = self.world.resource_mut::<DesignRules>();
+        if let Some(default) = classes.into_iter().find(|class| class.name == "Default") {
+            rules.default_class = default;
+        }
+        rules.net_classes = by_net;
+    }
+    
+    /// The rules used by `run_drc`, for adjusting board-wide minimums
+    pub fn design_rules_mut(&mut self) -> Mut<'_, DesignRules> {
+        self.world.resource_mut::<DesignRules>()
+    }
+    
+    /// The rules used by the placement checks in `run_drc`
+    pub fn placement_rules_mut(&mut self) -> Mut<'_, PlacementRules> {
+        self.world.resource_mut::<PlacementRules>()
+    }
+    
+    /// Decoupling limits used by the DRC schedule
+    pub fn decoupling_rules_mut(&mut self) -> Mut<'_, DecouplingRules> {
+        self.world.resource_mut::<DecouplingRules>()
+    }
+    
+    /// The DRC schedule, for adding custom rule systems
+    pub fn drc_schedule_mut(&mut self) -> &mut Schedule {
+        &mut self.drc
+    }
+    
+    /// Run every DRC rule, replacing the `Violation` entities of any previous run. Returns the
+    /// violations, most severe first.
+    #[instrument(skip(self))]
+    pub fn run_drc(&mut self) -> Vec<Violation> {
+        self.drc.run(&mut self.world);
+        let mut query = self.world.query::<&Violation>();
+        let mut violations: Vec<Violation> = query.iter(&self.world).cloned().collect();
+        violations.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.rule.cmp(&b.rule)));
+        info!("DRC found {} violations", violations.len());
+        violations
+    }
+    
+    /// Replace the known assembly variants; the board as drawn becomes active
+    pub fn set_variants(&mut self, variants: VariantOverrides) -> Result<()> {
+        self.world.insert_resource(variants);
+        self.select_variant(None)
+    }
+    
+    /// Apply an assembly variant, or the board as drawn for `None`, updating every footprint's
+    /// `EffectivePart` so that the BOM, placement list and statistics follow it
+    #[instrument(skip(self))]
+    pub fn select_variant(&mut self, name: Option<&str>) -> Result<()> {
+        self.world.resource_mut::<VariantOverrides>().select(name)?;
+        self.world
+            .run_system(self.variant_system)
+            .expect("variant system is registered in PcbWorld::new");
+        Ok(())
+    }
+    
+    /// Names of the known assembly variants
+    pub fn variant_names(&self) -> Vec<String> {
+        self.world.resource::<VariantOverrides>().variants.keys().cloned().collect()
+    }
+    
+    /// Compare this board (the older one) against `newer`
+    pub fn diff(&mut self, newer: &mut PcbWorld) -> BoardDiff {
+        BoardDiff::between(&mut self.world, &mut newer.world)
+    }
+    
+    /// Group the footprints into a bill of materials
+    pub fn bom(&mut self, options: &BomOptions) -> Bom {
+        generate_bom(&mut self.world, options)
+    }
+    
+    /// Build the pick-and-place list of the footprints
+    pub fn cpl(&mut self, options: &CplOptions) -> Result<Cpl> {
+        generate_cpl(&mut self.world, options)
+    }
+    
+    /// Save the board to `path`: JSON when it ends in `.json`, compact binary otherwise
+    #[instrument(skip(self, path), fields(path = %path.as_ref().display()))]
+    pub fn save(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let data = save_world(&mut self.world, SnapshotFormat::from_path(path))?;
+        std::fs::write(path, data).wrap_err_with(|| format!("Failed to write snapshot {}", path.display()))
+    }
+    
+    /// Load a board saved with `save`. Only built-in components are restored; use `restore` on
+    /// a world with registered user components to load those too.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let mut pcb = Self::new();
+        pcb.restore(path)?;
+        Ok(pcb)
+    }
+    
+    /// Add the entities of a snapshot to this world, returning how many were loaded
+    #[instrument(skip(self, path), fields(path = %path.as_ref().display()))]
+    pub fn restore(&mut self, path: impl AsRef<Path>) -> Result<usize> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).wrap_err_with(|| format!("Failed to read snapshot {}", path.display()))?;
+        let entities = load_world(&mut self.world, &data, SnapshotFormat::from_path(path))
+            .wrap_err_with(|| format!("Failed to load snapshot {}", path.display()))?;
+        self.component_count += entities.iter().filter(|&&entity| self.world.get::<ComponentInfo>(entity).is_some()).count();
+        Ok(entities.len())
+    }
+    
+    /// Include a user component in snapshots under `name`
+    pub fn register_snapshot_component<T: Component + Serialize + DeserializeOwned>(&mut self, name: &str) {
+        self.world.resource_mut::<SnapshotRegistry>().register::<T>(name);
+    }
+    
+    /// The board outline, if one has been built
+    pub fn board_outline(&self) -> Option<&BoardOutline> {
+        self.world.get_resource::<BoardOutline>()
+    }
+    
+    /// Bring the spatial index up to date with any position, layer or bounding box changes
+    #[instrument(skip(self))]
+    pub fn update_spatial_index(&mut self) {
+        self.world
+            .run_system(self.spatial_index_system)
+            .expect("spatial index system is registered in PcbWorld::new");
+    }
+    
+    /// The spatial index, updated with any changes since it was last used
+    pub fn spatial_index(&mut self) -> &SpatialIndex {
+        self.update_spatial_index();
+        self.world.resource::<SpatialIndex>()
+    }
+    
+    /// Set the user-visible layer names (typically from `KiCadClient::get_layer_names`)
+    pub fn set_layer_names(&mut self, names: LayerNames) {
+        self.world.insert_resource(names);
+    }
+    
+    /// Get the user-visible name of a layer
+    pub fn layer_name(&self, layer: PcbLayer) -> String {
+        self.world.resource::<LayerNames>().name(layer)
+    }
+    
+    /// Build schematic nets from the schematic wire, label and pin entities in the world
+    #[instrument(skip(self))]
+    pub fn extract_schematic_nets(&mut self) -> usize {
+        crate::netlist::extract_nets(&mut self.world).len()
+    }
+    
+    /// Compare the extracted schematic nets against the board's net list
+    #[instrument(skip(self, board_nets))]
+    pub fn compare_netlist(&mut self, board_nets: &[crate::client::NetData]) -> Vec<crate::netlist::NetDiscrepancy> {
+        crate::netlist::compare_with_board(&mut self.world, board_nets)
+    }
+    
     /// Get the number of components
     pub fn component_count(&self) -> usize {
         self.component_count
@@ -239,10 +584,10 @@ impl PcbWorld {
         // Count components by layer
         let mut component_query = self.world.query::<(&ComponentInfo, &Layer)>();
         for (_info, layer) in component_query.iter(&self.world) {
-            match layer.layer_name.as_str() {
-                "F.Cu" => stats.front_components += 1,
-                "B.Cu" => stats.back_components += 1,
-                _ => stats.other_layer_components += 1,
+            match layer.side() {
+                Some(Side::Front) => stats.front_components += 1,
+                Some(Side::Back) => stats.back_components += 1,
+                None => stats.other_layer_components += 1,
             }
         }
         
@@ -251,9 +596,10 @@ impl PcbWorld {
         stats.mounting_holes = mounting_hole_query.iter(&self.world).count();
         
         // Count special flags
-        let mut flag_query = self.world.query::<&ComponentFlags>();
-        for flags in flag_query.iter(&self.world) {
-            if flags.do_not_populate { stats.dnp_components += 1; }
+        // DNP follows the applied variant, if any
+        let mut flag_query = self.world.query::<(&ComponentFlags, Option<&EffectivePart>)>();
+        for (flags, part) in flag_query.iter(&self.world) {
+            if part.map_or(flags.do_not_populate, |part| part.dnp) { stats.dnp_components += 1; }
             if flags.exclude_from_bom { stats.exclude_bom_components += 1; }
             if flags.locked { stats.locked_components += 1; }
         }